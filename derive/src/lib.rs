@@ -0,0 +1,144 @@
+// Derive macros for cs6600
+//  - Std140 / Std430: lays out a struct the way GLSL lays out a `struct` in a std140/std430
+//    interface block, so it can be uploaded to uniform and storage buffers
+//  - UniformStruct: sets a GLSL `struct` uniform field by field
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Ident, Type};
+
+#[proc_macro_derive(Std140)]
+pub fn derive_std140(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let packing = quote!(::cs6600::interface_blocks::Std140);
+    layout(&input, packing)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+#[proc_macro_derive(Std430)]
+pub fn derive_std430(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let packing = quote!(::cs6600::interface_blocks::Std430);
+    layout(&input, packing)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+#[proc_macro_derive(UniformStruct)]
+pub fn derive_uniform_struct(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    uniform_struct(&input)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+// Implements `Layout<Packing>`: every field is placed at the next offset aligned to its base
+// alignment, and the struct is aligned to its most aligned field (and, in std140, to a vec4)
+fn layout(input: &DeriveInput, packing: TokenStream2) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (names, types) = named_fields(input)?;
+    let indices: Vec<usize> = (0..names.len()).collect();
+    let field_names: Vec<String> = names.iter().map(|name| name.to_string()).collect();
+    let layout = quote!(::cs6600::interface_blocks::layout);
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics #layout::Layout<#packing> for #name #type_generics #where_clause {
+            const ALIGNMENT: usize = #layout::struct_alignment::<#packing>(&[
+                #(<#types as #layout::Layout<#packing>>::ALIGNMENT),*
+            ]);
+            const SIZE: usize = #layout::struct_size(
+                [#((
+                    <#types as #layout::Layout<#packing>>::ALIGNMENT,
+                    <#types as #layout::Layout<#packing>>::SIZE,
+                )),*],
+                <Self as #layout::Layout<#packing>>::ALIGNMENT,
+            );
+            const FIELDS: &'static [&'static str] = &[#(#field_names),*];
+            const OFFSETS: &'static [usize] = &#layout::offsets([#((
+                <#types as #layout::Layout<#packing>>::ALIGNMENT,
+                <#types as #layout::Layout<#packing>>::SIZE,
+            )),*]);
+
+            fn write(&self, buffer: &mut [u8]) {
+                let offsets = <Self as #layout::Layout<#packing>>::OFFSETS;
+                #(
+                    #layout::Layout::<#packing>::write(&self.#names, &mut buffer[offsets[#indices]..]);
+                )*
+            }
+
+            fn read(buffer: &[u8]) -> Self {
+                let offsets = <Self as #layout::Layout<#packing>>::OFFSETS;
+                #name {
+                    #(
+                        #names: #layout::Layout::<#packing>::read(&buffer[offsets[#indices]..]),
+                    )*
+                }
+            }
+
+            fn member(path: &str) -> ::std::option::Option<(usize, usize)> {
+                if path.is_empty() {
+                    return ::std::option::Option::Some((0, <Self as #layout::Layout<#packing>>::SIZE));
+                }
+                let offsets = <Self as #layout::Layout<#packing>>::OFFSETS;
+                let (field, rest) = #layout::split_path(path);
+                match field {
+                    #(
+                        #field_names => <#types as #layout::Layout<#packing>>::member(rest)
+                            .map(|(offset, size)| (offsets[#indices] + offset, size)),
+                    )*
+                    _ => ::std::option::Option::None,
+                }
+            }
+        }
+    })
+}
+
+// Implements `UniformStruct`: a field `position` of a uniform `light` is set as `light.position`
+fn uniform_struct(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let (names, _) = named_fields(input)?;
+    let field_names = names.iter().map(|name| name.to_string());
+    let uniform = quote!(::cs6600::uniform);
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics #uniform::UniformStruct for #name #type_generics #where_clause {
+            fn set_fields(
+                &self,
+                program_id: u32,
+                name: &str,
+            ) -> ::std::result::Result<usize, #uniform::UniformError> {
+                let mut set = 0;
+                #(
+                    if #uniform::set_field(program_id, name, #field_names, &self.#names)? {
+                        set += 1;
+                    }
+                )*
+                Ok(set)
+            }
+        }
+    })
+}
+
+// Only structs with named fields map onto GLSL structs
+fn named_fields(input: &DeriveInput) -> syn::Result<(Vec<&Ident>, Vec<&Type>)> {
+    let error = || {
+        syn::Error::new_spanned(
+            &input.ident,
+            "can only be derived for structs with named fields",
+        )
+    };
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(error()),
+        },
+        _ => return Err(error()),
+    };
+    Ok(fields
+        .iter()
+        .filter_map(|field| field.ident.as_ref().map(|name| (name, &field.ty)))
+        .unzip())
+}
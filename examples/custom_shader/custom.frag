@@ -0,0 +1,13 @@
+#version 460 core
+
+// Magic uniform, seconds since the program started
+uniform float time;
+
+in vec3 normal;
+out vec4 color;
+
+void main() {
+    // Each face is coloured by the direction it faces, and pulses over time
+    vec3 base = normalize(normal) * 0.5 + 0.5;
+    color = vec4(base * (0.75 + 0.25 * sin(time)), 1.0);
+}
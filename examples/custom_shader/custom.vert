@@ -0,0 +1,17 @@
+#version 460 core
+
+// Vertices and normals in model space
+layout (location = 0) in vec3 vertices;
+layout (location = 1) in vec3 normals;
+// Per object model-world transforms, location = 3,4,5 reserved too
+layout (location = 2) in mat4 object_mw_transforms;
+
+// Set by the scene's camera every frame
+uniform mat4 view_projection_matrix;
+
+out vec3 normal;
+
+void main() {
+    gl_Position = view_projection_matrix * object_mw_transforms * vec4(vertices, 1.0);
+    normal = normals;
+}
@@ -0,0 +1,36 @@
+use cs6600::{
+    // Trait all GLPrograms conform to that allows the `.draw()` function
+    program::GLDraw,
+    // Convenience function for loading shader sources
+    shader::{load_shader, FragmentShader, VertexShader},
+    // Used to enable the automagical setting of common uniform variables
+    uniform::MagicUniform,
+    GLError,
+    GLProgram,
+    Mesh,
+    Shader,
+};
+use ultraviolet::mat::Mat4;
+
+fn main() -> Result<(), GLError> {
+    // Load shader sources from file
+    let vertex_source = load_shader("./examples/custom_shader/custom.vert")?;
+    let fragment_source = load_shader("./examples/custom_shader/custom.frag")?;
+
+    // Shaders are compiled in the window's context, so it comes first
+    let builder = GLProgram::new().use_default_window()?;
+    let vertex_shader = Shader::<VertexShader>::new(&vertex_source)?;
+    let fragment_shader = Shader::<FragmentShader>::new(&fragment_source)?;
+    let mut program = builder
+        .with_shaders(vertex_shader, fragment_shader, None)
+        .build()?
+        .enable_uniform(MagicUniform::TIME)?;
+
+    // Scene
+    program.attach_mesh(Mesh::parse("./examples/custom_shader/cube.obj")?)?;
+    program.create_object("cube", "cube", Mat4::identity());
+
+    // Render loop
+    while program.draw().is_ok() {}
+    Ok(())
+}
@@ -2,9 +2,11 @@ use std::process::Termination;
 
 // Library Error Types
 pub use crate::{
-    interface_blocks::InterfaceBlockError, program::mesh::MeshError,
-    program::scene_object::SceneObjectError, program::vao::VAOError, program::ProgramError,
-    shader::ShaderError, uniform::UniformError, window::WindowError,
+    interface_blocks::InterfaceBlockError, program::compute::ComputeError,
+    program::mesh::MeshError, program::pbr::PbrError, program::scene::SceneError,
+    program::scene_object::SceneObjectError, program::shadows::ShadowError, program::vao::VAOError,
+    program::ProgramError, render_graph::RenderGraphError, shader::ShaderError,
+    texture::TextureError, uniform::UniformError, window::WindowError,
 };
 // Make error logs, and shader source errors pretty and helpful
 use bat::PrettyPrinter;
@@ -20,6 +22,12 @@ pub enum GLError {
     VAO(VAOError),
     Mesh(MeshError),
     SceneObject(SceneObjectError),
+    Scene(SceneError),
+    Shadow(ShadowError),
+    Pbr(PbrError),
+    Texture(TextureError),
+    RenderGraph(RenderGraphError),
+    Compute(ComputeError),
     Other(GLUtilityError),
 }
 
@@ -94,6 +102,24 @@ impl std::fmt::Display for GLError {
             GLError::SceneObject(error) => {
                 write!(f, "GL Program SceneObject Error:\n{}", error.to_string())
             }
+            GLError::Scene(error) => {
+                write!(f, "GL Program Scene Error:\n{}", error)
+            }
+            GLError::Shadow(error) => {
+                write!(f, "GL Program Shadow Error:\n{}", error)
+            }
+            GLError::Pbr(error) => {
+                write!(f, "GL Program PBR Error:\n{}", error)
+            }
+            GLError::Texture(error) => {
+                write!(f, "GL Texture Error:\n{}", error)
+            }
+            GLError::RenderGraph(error) => {
+                write!(f, "GL Render Graph Error:\n{}", error)
+            }
+            GLError::Compute(error) => {
+                write!(f, "GL Compute Error:\n{}", error)
+            }
             GLError::Other(error) => {
                 write!(f, "GL Program Error:\n{}", error.to_string())
             }
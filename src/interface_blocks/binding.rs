@@ -0,0 +1,280 @@
+// Hands out the buffer binding points interface blocks read from. Binding points belong to the
+// context rather than to a program, so each window keeps one allocator for every program drawing
+// in it. Every block attached gets a binding point of its own, and programs only read from the
+// same one when the block is shared with `share_interface_block()`, e.g. the camera's matrices.
+// Each program holds a lease on the binding points of its blocks, and they're handed out again
+// once no program uses them.
+use super::{BlockType, InterfaceBlockError, Result};
+use crate::program::clusters::{CLUSTERS_BINDING, LIGHTS_BINDING, LIGHT_INDICES_BINDING};
+
+// OpenGL Types
+use gl::types::*;
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::rc::{Rc, Weak};
+
+#[derive(Debug, Default)]
+pub struct BindingPoints {
+    uniform: Rc<RefCell<Bindings>>,
+    storage: Rc<RefCell<Bindings>>,
+}
+
+impl BindingPoints {
+    pub(crate) fn new() -> Self {
+        let binding_points = BindingPoints::default();
+        // The built-in shaders' lights are bound directly, not through interface blocks
+        binding_points.storage.borrow_mut().reserved.extend([
+            LIGHTS_BINDING,
+            CLUSTERS_BINDING,
+            LIGHT_INDICES_BINDING,
+        ]);
+        binding_points
+    }
+
+    // A new binding point for the block `name`, kept until it's released. Fails if a block of
+    // the same name is already bound, which has to be shared instead.
+    pub fn allocate<B: BlockType>(&mut self, name: &str) -> Result<GLuint> {
+        let mut available = 0;
+        unsafe {
+            gl::GetIntegerv(B::MAX_BINDINGS, &mut available);
+        }
+        self.bindings::<B>()
+            .borrow_mut()
+            .allocate(name, available.max(0) as GLuint)
+    }
+
+    // Records a block is already bound to `binding_point`, e.g. a buffer shared with this context
+    pub fn claim<B: BlockType>(&mut self, name: &str, binding_point: GLuint) -> Result<()> {
+        self.bindings::<B>().borrow_mut().claim(name, binding_point)
+    }
+
+    // Frees the block's binding point to be handed out again, however many programs use it
+    pub fn release<B: BlockType>(&mut self, name: &str) {
+        self.bindings::<B>().borrow_mut().assigned.remove(name);
+    }
+
+    // Keeps a binding point from ever being handed out, e.g. one a shader hardcodes
+    pub fn reserve<B: BlockType>(&mut self, binding_point: GLuint) {
+        self.bindings::<B>()
+            .borrow_mut()
+            .reserved
+            .insert(binding_point);
+    }
+
+    pub fn get<B: BlockType>(&self, name: &str) -> Option<GLuint> {
+        self.bindings::<B>()
+            .borrow()
+            .assigned
+            .get(name)
+            .map(|assignment| assignment.binding_point)
+    }
+
+    // Same as `allocate()`, but the binding point is released when the lease is dropped
+    pub(crate) fn lease<B: BlockType>(&mut self, name: &str) -> Result<BindingLease> {
+        let binding_point = self.allocate::<B>(name)?;
+        Ok(BindingLease::new(self.bindings::<B>(), name, binding_point))
+    }
+
+    // Same as `claim()`, but the binding point is released when the lease is dropped (and no other
+    // lease on it is left)
+    pub(crate) fn lease_shared<B: BlockType>(
+        &mut self,
+        name: &str,
+        binding_point: GLuint,
+    ) -> Result<BindingLease> {
+        self.claim::<B>(name, binding_point)?;
+        Ok(BindingLease::new(self.bindings::<B>(), name, binding_point))
+    }
+
+    fn bindings<B: BlockType>(&self) -> &Rc<RefCell<Bindings>> {
+        match B::TARGET {
+            gl::UNIFORM_BUFFER => &self.uniform,
+            _ => &self.storage,
+        }
+    }
+}
+
+// A program's hold on the binding point of one of its blocks
+#[derive(Debug)]
+pub(crate) struct BindingLease {
+    bindings: Weak<RefCell<Bindings>>,
+    name: Rc<str>,
+    binding_point: GLuint,
+}
+
+impl BindingLease {
+    fn new(bindings: &Rc<RefCell<Bindings>>, name: &str, binding_point: GLuint) -> Self {
+        BindingLease {
+            bindings: Rc::downgrade(bindings),
+            name: Rc::from(name),
+            binding_point,
+        }
+    }
+
+    pub(crate) fn binding_point(&self) -> GLuint {
+        self.binding_point
+    }
+}
+
+impl Drop for BindingLease {
+    fn drop(&mut self) {
+        if let Some(bindings) = self.bindings.upgrade() {
+            bindings.borrow_mut().unuse(&self.name, self.binding_point);
+        }
+    }
+}
+
+// The binding points of one buffer target
+#[derive(Debug, Default)]
+struct Bindings {
+    assigned: HashMap<Rc<str>, Assignment>,
+    reserved: BTreeSet<GLuint>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Assignment {
+    binding_point: GLuint,
+    // Allocations and claims not yet released
+    users: usize,
+}
+
+impl Bindings {
+    fn allocate(&mut self, name: &str, available: GLuint) -> Result<GLuint> {
+        if let Some(assignment) = self.assigned.get(name) {
+            return Err(InterfaceBlockError::BlockAlreadyBound {
+                block: name.to_string(),
+                binding_point: assignment.binding_point,
+            });
+        }
+        // Blocks read from 0 unless told otherwise, so it's never handed out
+        let binding_point = (1..available)
+            .find(|binding_point| self.owner(*binding_point).is_none())
+            .ok_or_else(|| InterfaceBlockError::OutOfBindingPoints {
+                block: name.to_string(),
+                available,
+            })?;
+        let assignment = Assignment {
+            binding_point,
+            users: 1,
+        };
+        self.assigned.insert(Rc::from(name), assignment);
+        Ok(binding_point)
+    }
+
+    fn claim(&mut self, name: &str, binding_point: GLuint) -> Result<()> {
+        // Another buffer of the same name is bound elsewhere
+        if let Some(assignment) = self.assigned.get(name) {
+            if assignment.binding_point != binding_point {
+                return Err(InterfaceBlockError::BlockAlreadyBound {
+                    block: name.to_string(),
+                    binding_point: assignment.binding_point,
+                });
+            }
+        }
+        match self.owner(binding_point) {
+            Some(owner) if owner != name => Err(InterfaceBlockError::BindingPointTaken {
+                block: name.to_string(),
+                binding_point,
+                owner: owner.to_string(),
+            }),
+            _ => {
+                self.assigned
+                    .entry(Rc::from(name))
+                    .or_insert(Assignment {
+                        binding_point,
+                        users: 0,
+                    })
+                    .users += 1;
+                Ok(())
+            }
+        }
+    }
+
+    // Undoes one allocation or claim, freeing the binding point once nothing uses it
+    fn unuse(&mut self, name: &str, binding_point: GLuint) {
+        let Some(assignment) = self.assigned.get_mut(name) else {
+            return;
+        };
+        // Released by hand, and since handed out again
+        if assignment.binding_point != binding_point {
+            return;
+        }
+        assignment.users = assignment.users.saturating_sub(1);
+        if assignment.users == 0 {
+            self.assigned.remove(name);
+        }
+    }
+
+    // Who is using a binding point
+    fn owner(&self, binding_point: GLuint) -> Option<&str> {
+        match self.reserved.contains(&binding_point) {
+            true => Some("a built-in shader"),
+            false => self
+                .assigned
+                .iter()
+                .find(|(_, assignment)| assignment.binding_point == binding_point)
+                .map(|(name, _)| name.as_ref()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_keep_their_binding_point() {
+        let mut bindings = Bindings::default();
+        bindings.reserved.insert(2);
+        let camera = bindings.allocate("Camera", 8).unwrap();
+        let lights = bindings.allocate("Lights", 8).unwrap();
+        let material = bindings.allocate("Material", 8).unwrap();
+        assert_eq!((camera, lights, material), (1, 3, 4));
+        // A second buffer of the same name has to be shared instead
+        assert!(matches!(
+            bindings.allocate("Camera", 8),
+            Err(InterfaceBlockError::BlockAlreadyBound {
+                binding_point: 1,
+                ..
+            })
+        ));
+        assert!(bindings.claim("Camera", camera).is_ok());
+        assert!(matches!(
+            bindings.claim("Camera", material),
+            Err(InterfaceBlockError::BlockAlreadyBound { .. })
+        ));
+    }
+
+    #[test]
+    fn running_out_and_conflicts() {
+        let mut bindings = Bindings::default();
+        bindings.allocate("Camera", 2).unwrap();
+        assert!(matches!(
+            bindings.allocate("Lights", 2),
+            Err(InterfaceBlockError::OutOfBindingPoints { available: 2, .. })
+        ));
+
+        assert!(bindings.claim("Camera", 1).is_ok());
+        assert!(matches!(
+            bindings.claim("Lights", 1),
+            Err(InterfaceBlockError::BindingPointTaken { .. })
+        ));
+        bindings.assigned.remove("Camera");
+        assert!(bindings.claim("Lights", 1).is_ok());
+    }
+
+    #[test]
+    fn leases_release_their_binding_point() {
+        let bindings = Rc::new(RefCell::new(Bindings::default()));
+        let camera = bindings.borrow_mut().allocate("Camera", 3).unwrap();
+        let owner = BindingLease::new(&bindings, "Camera", camera);
+        bindings.borrow_mut().claim("Camera", camera).unwrap();
+        let sharer = BindingLease::new(&bindings, "Camera", camera);
+
+        // Still used by the program it was shared with
+        drop(owner);
+        assert!(bindings.borrow_mut().allocate("Lights", 2).is_err());
+        drop(sharer);
+        assert_eq!(bindings.borrow_mut().allocate("Lights", 2).unwrap(), 1);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::GLUtilityError;
+use gl::types::{GLint, GLuint};
 use std::ffi::CString;
 
 // Error type for Uniforms
@@ -8,6 +9,56 @@ pub enum InterfaceBlockError {
     MatrixConversion((u8, u8)),
     SettingUniformValue(String),
     CouldNotFindUniformIndex(CString),
+    // The driver's layout of a block disagrees with the layout of the values uploaded to it
+    SizeMismatch {
+        block: String,
+        driver: GLint,
+        rust: usize,
+    },
+    LayoutMismatch {
+        block: String,
+        member: String,
+        driver: GLint,
+        rust: usize,
+    },
+    StrideMismatch {
+        block: String,
+        member: String,
+        driver: GLint,
+        rust: usize,
+    },
+    // A member the driver reports that the values uploaded to the block don't have
+    MissingMember {
+        block: String,
+        member: String,
+    },
+    // Two programs packed the same block differently, so they can't share its buffer
+    PackedLayoutDiffers {
+        block: String,
+    },
+    // Writing to, or reading from, past the end of a buffer
+    OutOfRange {
+        block: String,
+        first: usize,
+        count: usize,
+        length: usize,
+    },
+    MapBuffer(String),
+    // Every binding point of the buffer target is in use
+    OutOfBindingPoints {
+        block: String,
+        available: GLuint,
+    },
+    BindingPointTaken {
+        block: String,
+        binding_point: GLuint,
+        owner: String,
+    },
+    // A different buffer was attached under the same block name
+    BlockAlreadyBound {
+        block: String,
+        binding_point: GLuint,
+    },
     Other(GLUtilityError),
 }
 
@@ -38,6 +89,105 @@ impl std::fmt::Display for InterfaceBlockError {
                     name.to_string_lossy()
                 )
             }
+            InterfaceBlockError::SizeMismatch {
+                block,
+                driver,
+                rust,
+            } => {
+                write!(
+                    f,
+                    "Interface block '{}' is {} bytes on the GPU, but the values uploaded to it are {} bytes.",
+                    block, driver, rust
+                )
+            }
+            InterfaceBlockError::LayoutMismatch {
+                block,
+                member,
+                driver,
+                rust,
+            } => {
+                write!(
+                    f,
+                    "Member '{}' of interface block '{}' is at byte {} on the GPU, but at byte {} in the values uploaded to it.",
+                    member, block, driver, rust
+                )
+            }
+            InterfaceBlockError::StrideMismatch {
+                block,
+                member,
+                driver,
+                rust,
+            } => {
+                write!(
+                    f,
+                    "Elements of '{}' in interface block '{}' are {} bytes apart on the GPU, but {} bytes apart in the values uploaded to it.",
+                    member, block, driver, rust
+                )
+            }
+            InterfaceBlockError::MissingMember { block, member } => {
+                write!(
+                    f,
+                    "Member '{}' of interface block '{}' isn't in the values uploaded to it.",
+                    member, block
+                )
+            }
+            InterfaceBlockError::PackedLayoutDiffers { block } => {
+                write!(
+                    f,
+                    "Interface block '{}' is laid out differently in each program, so they can't share a buffer.",
+                    block
+                )
+            }
+            InterfaceBlockError::OutOfRange {
+                block,
+                first,
+                count,
+                length,
+            } => {
+                write!(
+                    f,
+                    "Cannot access values {}..{} of interface block '{}', which only holds {} values.",
+                    first,
+                    first + count,
+                    block,
+                    length
+                )
+            }
+            InterfaceBlockError::MapBuffer(block) => {
+                write!(
+                    f,
+                    "Failed to map the buffer of interface block '{}' into memory.",
+                    block
+                )
+            }
+            InterfaceBlockError::OutOfBindingPoints { block, available } => {
+                write!(
+                    f,
+                    "Cannot bind interface block '{}', all {} binding points are in use.",
+                    block, available
+                )
+            }
+            InterfaceBlockError::BindingPointTaken {
+                block,
+                binding_point,
+                owner,
+            } => {
+                write!(
+                    f,
+                    "Cannot bind interface block '{}' to binding point {}, it's used by '{}'.",
+                    block, binding_point, owner
+                )
+            }
+            InterfaceBlockError::BlockAlreadyBound {
+                block,
+                binding_point,
+            } => {
+                write!(
+                    f,
+                    "A buffer for interface block '{}' is already bound to binding point {}, share that block instead of attaching another.",
+                    block, binding_point
+                )
+            }
             InterfaceBlockError::Other(error) => {
                 write!(f, "{}", error)
             }
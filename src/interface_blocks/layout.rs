@@ -0,0 +1,479 @@
+// How values are laid out in the memory backing an interface block. GLSL pads block members to
+// their base alignment, so e.g. a `vec3` followed by a `float` packs the float into the vec3's 4th
+// component, but an array of floats in std140 gives every float 16 bytes. Rust's layout of the same
+// struct rarely agrees, so values are written into a byte buffer member by member instead.
+//
+// Structs get their layout with `#[derive(Std140)]` and `#[derive(Std430)]`.
+use super::{Packed, Shared, Std140, Std430};
+use crate::uniform::matrix::*;
+
+// Linear Algebra Types
+use ultraviolet::{
+    DMat2, DMat3, DMat4, DVec2, DVec3, DVec4, IVec2, IVec3, IVec4, Mat2, Mat3, Mat4, UVec2, UVec3,
+    UVec4, Vec2, Vec3, Vec4,
+};
+
+// The packing rules of a block layout which can be computed ahead of time
+pub trait Packing {
+    // Arrays and structs are aligned to at least this many bytes (a vec4 in std140)
+    const MINIMUM_AGGREGATE_ALIGNMENT: usize;
+}
+
+impl Packing for Std140 {
+    const MINIMUM_AGGREGATE_ALIGNMENT: usize = 16;
+}
+
+impl Packing for Std430 {
+    const MINIMUM_AGGREGATE_ALIGNMENT: usize = 1;
+}
+
+// The layout a block is declared with, and the packing rules values uploaded to it follow. How
+// `shared` and `packed` blocks are laid out is up to the driver (a packed block may even drop the
+// members no shader uses), so their values are laid out by std140 first, then each member is moved
+// to the offset the driver reports for it when the block is attached.
+pub trait BlockLayout {
+    type Rules: Packing;
+    // Whether the driver decides where members go, rather than `Rules`
+    const DRIVER_CHOSEN: bool = false;
+}
+
+impl BlockLayout for Std140 {
+    type Rules = Std140;
+}
+
+impl BlockLayout for Std430 {
+    type Rules = Std430;
+}
+
+impl BlockLayout for Shared {
+    type Rules = Std140;
+    const DRIVER_CHOSEN: bool = true;
+}
+
+impl BlockLayout for Packed {
+    type Rules = Std140;
+    const DRIVER_CHOSEN: bool = true;
+}
+
+// Implemented by types which can be a member of a block with the given packing
+pub trait Layout<P> {
+    // Base alignment in bytes, every value starts at a multiple of it
+    const ALIGNMENT: usize;
+    // Bytes the value occupies, not counting padding after it (a vec3 is 12, not 16)
+    const SIZE: usize;
+    // Names and offsets of each field of a struct, in declaration order
+    const FIELDS: &'static [&'static str] = &[];
+    const OFFSETS: &'static [usize] = &[];
+
+    // Writes the value into the start of `buffer`, which is at least SIZE bytes long
+    fn write(&self, buffer: &mut [u8]);
+    // Reads a value back out of the start of `buffer`
+    fn read(buffer: &[u8]) -> Self;
+
+    // Where a member of the value starts, and how many bytes it occupies, e.g. "color" or
+    // "lights[2].color" of a struct, "[2]" of an array, and "" for the value itself
+    fn member(path: &str) -> Option<(usize, usize)> {
+        path.is_empty().then_some((0, Self::SIZE))
+    }
+}
+
+// The first field or array index of a member's path, and the rest of it, e.g. "lights" and
+// "[2].color" for "lights[2].color", or "[2]" and "color" for "[2].color"
+pub fn split_path(path: &str) -> (&str, &str) {
+    let end = match path.strip_prefix('[') {
+        Some(index) => index.find(']').map_or(path.len(), |end| end + 2),
+        None => path.find(['.', '[']).unwrap_or(path.len()),
+    };
+    let (first, rest) = path.split_at(end);
+    (first, rest.strip_prefix('.').unwrap_or(rest))
+}
+
+pub const fn round_up(value: usize, alignment: usize) -> usize {
+    match alignment {
+        0 => value,
+        _ => value.div_ceil(alignment) * alignment,
+    }
+}
+
+// Distance between consecutive elements of an array of T
+pub const fn array_stride<P, T>() -> usize
+where
+    P: Packing,
+    T: Layout<P>,
+{
+    round_up(T::SIZE, array_alignment::<P>(T::ALIGNMENT))
+}
+
+const fn array_alignment<P: Packing>(element_alignment: usize) -> usize {
+    max(element_alignment, P::MINIMUM_AGGREGATE_ALIGNMENT)
+}
+
+// Alignment of a struct with members of the given alignments
+pub const fn struct_alignment<P: Packing>(members: &[usize]) -> usize {
+    let mut alignment = P::MINIMUM_AGGREGATE_ALIGNMENT;
+    let mut index = 0;
+    while index < members.len() {
+        alignment = max(alignment, members[index]);
+        index += 1;
+    }
+    alignment
+}
+
+// Offsets of struct members with the given (alignment, size)s
+pub const fn offsets<const N: usize>(members: [(usize, usize); N]) -> [usize; N] {
+    let mut offsets = [0; N];
+    let mut end = 0;
+    let mut index = 0;
+    while index < N {
+        let (alignment, size) = members[index];
+        offsets[index] = round_up(end, alignment);
+        end = offsets[index] + size;
+        index += 1;
+    }
+    offsets
+}
+
+// Size of a struct with members of the given (alignment, size)s, padded to its alignment
+pub const fn struct_size<const N: usize>(members: [(usize, usize); N], alignment: usize) -> usize {
+    let end = match N {
+        0 => 0,
+        _ => offsets(members)[N - 1] + members[N - 1].1,
+    };
+    round_up(end, alignment)
+}
+
+const fn max(a: usize, b: usize) -> usize {
+    if a > b {
+        a
+    } else {
+        b
+    }
+}
+
+// Lays out `values` as an array, which is how a block's contents are uploaded
+pub fn to_bytes<P, T>(values: &[T]) -> Vec<u8>
+where
+    P: Packing,
+    T: Layout<P>,
+{
+    let stride = array_stride::<P, T>();
+    let mut buffer = vec![0; stride * values.len()];
+    for (index, value) in values.iter().enumerate() {
+        value.write(&mut buffer[index * stride..]);
+    }
+    buffer
+}
+
+// Reads `count` values back out of an array laid out by `to_bytes()`
+pub fn from_bytes<P, T>(bytes: &[u8], count: usize) -> Vec<T>
+where
+    P: Packing,
+    T: Layout<P>,
+{
+    let stride = array_stride::<P, T>();
+    (0..count)
+        .map(|index| T::read(&bytes[index * stride..]))
+        .collect()
+}
+
+// The bytes of a value with no padding in it
+fn bytes_of<T: Copy>(value: &T) -> &[u8] {
+    unsafe { std::slice::from_raw_parts(value as *const T as *const u8, std::mem::size_of::<T>()) }
+}
+
+// ...and back
+fn from_bytes_of<T: Copy>(bytes: &[u8]) -> T {
+    let bytes = &bytes[..std::mem::size_of::<T>()];
+    unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const T) }
+}
+
+////////////
+// ARRAYS //
+////////////
+impl<P, T, const N: usize> Layout<P> for [T; N]
+where
+    P: Packing,
+    T: Layout<P>,
+{
+    const ALIGNMENT: usize = array_alignment::<P>(T::ALIGNMENT);
+    const SIZE: usize = array_stride::<P, T>() * N;
+
+    fn write(&self, buffer: &mut [u8]) {
+        let stride = array_stride::<P, T>();
+        for (index, value) in self.iter().enumerate() {
+            value.write(&mut buffer[index * stride..]);
+        }
+    }
+
+    fn read(buffer: &[u8]) -> Self {
+        let stride = array_stride::<P, T>();
+        std::array::from_fn(|index| T::read(&buffer[index * stride..]))
+    }
+
+    fn member(path: &str) -> Option<(usize, usize)> {
+        if path.is_empty() {
+            return Some((0, <Self as Layout<P>>::SIZE));
+        }
+        let (index, rest) = split_path(path);
+        let index: usize = index.strip_prefix('[')?.strip_suffix(']')?.parse().ok()?;
+        if index >= N {
+            return None;
+        }
+        let (offset, size) = T::member(rest)?;
+        Some((index * array_stride::<P, T>() + offset, size))
+    }
+}
+
+// Scalars, and vectors of them, whose memory is already laid out the way GLSL expects
+macro_rules! plain {
+    ($type:ty, $alignment:expr) => {
+        impl<P: Packing> Layout<P> for $type {
+            const ALIGNMENT: usize = $alignment;
+            const SIZE: usize = std::mem::size_of::<$type>();
+
+            fn write(&self, buffer: &mut [u8]) {
+                buffer[..std::mem::size_of::<$type>()].copy_from_slice(bytes_of(self));
+            }
+
+            fn read(buffer: &[u8]) -> Self {
+                from_bytes_of(buffer)
+            }
+        }
+    };
+}
+
+// Matrices are stored as arrays of column vectors
+macro_rules! matrix {
+    ($type:ty, $component:ty, $columns:literal, $rows:literal) => {
+        impl<P: Packing> Layout<P> for $type {
+            const ALIGNMENT: usize = array_alignment::<P>(vector_alignment::<$component>($rows));
+            const SIZE: usize = round_up(
+                $rows * std::mem::size_of::<$component>(),
+                <Self as Layout<P>>::ALIGNMENT,
+            ) * $columns;
+
+            fn write(&self, buffer: &mut [u8]) {
+                let stride = <Self as Layout<P>>::SIZE / $columns;
+                for (index, column) in self.cols.iter().enumerate() {
+                    let column = bytes_of(column);
+                    buffer[index * stride..index * stride + column.len()].copy_from_slice(column);
+                }
+            }
+
+            fn read(buffer: &[u8]) -> Self {
+                // Gathers the (padded) columns back together
+                let stride = <Self as Layout<P>>::SIZE / $columns;
+                let column = $rows * std::mem::size_of::<$component>();
+                let bytes: Vec<u8> = (0..$columns)
+                    .flat_map(|index| &buffer[index * stride..index * stride + column])
+                    .copied()
+                    .collect();
+                from_bytes_of(&bytes)
+            }
+        }
+    };
+}
+
+// A vec2 is aligned to 2 components, vec3s and vec4s to 4
+const fn vector_alignment<Component>(components: usize) -> usize {
+    let components = match components {
+        1 | 2 => components,
+        _ => 4,
+    };
+    components * std::mem::size_of::<Component>()
+}
+
+/////////////
+// SCALARS //
+/////////////
+plain!(f32, 4);
+plain!(f64, 8);
+plain!(i32, 4);
+plain!(u32, 4);
+
+// GLSL bools are 4 bytes
+impl<P: Packing> Layout<P> for bool {
+    const ALIGNMENT: usize = 4;
+    const SIZE: usize = 4;
+
+    fn write(&self, buffer: &mut [u8]) {
+        Layout::<P>::write(&(*self as u32), buffer)
+    }
+
+    fn read(buffer: &[u8]) -> Self {
+        <u32 as Layout<P>>::read(buffer) != 0
+    }
+}
+
+/////////////
+// VECTORS //
+/////////////
+plain!(Vec2, vector_alignment::<f32>(2));
+plain!(Vec3, vector_alignment::<f32>(3));
+plain!(Vec4, vector_alignment::<f32>(4));
+plain!(DVec2, vector_alignment::<f64>(2));
+plain!(DVec3, vector_alignment::<f64>(3));
+plain!(DVec4, vector_alignment::<f64>(4));
+plain!(IVec2, vector_alignment::<i32>(2));
+plain!(IVec3, vector_alignment::<i32>(3));
+plain!(IVec4, vector_alignment::<i32>(4));
+plain!(UVec2, vector_alignment::<u32>(2));
+plain!(UVec3, vector_alignment::<u32>(3));
+plain!(UVec4, vector_alignment::<u32>(4));
+
+//////////////
+// MATRICES //
+//////////////
+matrix!(Mat2, f32, 2, 2);
+matrix!(Mat3, f32, 3, 3);
+matrix!(Mat4, f32, 4, 4);
+matrix!(Matrix2x3, f32, 2, 3);
+matrix!(Matrix2x4, f32, 2, 4);
+matrix!(Matrix3x2, f32, 3, 2);
+matrix!(Matrix3x4, f32, 3, 4);
+matrix!(Matrix4x2, f32, 4, 2);
+matrix!(Matrix4x3, f32, 4, 3);
+matrix!(DMat2, f64, 2, 2);
+matrix!(DMat3, f64, 3, 3);
+matrix!(DMat4, f64, 4, 4);
+matrix!(DMatrix2x3, f64, 2, 3);
+matrix!(DMatrix2x4, f64, 2, 4);
+matrix!(DMatrix3x2, f64, 3, 2);
+matrix!(DMatrix3x4, f64, 3, 4);
+matrix!(DMatrix4x2, f64, 4, 2);
+matrix!(DMatrix4x3, f64, 4, 3);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interface_blocks::{Std140, Std430};
+
+    // The example block from the ARB_uniform_buffer_object specification:
+    //
+    // struct F { int d; bvec2 e; };
+    // struct O { uvec3 j; vec2 k; float l[2]; vec2 m; mat3 n[2]; };
+    // uniform Example { float a; vec2 b; vec3 c; F f; float g; float h[2]; mat2x3 i; O o[2]; };
+    #[derive(Std140, Std430)]
+    struct F {
+        d: i32,
+        e: UVec2,
+    }
+
+    #[derive(Std140, Std430)]
+    struct O {
+        j: UVec3,
+        k: Vec2,
+        l: [f32; 2],
+        m: Vec2,
+        n: [Mat3; 2],
+    }
+
+    #[derive(Std140, Std430)]
+    struct Example {
+        a: f32,
+        b: Vec2,
+        c: Vec3,
+        f: F,
+        g: f32,
+        h: [f32; 2],
+        i: Matrix2x3,
+        o: [O; 2],
+    }
+
+    #[test]
+    fn std140_offsets() {
+        assert_eq!(<F as Layout<Std140>>::OFFSETS, &[0, 8]);
+        assert_eq!(<F as Layout<Std140>>::SIZE, 16);
+        assert_eq!(<O as Layout<Std140>>::OFFSETS, &[0, 16, 32, 64, 80]);
+        assert_eq!(<O as Layout<Std140>>::SIZE, 176);
+        assert_eq!(
+            <Example as Layout<Std140>>::OFFSETS,
+            &[0, 8, 16, 32, 48, 64, 96, 128]
+        );
+        assert_eq!(<Example as Layout<Std140>>::SIZE, 480);
+        assert_eq!(<Example as Layout<Std140>>::member("h[1]"), Some((80, 4)));
+        assert_eq!(
+            <Example as Layout<Std140>>::member("o[1].n[1]"),
+            Some((432, 48))
+        );
+        assert_eq!(<Example as Layout<Std140>>::member("o[2]"), None);
+        assert_eq!(<Example as Layout<Std140>>::member("p"), None);
+    }
+
+    #[test]
+    fn std430_offsets() {
+        assert_eq!(<F as Layout<Std430>>::OFFSETS, &[0, 8]);
+        assert_eq!(<F as Layout<Std430>>::SIZE, 16);
+        assert_eq!(<O as Layout<Std430>>::OFFSETS, &[0, 16, 24, 32, 48]);
+        assert_eq!(<O as Layout<Std430>>::SIZE, 144);
+        assert_eq!(
+            <Example as Layout<Std430>>::OFFSETS,
+            &[0, 8, 16, 32, 48, 52, 64, 96]
+        );
+        assert_eq!(<Example as Layout<Std430>>::SIZE, 384);
+    }
+
+    #[test]
+    fn array_strides() {
+        assert_eq!(array_stride::<Std140, f32>(), 16);
+        assert_eq!(array_stride::<Std430, f32>(), 4);
+        assert_eq!(array_stride::<Std140, Vec3>(), 16);
+        assert_eq!(array_stride::<Std430, Vec3>(), 16);
+        assert_eq!(array_stride::<Std140, Mat2>(), 32);
+        assert_eq!(array_stride::<Std430, Mat2>(), 16);
+    }
+
+    #[test]
+    fn vec3_packs_a_float_after_it() {
+        #[derive(Std140)]
+        struct Light {
+            position: Vec3,
+            intensity: f32,
+        }
+
+        let light = Light {
+            position: Vec3::new(1.0, 2.0, 3.0),
+            intensity: 4.0,
+        };
+        let bytes = to_bytes::<Std140, Light>(&[light]);
+        let floats: Vec<f32> = bytes
+            .chunks(4)
+            .map(|chunk| f32::from_ne_bytes(chunk.try_into().unwrap()))
+            .collect();
+        assert_eq!(floats, vec![1.0, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn values_read_back_as_written() {
+        #[derive(Debug, Clone, Copy, PartialEq, Std140, Std430)]
+        struct Particle {
+            position: Vec3,
+            alive: bool,
+            transform: Mat3,
+            history: [Vec2; 3],
+        }
+
+        let particles: Vec<Particle> = (0..4)
+            .map(|index| Particle {
+                position: Vec3::new(index as f32, 1.0, 2.0),
+                alive: index % 2 == 0,
+                transform: Mat3::identity() * index as f32,
+                history: [Vec2::new(index as f32, 0.0); 3],
+            })
+            .collect();
+        let bytes = to_bytes::<Std140, Particle>(&particles);
+        assert_eq!(from_bytes::<Std140, Particle>(&bytes, 4), particles);
+        let bytes = to_bytes::<Std430, Particle>(&particles);
+        assert_eq!(from_bytes::<Std430, Particle>(&bytes, 4), particles);
+    }
+
+    #[test]
+    fn std140_matrix_columns_are_padded() {
+        let bytes = to_bytes::<Std140, Mat2>(&[Mat2::identity()]);
+        let floats: Vec<f32> = bytes
+            .chunks(4)
+            .map(|chunk| f32::from_ne_bytes(chunk.try_into().unwrap()))
+            .collect();
+        assert_eq!(floats, vec![1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+    }
+}
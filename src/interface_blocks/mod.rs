@@ -2,11 +2,34 @@
 pub mod error;
 pub use error::InterfaceBlockError;
 type Result<T> = std::result::Result<T, InterfaceBlockError>;
+// Laying values out the way GLSL expects them
+pub mod layout;
+pub use layout::{BlockLayout, Layout, Packing};
+// Checking the driver agrees with the layout, or following it where it decides
+mod verify;
+use verify::DriverLayout;
+// Shader Storage Buffer Objects
+mod storage;
+pub use storage::ShaderStorageBlock;
+// Handing out binding points
+pub mod binding;
+pub(crate) use binding::BindingLease;
+pub use binding::BindingPoints;
+// Updating buffers without stalling
+pub mod streaming;
+use streaming::Stream;
+pub use streaming::Streaming;
+// #[derive(Std140)] and #[derive(Std430)]
+pub use cs6600_derive::{Std140, Std430};
+
+use crate::program::reflection;
 
 // OpenGL Types
 use gl::types::*;
 // Used for defining arrays of floats, vectors, and matrices
-use std::ffi::CString;
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::ffi::{CStr, CString};
 use std::marker::PhantomData;
 use std::rc::Rc;
 use std::vec::Vec;
@@ -19,15 +42,60 @@ pub struct Unattached<Value> {
     value: Vec<Value>,
 }
 pub struct Attached {
-    buffer_id: GLuint,
-    block_index: GLuint,
     binding_point: GLuint,
+    // Number of values in the buffer
+    length: Cell<usize>,
+    // The buffer, and how it's updated
+    stream: RefCell<Stream>,
+    // Where the driver put the members of a shared or packed block
+    driver_layout: Option<DriverLayout>,
 }
 
 // Interface Block Buffer Types
 pub struct Uniform;
 pub struct Shader;
 
+// How each type of block is found in a program, and bound to a buffer
+pub trait BlockType: 'static {
+    // The buffer target, e.g. GL_UNIFORM_BUFFER
+    const TARGET: GLenum;
+    // The program interface the blocks are reflected through
+    const INTERFACE: GLenum;
+    // How many binding points the context has for the target
+    const MAX_BINDINGS: GLenum;
+    // Hint of how the buffer's contents are used
+    const USAGE: GLenum;
+    // Tells the program which binding point a block reads from
+    fn block_binding(program_id: GLuint, block_index: GLuint, binding_point: GLuint);
+}
+
+impl BlockType for Uniform {
+    const TARGET: GLenum = gl::UNIFORM_BUFFER;
+    const INTERFACE: GLenum = gl::UNIFORM_BLOCK;
+    const MAX_BINDINGS: GLenum = gl::MAX_UNIFORM_BUFFER_BINDINGS;
+    const USAGE: GLenum = gl::DYNAMIC_DRAW;
+
+    fn block_binding(program_id: GLuint, block_index: GLuint, binding_point: GLuint) {
+        unsafe {
+            gl::UniformBlockBinding(program_id, block_index, binding_point);
+        }
+    }
+}
+
+impl BlockType for Shader {
+    const TARGET: GLenum = gl::SHADER_STORAGE_BUFFER;
+    const INTERFACE: GLenum = gl::SHADER_STORAGE_BLOCK;
+    const MAX_BINDINGS: GLenum = gl::MAX_SHADER_STORAGE_BUFFER_BINDINGS;
+    // Shaders write to them, and we read them back
+    const USAGE: GLenum = gl::DYNAMIC_READ;
+
+    fn block_binding(program_id: GLuint, block_index: GLuint, binding_point: GLuint) {
+        unsafe {
+            gl::ShaderStorageBlockBinding(program_id, block_index, binding_point);
+        }
+    }
+}
+
 // Interface Block Buffer Packing Layout Types
 pub struct Packed;
 pub struct Shared;
@@ -52,6 +120,42 @@ impl UniformBufferBlock {
     ) -> Result<InterfaceBlock<Uniform, Std140, Value, Unattached<Value>>>
     where
         S: AsRef<str>,
+        Value: Layout<Std140>,
+    {
+        UniformBufferBlock::with_layout(name, value)
+    }
+
+    // The driver decides the layout of shared and packed blocks, which is checked when attached
+    pub fn new_shared<S, Value>(
+        name: S,
+        value: Vec<Value>,
+    ) -> Result<InterfaceBlock<Uniform, Shared, Value, Unattached<Value>>>
+    where
+        S: AsRef<str>,
+        Value: Layout<Std140>,
+    {
+        UniformBufferBlock::with_layout(name, value)
+    }
+
+    pub fn new_packed<S, Value>(
+        name: S,
+        value: Vec<Value>,
+    ) -> Result<InterfaceBlock<Uniform, Packed, Value, Unattached<Value>>>
+    where
+        S: AsRef<str>,
+        Value: Layout<Std140>,
+    {
+        UniformBufferBlock::with_layout(name, value)
+    }
+
+    pub fn with_layout<T, S, Value>(
+        name: S,
+        value: Vec<Value>,
+    ) -> Result<InterfaceBlock<Uniform, T, Value, Unattached<Value>>>
+    where
+        T: BlockLayout,
+        S: AsRef<str>,
+        Value: Layout<T::Rules>,
     {
         // Attempt CString conversion
         let name = CString::new(name.as_ref()).map_err(|_| {
@@ -71,53 +175,41 @@ impl UniformBufferBlock {
     }
 }
 
-// Only std140 rep, only uniform for the current implementation
-impl<T, Value> InterfaceBlock<Uniform, T, Value, Unattached<Value>> {
+impl<B, T, Value> InterfaceBlock<B, T, Value, Unattached<Value>>
+where
+    B: BlockType,
+    T: BlockLayout,
+    Value: Layout<T::Rules>,
+{
     pub(crate) fn attach(
         self,
         program_id: GLuint,
         binding_point: GLuint,
-    ) -> Result<InterfaceBlock<Uniform, T, Value, Attached>> {
-        unsafe {
-            gl::UseProgram(program_id);
-        }
+    ) -> Result<InterfaceBlock<B, T, Value, Attached>> {
         let name = self.name.clone();
+        let (block_index, driver_layout) =
+            find_block::<B, T, Value>(program_id, &name, self.data.value.len())?;
 
-        // Get location of the lights on the GPU, and length of the array CPU side
-        let block_index = get_interface_block_index(program_id, name.clone())?;
-
-        // Initialize a Uniform Buffer for the lights, if we haven't alreaady
-        let mut buffer_id: GLuint = 0;
-        unsafe {
-            gl::GenBuffers(1, &mut buffer_id);
-        }
-
-        // Buffer the data
-        let ptr = self.data.value.as_ptr() as *const std::ffi::c_void;
-        let size =
-            (self.data.value.len() as u32 * std::mem::size_of::<Value>() as u32) as GLsizeiptr;
-        unsafe {
-            gl::UniformBlockBinding(buffer_id, block_index, binding_point);
-            // Could be error site
-            gl::BindBuffer(gl::UNIFORM_BUFFER, buffer_id);
-            gl::BufferData(gl::UNIFORM_BUFFER, size, ptr, gl::DYNAMIC_DRAW);
-            gl::BindBufferBase(gl::UNIFORM_BUFFER, binding_point, buffer_id);
-        }
+        // Initialize a buffer for the block, and point the block at it
+        B::block_binding(program_id, block_index, binding_point);
+        let stream = Stream::new(B::TARGET, B::USAGE, &name.to_string_lossy());
         let attached = InterfaceBlock {
             name,
             data: Attached {
-                buffer_id,
-                block_index,
                 binding_point,
+                length: Cell::new(0),
+                stream: RefCell::new(stream),
+                driver_layout,
             },
             // Ghosts
             data_type: PhantomData,
             block_type: PhantomData,
             packing_type: PhantomData,
         };
-
+        attached.update(self.data.value);
         Ok(attached)
     }
+
     pub(crate) fn key(&self) -> Rc<str> {
         let name = self.name.to_string_lossy();
         let key: Rc<str> = Rc::from(name);
@@ -125,42 +217,179 @@ impl<T, Value> InterfaceBlock<Uniform, T, Value, Unattached<Value>> {
     }
 }
 
-impl<T, Value> InterfaceBlock<Uniform, T, Value, Attached> {
-    pub(crate) fn update(&self, value: Vec<Value>) -> () {
-        // Buffer the data
-        let ptr = value.as_ptr() as *const std::ffi::c_void;
-        let size = (value.len() as u32 * std::mem::size_of::<Value>() as u32) as GLsizeiptr;
-        unsafe {
-            gl::UniformBlockBinding(
-                self.data.buffer_id,
-                self.data.block_index,
-                self.data.binding_point,
-            );
-            // Could be error site
-            gl::BindBuffer(gl::UNIFORM_BUFFER, self.data.buffer_id);
-            gl::BufferData(gl::UNIFORM_BUFFER, size, ptr, gl::DYNAMIC_DRAW);
-            gl::BindBufferBase(
-                gl::UNIFORM_BUFFER,
-                self.data.binding_point,
-                self.data.buffer_id,
-            );
+impl<B, T, Value> InterfaceBlock<B, T, Value, Attached>
+where
+    B: BlockType,
+    T: BlockLayout,
+    Value: Layout<T::Rules>,
+{
+    // Replaces the contents of the buffer
+    pub fn update(&self, value: Vec<Value>) {
+        // Buffer the data, padded the way the block's layout expects
+        let mut bytes = self.to_bytes(&value);
+        if let Some(driver_layout) = self.data.driver_layout.as_ref() {
+            bytes.resize(bytes.len().max(driver_layout.size), 0);
+        }
+        self.data
+            .stream
+            .borrow_mut()
+            .upload(self.data.binding_point, &bytes);
+        self.data.length.set(value.len());
+    }
+
+    // Overwrites the values starting at `first`, leaving the rest of the buffer as it is
+    pub fn update_range(&self, first: usize, value: &[Value]) -> Result<()> {
+        let range = self.byte_range(first..first + value.len())?;
+        let bytes = self.to_bytes(value);
+        self.data
+            .stream
+            .borrow_mut()
+            .upload_range(self.data.binding_point, range.start, &bytes);
+        Ok(())
+    }
+
+    // Where a range of values is in the buffer, in bytes
+    fn byte_range(&self, range: std::ops::Range<usize>) -> Result<std::ops::Range<usize>> {
+        if range.end > self.len() {
+            return Err(InterfaceBlockError::OutOfRange {
+                block: self.name.to_string_lossy().to_string(),
+                first: range.start,
+                count: range.len(),
+                length: self.len(),
+            });
+        }
+        let stride = match self.data.driver_layout.as_ref() {
+            Some(driver_layout) => driver_layout.stride,
+            None => layout::array_stride::<T::Rules, Value>(),
+        };
+        Ok(range.start * stride..range.end.max(range.start) * stride)
+    }
+
+    // The values laid out by the block's packing rules, or where the driver put their members
+    fn to_bytes(&self, values: &[Value]) -> Vec<u8> {
+        match self.data.driver_layout.as_ref() {
+            Some(driver_layout) => driver_layout.to_bytes::<T::Rules, Value>(values),
+            None => layout::to_bytes::<T::Rules, Value>(values),
+        }
+    }
+
+    // ...and back
+    fn read_bytes(&self, bytes: &[u8], count: usize) -> Vec<Value> {
+        match self.data.driver_layout.as_ref() {
+            Some(driver_layout) => driver_layout.read_bytes::<T::Rules, Value>(bytes, count),
+            None => layout::from_bytes::<T::Rules, Value>(bytes, count),
+        }
+    }
+
+    // Points another program's block of the same name at this block's buffer
+    pub(crate) fn share(&self, program_id: GLuint) -> Result<()> {
+        let (block_index, driver_layout) =
+            find_block::<B, T, Value>(program_id, &self.name, self.len())?;
+        // Shared blocks are laid out the same in every program, packed ones needn't be
+        if driver_layout != self.data.driver_layout {
+            return Err(InterfaceBlockError::PackedLayoutDiffers {
+                block: self.name.to_string_lossy().to_string(),
+            });
         }
+        B::block_binding(program_id, block_index, self.data.binding_point);
+        Ok(())
+    }
+
+    pub(crate) fn key(&self) -> Rc<str> {
+        Rc::from(self.name.to_string_lossy())
+    }
+
+    // Number of values in the buffer
+    pub fn len(&self) -> usize {
+        self.data.length.get()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn binding_point(&self) -> GLuint {
+        self.data.binding_point
+    }
+}
+
+// Per-frame uniforms can be streamed without reallocating their buffer every update
+impl<T, Value> InterfaceBlock<Uniform, T, Value, Attached>
+where
+    T: BlockLayout,
+    Value: Layout<T::Rules>,
+{
+    // Takes effect from the next update
+    pub fn set_streaming(&self, streaming: Streaming) {
+        self.data.stream.borrow_mut().set_streaming(streaming)
+    }
+
+    pub fn streaming(&self) -> Streaming {
+        self.data.stream.borrow().streaming()
     }
 }
 
-// UGH KILL ME
-pub trait InterfaceBuffer {}
-impl<Value> InterfaceBuffer for InterfaceBlock<Uniform, Std140, Value, Attached> {}
+// The GLProgram's map of attached blocks is abstracted over this, blocks of a known type can be
+// recovered from it with `as_any()`
+pub trait InterfaceBuffer {
+    fn buffer_id(&self) -> GLuint;
+    fn binding_point(&self) -> GLuint;
+    // The buffer target it's bound to, e.g. GL_UNIFORM_BUFFER
+    fn target(&self) -> GLenum;
+    fn as_any(self: Rc<Self>) -> Rc<dyn Any>;
+}
+
+impl<B, T, Value> InterfaceBuffer for InterfaceBlock<B, T, Value, Attached>
+where
+    B: BlockType,
+    T: 'static,
+    Value: 'static,
+{
+    fn buffer_id(&self) -> GLuint {
+        self.data.stream.borrow().buffer_id()
+    }
+
+    fn binding_point(&self) -> GLuint {
+        self.data.binding_point
+    }
+
+    fn target(&self) -> GLenum {
+        B::TARGET
+    }
 
-// Similar to get_uniform_location but for block indices
-fn get_interface_block_index(program_id: GLuint, name: CString) -> Result<GLuint> {
-    let location;
-    unsafe {
-        location = gl::GetUniformBlockIndex(program_id, name.clone().into_raw());
+    fn as_any(self: Rc<Self>) -> Rc<dyn Any> {
+        self
     }
-    match location {
-        gl::INVALID_INDEX => Err(InterfaceBlockError::CouldNotFindUniformIndex(name)),
-        _ => Ok(location),
+}
+
+// Finds the block in the program, and makes sure the GPU reads `count` values from where they're
+// written: by checking the driver laid the block out by its rules, or, if the driver chose the
+// layout, by finding out where it put each member
+fn find_block<B, T, Value>(
+    program_id: GLuint,
+    name: &CStr,
+    count: usize,
+) -> Result<(GLuint, Option<DriverLayout>)>
+where
+    B: BlockType,
+    T: BlockLayout,
+    Value: Layout<T::Rules>,
+{
+    let block_index =
+        unsafe { gl::GetProgramResourceIndex(program_id, B::INTERFACE, name.as_ptr()) };
+    let block = reflection::block(program_id, B::INTERFACE, name);
+    match (block_index, block) {
+        (gl::INVALID_INDEX, _) | (_, None) => Err(InterfaceBlockError::CouldNotFindUniformIndex(
+            CString::from(name),
+        )),
+        (block_index, Some(block)) if T::DRIVER_CHOSEN => {
+            let driver_layout = verify::driver_layout::<T::Rules, Value>(&block, count)?;
+            Ok((block_index, Some(driver_layout)))
+        }
+        (block_index, Some(block)) => {
+            verify::verify::<T::Rules, Value>(&block, count)?;
+            Ok((block_index, None))
+        }
     }
 }
 
@@ -0,0 +1,89 @@
+// Shader Storage Buffer Objects: interface blocks shaders can write to as well as read from, e.g.
+// `buffer Particles { Particle particles[]; }`, whose contents can be read back to the CPU
+use super::layout::{BlockLayout, Layout};
+use super::{Attached, InterfaceBlock, InterfaceBlockError, Result, Shader, Std430};
+use super::{Unattached, UniformBufferBlock};
+
+// OpenGL Types
+use gl::types::*;
+use std::marker::PhantomData;
+use std::ops::Range;
+
+// Public API for creating new, unattached Shader Storage Blocks
+pub struct ShaderStorageBlock;
+impl ShaderStorageBlock {
+    // The values fill the block's array, an unsized array is as long as `value`
+    pub fn new_std430<S, Value>(
+        name: S,
+        value: Vec<Value>,
+    ) -> Result<InterfaceBlock<Shader, Std430, Value, Unattached<Value>>>
+    where
+        S: AsRef<str>,
+        Value: Layout<Std430>,
+    {
+        ShaderStorageBlock::with_layout(name, value)
+    }
+
+    pub fn with_layout<T, S, Value>(
+        name: S,
+        value: Vec<Value>,
+    ) -> Result<InterfaceBlock<Shader, T, Value, Unattached<Value>>>
+    where
+        T: BlockLayout,
+        S: AsRef<str>,
+        Value: Layout<T::Rules>,
+    {
+        // Same as a uniform block, just a different ghost
+        let block = UniformBufferBlock::with_layout::<T, S, Value>(name, value)?;
+        Ok(InterfaceBlock {
+            name: block.name,
+            data: block.data,
+            // Ghosts
+            data_type: PhantomData,
+            block_type: PhantomData,
+            packing_type: PhantomData,
+        })
+    }
+}
+
+impl<T, Value> InterfaceBlock<Shader, T, Value, Attached>
+where
+    T: BlockLayout,
+    Value: Layout<T::Rules>,
+{
+    // Reads every value in the buffer back, e.g. after a shader has written to it
+    pub fn read(&self) -> Result<Vec<Value>> {
+        self.read_range(0..self.len())
+    }
+
+    pub fn read_range(&self, range: Range<usize>) -> Result<Vec<Value>> {
+        let count = range.len();
+        let bytes = self.byte_range(range)?;
+        if count == 0 {
+            return Ok(Vec::new());
+        }
+
+        let values;
+        unsafe {
+            // Writes made by shaders have to land before the buffer is mapped
+            gl::MemoryBarrier(gl::BUFFER_UPDATE_BARRIER_BIT);
+            let buffer_id = self.data.stream.borrow().buffer_id();
+            gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, buffer_id);
+            let ptr = gl::MapBufferRange(
+                gl::SHADER_STORAGE_BUFFER,
+                bytes.start as GLintptr,
+                bytes.len() as GLsizeiptr,
+                gl::MAP_READ_BIT,
+            );
+            if ptr.is_null() {
+                return Err(InterfaceBlockError::MapBuffer(
+                    self.name.to_string_lossy().to_string(),
+                ));
+            }
+            let mapped = std::slice::from_raw_parts(ptr as *const u8, bytes.len());
+            values = self.read_bytes(mapped, count);
+            gl::UnmapBuffer(gl::SHADER_STORAGE_BUFFER);
+        }
+        Ok(values)
+    }
+}
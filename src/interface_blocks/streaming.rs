@@ -0,0 +1,285 @@
+// How an attached block's buffer is updated. Per-frame data, e.g. the camera's matrices, is best
+// written somewhere the GPU isn't reading from, so neither side waits on the other.
+use super::layout;
+use crate::window::object::GLObject;
+
+// OpenGL Types
+use gl::types::*;
+use std::ffi::c_void;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Streaming {
+    // `glBufferData()` every update, reallocating the buffer
+    #[default]
+    Reallocate,
+    // Orphans the old storage, which the GPU keeps reading until it's done with it, and writes to
+    // fresh storage of the same size
+    Orphan,
+    // `glBufferSubData()` into the same storage, which waits if the GPU is still reading it
+    SubData,
+    // Updates are written round-robin to the regions of a persistently mapped buffer, waiting on a
+    // fence only if the GPU is still reading a region from RING_REGIONS updates ago
+    Ring,
+}
+
+// Triple buffered
+pub const RING_REGIONS: usize = 3;
+
+// How long to wait on a fence before checking again, in nanoseconds
+const FENCE_TIMEOUT: GLuint64 = 1_000_000;
+
+// A block's buffer, and how it's being updated
+pub(crate) struct Stream {
+    target: GLenum,
+    usage: GLenum,
+    buffer: GLObject,
+    // The block's, which the buffer is labelled with
+    name: Rc<str>,
+    // In bytes, of what was last uploaded
+    size: usize,
+    streaming: Streaming,
+    ring: Option<Ring>,
+}
+
+impl Stream {
+    pub(crate) fn new(target: GLenum, usage: GLenum, name: &str) -> Self {
+        let name: Rc<str> = Rc::from(name);
+        Stream {
+            target,
+            usage,
+            buffer: create_buffer(target, &name),
+            name,
+            size: 0,
+            streaming: Streaming::default(),
+            ring: None,
+        }
+    }
+
+    pub(crate) fn buffer_id(&self) -> GLuint {
+        self.buffer.id()
+    }
+
+    pub(crate) fn streaming(&self) -> Streaming {
+        self.streaming
+    }
+
+    // Takes effect from the next upload
+    pub(crate) fn set_streaming(&mut self, streaming: Streaming) {
+        // The ring's storage is immutable, so the other strategies need a new buffer
+        if self.ring.is_some() && streaming != Streaming::Ring {
+            self.replace_buffer();
+        }
+        self.streaming = streaming;
+    }
+
+    // Replaces the contents of the buffer
+    pub(crate) fn upload(&mut self, binding_point: GLuint, bytes: &[u8]) {
+        if self.streaming == Streaming::Ring {
+            self.ring(bytes.len()).contents = bytes.to_vec();
+            self.next_region(binding_point);
+            self.size = bytes.len();
+            return;
+        }
+
+        let ptr = bytes.as_ptr() as *const c_void;
+        let size = bytes.len() as GLsizeiptr;
+        unsafe {
+            gl::BindBuffer(self.target, self.buffer.id());
+            // Storage can only be reused if it's the same size
+            match (self.streaming, bytes.len() == self.size) {
+                (Streaming::Orphan, true) => {
+                    gl::BufferData(self.target, size, std::ptr::null(), self.usage);
+                    gl::BufferSubData(self.target, 0, size, ptr);
+                }
+                (Streaming::SubData, true) => gl::BufferSubData(self.target, 0, size, ptr),
+                _ => gl::BufferData(self.target, size, ptr, self.usage),
+            }
+            gl::BindBufferBase(self.target, binding_point, self.buffer.id());
+        }
+        self.size = bytes.len();
+    }
+
+    // Overwrites part of the buffer, which must lie within what was last uploaded
+    pub(crate) fn upload_range(&mut self, binding_point: GLuint, offset: usize, bytes: &[u8]) {
+        match self.ring.as_mut() {
+            // The rest of the region has to be carried over too
+            Some(ring) if self.streaming == Streaming::Ring => {
+                ring.contents[offset..offset + bytes.len()].copy_from_slice(bytes);
+                self.next_region(binding_point);
+            }
+            _ => unsafe {
+                gl::BindBuffer(self.target, self.buffer.id());
+                gl::BufferSubData(
+                    self.target,
+                    offset as GLintptr,
+                    bytes.len() as GLsizeiptr,
+                    bytes.as_ptr() as *const c_void,
+                );
+            },
+        }
+    }
+
+    // A ring whose regions can hold `capacity` bytes, replacing the current one if it's too small
+    fn ring(&mut self, capacity: usize) -> &mut Ring {
+        let ring = match self.ring.take() {
+            Some(ring) if ring.region_size >= capacity => ring,
+            ring => {
+                self.ring = ring;
+                self.replace_buffer();
+                Ring::new(self.target, self.buffer.id(), capacity)
+            }
+        };
+        self.ring.insert(ring)
+    }
+
+    // Moves on to the next region of the ring, writes the contents to it, and binds it
+    fn next_region(&mut self, binding_point: GLuint) {
+        let (target, buffer_id) = (self.target, self.buffer.id());
+        let Some(ring) = self.ring.as_mut() else {
+            return;
+        };
+        unsafe {
+            // Everything drawn since the last update reads from the current region
+            ring.fences[ring.region] = gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0);
+            ring.region = (ring.region + 1) % RING_REGIONS;
+            wait(&mut ring.fences[ring.region]);
+
+            let offset = ring.region * ring.region_size;
+            match ring.mapped.is_null() {
+                false => std::ptr::copy_nonoverlapping(
+                    ring.contents.as_ptr(),
+                    ring.mapped.add(offset),
+                    ring.contents.len(),
+                ),
+                // Mapping failed, but the storage can still be written to
+                true => {
+                    gl::BindBuffer(target, buffer_id);
+                    gl::BufferSubData(
+                        target,
+                        offset as GLintptr,
+                        ring.contents.len() as GLsizeiptr,
+                        ring.contents.as_ptr() as *const c_void,
+                    );
+                }
+            }
+            gl::BindBufferRange(
+                target,
+                binding_point,
+                buffer_id,
+                offset as GLintptr,
+                ring.region_size as GLsizeiptr,
+            );
+        }
+    }
+
+    // Buffer storage can't be resized once it's immutable, so it's replaced by a new buffer (and
+    // the old one deleted)
+    fn replace_buffer(&mut self) {
+        if let Some(ring) = self.ring.take() {
+            ring.release(self.target, self.buffer.id());
+        }
+        self.buffer = create_buffer(self.target, &self.name);
+        self.size = 0;
+    }
+}
+
+// Deleting the buffer unmaps it, but the ring's fences have to be deleted separately
+impl Drop for Stream {
+    fn drop(&mut self) {
+        let (Some(ring), Some(context)) = (self.ring.as_ref(), self.buffer.context()) else {
+            return;
+        };
+        context.with(|| {
+            for fence in ring.fences.iter().filter(|fence| !fence.is_null()) {
+                unsafe {
+                    gl::DeleteSync(*fence);
+                }
+            }
+        });
+    }
+}
+
+// A persistently mapped buffer split into RING_REGIONS regions
+struct Ring {
+    mapped: *mut u8,
+    // Each region is aligned to the target's binding offset alignment
+    region_size: usize,
+    // Which region the block is currently reading from
+    region: usize,
+    // Signalled when the GPU is done with each region
+    fences: [GLsync; RING_REGIONS],
+    // Copied into each region written
+    contents: Vec<u8>,
+}
+
+impl Ring {
+    fn new(target: GLenum, buffer_id: GLuint, capacity: usize) -> Self {
+        let mut alignment = 0;
+        let alignment_parameter = match target {
+            gl::UNIFORM_BUFFER => gl::UNIFORM_BUFFER_OFFSET_ALIGNMENT,
+            _ => gl::SHADER_STORAGE_BUFFER_OFFSET_ALIGNMENT,
+        };
+        unsafe {
+            gl::GetIntegerv(alignment_parameter, &mut alignment);
+        }
+        let region_size = layout::round_up(capacity.max(1), alignment.max(1) as usize);
+
+        let size = (region_size * RING_REGIONS) as GLsizeiptr;
+        let flags = gl::MAP_WRITE_BIT | gl::MAP_PERSISTENT_BIT | gl::MAP_COHERENT_BIT;
+        let mapped = unsafe {
+            gl::BindBuffer(target, buffer_id);
+            gl::BufferStorage(
+                target,
+                size,
+                std::ptr::null(),
+                flags | gl::DYNAMIC_STORAGE_BIT,
+            );
+            gl::MapBufferRange(target, 0, size, flags) as *mut u8
+        };
+        Ring {
+            mapped,
+            region_size,
+            region: 0,
+            fences: [std::ptr::null(); RING_REGIONS],
+            contents: Vec::new(),
+        }
+    }
+
+    fn release(mut self, target: GLenum, buffer_id: GLuint) {
+        for fence in self.fences.iter_mut() {
+            wait(fence);
+        }
+        if !self.mapped.is_null() {
+            unsafe {
+                gl::BindBuffer(target, buffer_id);
+                gl::UnmapBuffer(target);
+            }
+        }
+    }
+}
+
+// Blocks until the GPU has passed the fence, then deletes it
+fn wait(fence: &mut GLsync) {
+    if fence.is_null() {
+        return;
+    }
+    unsafe {
+        while gl::ClientWaitSync(*fence, gl::SYNC_FLUSH_COMMANDS_BIT, FENCE_TIMEOUT)
+            == gl::TIMEOUT_EXPIRED
+        {}
+        gl::DeleteSync(*fence);
+    }
+    *fence = std::ptr::null();
+}
+
+// Binding the buffer creates it, so it can be labelled before anything's uploaded to it
+fn create_buffer(target: GLenum, name: &str) -> GLObject {
+    let buffer = GLObject::buffer();
+    unsafe {
+        gl::BindBuffer(target, buffer.id());
+        gl::BindBuffer(target, 0);
+    }
+    buffer.label(name);
+    buffer
+}
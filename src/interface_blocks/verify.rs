@@ -0,0 +1,529 @@
+// Checks the layout values are uploaded with agrees with the layout the driver gave the block.
+// Blocks either hold an array of values, e.g. `uniform Lights { Light lights[8]; }`, or a single
+// value's fields directly, e.g. `uniform Light { vec3 position; float intensity; }`.
+// Storage blocks may end in an unsized array, e.g. `buffer Particles { Particle particles[]; }`,
+// which can be backed by as many values as we like.
+//
+// `shared` and `packed` blocks aren't checked, there's no layout to check them against. Instead
+// their values are laid out by std140, then each member the driver reports is copied to the offset
+// it was given, and members a packed block dropped are left out.
+use super::layout::{self, Layout, Packing};
+use super::{InterfaceBlockError, Result};
+use crate::program::reflection::{ActiveBlock, BlockMember, GlslType};
+
+use std::collections::{HashMap, HashSet};
+
+pub(crate) fn verify<P, Value>(block: &ActiveBlock, count: usize) -> Result<()>
+where
+    P: Packing,
+    Value: Layout<P>,
+{
+    let stride = layout::array_stride::<P, Value>();
+    let mismatch = |member: &str, driver: i32, rust: usize| {
+        Err(InterfaceBlockError::LayoutMismatch {
+            block: block.name.clone(),
+            member: member.to_string(),
+            driver,
+            rust,
+        })
+    };
+
+    // The driver sizes an unsized array as if it had a single element
+    let size = stride * count;
+    let minimum = block.data_size.max(0) as usize;
+    let unsized_array = block
+        .members
+        .iter()
+        .any(|member| member.top_level_array_size == 0 || member.array_size == 0);
+    if (unsized_array && size < minimum) || (!unsized_array && size != minimum) {
+        return Err(InterfaceBlockError::SizeMismatch {
+            block: block.name.clone(),
+            driver: block.data_size,
+            rust: size,
+        });
+    }
+
+    // Members are sorted by offset, so the first member seen of each field of each element is where
+    // that field starts, the rest belong to nested structs and arrays
+    let mut seen = HashSet::new();
+    for member in block.members.iter() {
+        let (element, field) = locate(&member.name, Value::FIELDS);
+        let in_array = element.is_some();
+        let element = element.unwrap_or(0);
+        if element >= count || !seen.insert((element, field)) {
+            continue;
+        }
+
+        let field_offset = match field {
+            Some(field) => Value::OFFSETS[field],
+            None => 0,
+        };
+        let expected = element * stride + field_offset;
+        if member.offset.max(0) as usize != expected {
+            return mismatch(&member.name, member.offset, expected);
+        }
+        // An array of plain values, e.g. `vec4 colors[8]`, or of structs in a storage block, where
+        // only the first element's members are reported
+        let driver = match field {
+            None if count > 1 => member.array_stride,
+            Some(_) if in_array => member.top_level_array_stride,
+            _ => 0,
+        };
+        if driver > 0 && driver as usize != stride {
+            return Err(InterfaceBlockError::StrideMismatch {
+                block: block.name.clone(),
+                member: member.name.clone(),
+                driver,
+                rust: stride,
+            });
+        }
+    }
+    Ok(())
+}
+
+// Where the driver put the members of a `shared` or `packed` block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DriverLayout {
+    // Bytes from one value to the next
+    pub(crate) stride: usize,
+    // Smallest buffer the block can be backed by
+    pub(crate) size: usize,
+    // (std140 offset, driver offset, length) of each run of bytes of the first value, the rest are
+    // `stride` apart
+    copies: Vec<(usize, usize, usize)>,
+}
+
+pub(crate) fn driver_layout<P, Value>(block: &ActiveBlock, count: usize) -> Result<DriverLayout>
+where
+    P: Packing,
+    Value: Layout<P>,
+{
+    let mut copies = Vec::new();
+    let mut stride = 0;
+    // Offsets of the first value's members, to tell how far apart the next value's are
+    let mut first = HashMap::new();
+    for member in block.members.iter() {
+        let (element, path) = member_path(&member.name, Value::FIELDS);
+        match element {
+            Some(0) => {
+                first.insert(path, member.offset);
+            }
+            Some(_) => {
+                if let Some(offset) = first.get(path) {
+                    if stride == 0 {
+                        stride = (member.offset - offset).max(0) as usize;
+                    }
+                }
+                continue;
+            }
+            None => {}
+        }
+        if element.is_some() && member.top_level_array_stride > 0 {
+            stride = member.top_level_array_stride as usize;
+        }
+
+        let offset = member.offset.max(0) as usize;
+        let array_stride = member.array_stride.max(0) as usize;
+        // Each element of an array member, e.g. "h[0]" of a `float h[2]` stands for "h[0]" and "h[1]"
+        let elements = |array: &str| {
+            (0..member.array_size.max(1) as usize)
+                .map(|index| {
+                    (
+                        format!("{}[{}]", array, index),
+                        offset + index * array_stride,
+                    )
+                })
+                .collect()
+        };
+        let paths: Vec<(String, usize)> = match path.strip_suffix("[0]") {
+            // A single array, e.g. a `[f32; 4]` uploaded to `float values[4]`
+            _ if path.is_empty() && count == 1 && Value::member("[0]").is_some() => elements(""),
+            // The uploaded values are the block's array, e.g. `float values[8]`
+            _ if path.is_empty() => {
+                if element.is_some() && array_stride > 0 {
+                    stride = array_stride;
+                }
+                vec![(String::new(), offset)]
+            }
+            Some(array) => elements(array),
+            None => vec![(path.to_string(), offset)],
+        };
+        for (path, to) in paths {
+            let Some((from, size)) = Value::member(&path) else {
+                return Err(InterfaceBlockError::MissingMember {
+                    block: block.name.clone(),
+                    member: member.name.clone(),
+                });
+            };
+            copies.extend(member_copies(member, from, size, to));
+        }
+    }
+
+    // A single value, rather than an array of them
+    let size = block.data_size.max(0) as usize;
+    if stride == 0 {
+        if count > 1 {
+            return Err(InterfaceBlockError::SizeMismatch {
+                block: block.name.clone(),
+                driver: block.data_size,
+                rust: count * layout::array_stride::<P, Value>(),
+            });
+        }
+        stride = size;
+    }
+    if let Some(end) = copies.iter().map(|(_, to, length)| to + length).max() {
+        if end > stride {
+            return Err(InterfaceBlockError::SizeMismatch {
+                block: block.name.clone(),
+                driver: stride as i32,
+                rust: end,
+            });
+        }
+    }
+    Ok(DriverLayout {
+        stride,
+        size,
+        copies,
+    })
+}
+
+impl DriverLayout {
+    // Lays out `values` where the driver expects them, `stride` bytes apart
+    pub(crate) fn to_bytes<P, Value>(&self, values: &[Value]) -> Vec<u8>
+    where
+        P: Packing,
+        Value: Layout<P>,
+    {
+        let staged = layout::to_bytes::<P, Value>(values);
+        let staged_stride = layout::array_stride::<P, Value>();
+        let mut bytes = vec![0; self.stride * values.len()];
+        for index in 0..values.len() {
+            for &(from, to, length) in self.copies.iter() {
+                let from = index * staged_stride + from;
+                let to = index * self.stride + to;
+                bytes[to..to + length].copy_from_slice(&staged[from..from + length]);
+            }
+        }
+        bytes
+    }
+
+    // Reads `count` values back out of bytes laid out by `to_bytes()`
+    pub(crate) fn read_bytes<P, Value>(&self, bytes: &[u8], count: usize) -> Vec<Value>
+    where
+        P: Packing,
+        Value: Layout<P>,
+    {
+        let staged_stride = layout::array_stride::<P, Value>();
+        let mut staged = vec![0; staged_stride * count];
+        for index in 0..count {
+            for &(from, to, length) in self.copies.iter() {
+                let from = index * staged_stride + from;
+                let to = index * self.stride + to;
+                staged[from..from + length].copy_from_slice(&bytes[to..to + length]);
+            }
+        }
+        layout::from_bytes::<P, Value>(&staged, count)
+    }
+}
+
+// The runs of bytes a member is copied in. Matrix columns (or rows, if it's row major) are
+// `matrix_stride` apart on the GPU, which needn't be std140's stride.
+fn member_copies(
+    member: &BlockMember,
+    from: usize,
+    size: usize,
+    to: usize,
+) -> Vec<(usize, usize, usize)> {
+    let Some((columns, rows, component)) = matrix_shape(member.glsl_type) else {
+        return vec![(from, to, size)];
+    };
+    let column_stride = size / columns;
+    let matrix_stride = member.matrix_stride.max(0) as usize;
+    match member.row_major {
+        false => (0..columns)
+            .map(|column| {
+                let from = from + column * column_stride;
+                (from, to + column * matrix_stride, rows * component)
+            })
+            .collect(),
+        true => (0..columns)
+            .flat_map(|column| {
+                (0..rows).map(move |row| {
+                    let from = from + column * column_stride + row * component;
+                    (
+                        from,
+                        to + row * matrix_stride + column * component,
+                        component,
+                    )
+                })
+            })
+            .collect(),
+    }
+}
+
+// Columns, rows, and bytes per component of a matrix type, e.g. (2, 4, 8) for a `dmat2x4`
+fn matrix_shape(glsl_type: GlslType) -> Option<(usize, usize, usize)> {
+    let name = glsl_type.name();
+    let (component, shape) = match name.strip_prefix("dmat") {
+        Some(shape) => (8, shape),
+        None => (4, name.strip_prefix("mat")?),
+    };
+    let (columns, rows) = match shape.split_once('x') {
+        Some((columns, rows)) => (columns.parse().ok()?, rows.parse().ok()?),
+        None => (shape.parse().ok()?, shape.parse().ok()?),
+    };
+    Some((columns, rows, component))
+}
+
+// Which element of the uploaded values a member belongs to, and the path to it within the element,
+// e.g. element 2 and "color.r" for "lights[2].color.r", and no element and "color.r" for
+// "color.r", a field of the single value (which isn't an element of an array)
+fn member_path<'a>(name: &'a str, fields: &[&str]) -> (Option<usize>, &'a str) {
+    let (first, rest) = layout::split_path(name);
+    if fields.contains(&first) {
+        return (None, name);
+    }
+    let (array, rest) = match rest.strip_prefix('[') {
+        Some(_) => layout::split_path(rest),
+        None => ("", rest),
+    };
+    let element = array
+        .strip_prefix('[')
+        .and_then(|index| index.strip_suffix(']'))
+        .and_then(|index| index.parse().ok());
+    (element, rest)
+}
+
+// Which element of the uploaded values, and which of its fields, a member belongs to, e.g.
+// "lights[2].color.r" is the `color` field of element 2, and "color.r" is the `color` field of the
+// single value (which isn't an element of an array)
+fn locate(name: &str, fields: &[&str]) -> (Option<usize>, Option<usize>) {
+    let mut components = name.split('.');
+    let first = components.next().unwrap_or(name);
+    let field = |component: &str| {
+        let base = component.split('[').next().unwrap_or(component);
+        fields.iter().position(|field| *field == base)
+    };
+
+    match field(first) {
+        Some(index) => (None, Some(index)),
+        None => {
+            let element = first
+                .split_once('[')
+                .and_then(|(_, index)| index.trim_end_matches(']').parse().ok());
+            (element, components.next().and_then(field))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interface_blocks::{Std140, Std430};
+    use crate::program::reflection::{BlockMember, GlslType};
+    use ultraviolet::{Mat2, Vec2, Vec3, Vec4};
+
+    #[derive(Std140, Std430)]
+    struct Light {
+        position: Vec3,
+        intensity: f32,
+        color: Vec4,
+    }
+
+    fn member(name: &str, offset: i32, array_stride: i32) -> BlockMember {
+        BlockMember {
+            name: name.to_string(),
+            glsl_type: GlslType(gl::FLOAT),
+            array_size: 1,
+            offset,
+            array_stride,
+            matrix_stride: 0,
+            row_major: false,
+            top_level_array_size: 1,
+            top_level_array_stride: 0,
+        }
+    }
+
+    fn block(data_size: i32, members: Vec<BlockMember>) -> ActiveBlock {
+        ActiveBlock {
+            name: "Lights".to_string(),
+            binding: 0,
+            data_size,
+            members,
+        }
+    }
+
+    #[test]
+    fn array_of_structs() {
+        let lights = block(
+            64,
+            vec![
+                member("lights[0].position", 0, 0),
+                member("lights[0].intensity", 12, 0),
+                member("lights[0].color", 16, 0),
+                member("lights[1].position", 32, 0),
+                member("lights[1].intensity", 44, 0),
+                member("lights[1].color", 48, 0),
+            ],
+        );
+        assert!(verify::<Std140, Light>(&lights, 2).is_ok());
+    }
+
+    #[test]
+    fn fields_of_a_single_struct() {
+        let light = block(
+            32,
+            vec![
+                member("position", 0, 0),
+                member("intensity", 12, 0),
+                member("color", 16, 0),
+            ],
+        );
+        assert!(verify::<Std430, Light>(&light, 1).is_ok());
+    }
+
+    #[test]
+    fn pinpoints_the_mismatching_member() {
+        // e.g. a driver that didn't pack `intensity` after `position`
+        let light = block(
+            32,
+            vec![
+                member("light.position", 0, 0),
+                member("light.intensity", 16, 0),
+                member("light.color", 20, 0),
+            ],
+        );
+        match verify::<Std140, Light>(&light, 1) {
+            Err(InterfaceBlockError::LayoutMismatch {
+                member,
+                driver,
+                rust,
+                ..
+            }) => assert_eq!((member.as_str(), driver, rust), ("light.intensity", 16, 12)),
+            _ => panic!("expected a layout mismatch"),
+        }
+    }
+
+    #[test]
+    fn size_and_stride() {
+        let too_small = block(32, vec![member("lights[0].position", 0, 0)]);
+        assert!(matches!(
+            verify::<Std140, Light>(&too_small, 2),
+            Err(InterfaceBlockError::SizeMismatch { .. })
+        ));
+
+        let floats = block(32, vec![member("values[0]", 0, 4)]);
+        assert!(matches!(
+            verify::<Std140, f32>(&floats, 2),
+            Err(InterfaceBlockError::StrideMismatch { .. })
+        ));
+        assert!(verify::<Std430, f32>(&block(8, vec![member("values[0]", 0, 4)]), 2).is_ok());
+    }
+
+    #[test]
+    fn unsized_storage_arrays() {
+        // `buffer Lights { Light lights[]; }` is sized as if it held one light
+        let unsized_member = |name, offset, top_level_array_stride| BlockMember {
+            top_level_array_size: 0,
+            top_level_array_stride,
+            ..member(name, offset, 0)
+        };
+        let lights = |stride| {
+            block(
+                32,
+                vec![
+                    unsized_member("lights[0].position", 0, stride),
+                    unsized_member("lights[0].intensity", 12, stride),
+                    unsized_member("lights[0].color", 16, stride),
+                ],
+            )
+        };
+        assert!(verify::<Std430, Light>(&lights(32), 100).is_ok());
+        assert!(matches!(
+            verify::<Std430, Light>(&lights(32), 0),
+            Err(InterfaceBlockError::SizeMismatch { .. })
+        ));
+        assert!(matches!(
+            verify::<Std430, Light>(&lights(48), 100),
+            Err(InterfaceBlockError::StrideMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn packed_blocks_follow_the_driver() {
+        // `intensity` is never read, so the driver dropped it and packed the rest tightly
+        let lights = block(
+            56,
+            vec![
+                member("lights[0].position", 0, 0),
+                member("lights[0].color", 12, 0),
+                member("lights[1].position", 28, 0),
+                member("lights[1].color", 40, 0),
+            ],
+        );
+        assert!(verify::<Std140, Light>(&lights, 2).is_err());
+        let packed = driver_layout::<Std140, Light>(&lights, 2).unwrap();
+        assert_eq!((packed.stride, packed.size), (28, 56));
+
+        let light = |index: f32| Light {
+            position: Vec3::new(index, 1.0, 2.0),
+            intensity: 3.0,
+            color: Vec4::new(4.0, 5.0, 6.0, index),
+        };
+        let bytes = packed.to_bytes::<Std140, Light>(&[light(0.0), light(1.0)]);
+        let floats: Vec<f32> = bytes
+            .chunks(4)
+            .map(|chunk| f32::from_ne_bytes(chunk.try_into().unwrap()))
+            .collect();
+        assert_eq!(
+            floats,
+            [0.0, 1.0, 2.0, 4.0, 5.0, 6.0, 0.0, 1.0, 1.0, 2.0, 4.0, 5.0, 6.0, 1.0]
+        );
+        let read = packed.read_bytes::<Std140, Light>(&bytes, 2);
+        assert_eq!(
+            (read[1].position, read[1].color),
+            (light(1.0).position, light(1.0).color)
+        );
+        assert_eq!(read[1].intensity, 0.0);
+
+        let unknown = block(16, vec![member("lights[0].radius", 0, 0)]);
+        assert!(matches!(
+            driver_layout::<Std140, Light>(&unknown, 1),
+            Err(InterfaceBlockError::MissingMember { .. })
+        ));
+    }
+
+    #[test]
+    fn packed_arrays_and_matrices_use_the_driver_strides() {
+        #[derive(Std140)]
+        struct Transform {
+            weights: [f32; 2],
+            matrix: Mat2,
+        }
+        // std140 would give the floats 16 bytes each, and the matrix's columns 16 bytes each
+        let transform = block(
+            24,
+            vec![
+                BlockMember {
+                    array_size: 2,
+                    ..member("weights[0]", 0, 4)
+                },
+                BlockMember {
+                    glsl_type: GlslType(gl::FLOAT_MAT2),
+                    matrix_stride: 8,
+                    ..member("matrix", 8, 0)
+                },
+            ],
+        );
+        let packed = driver_layout::<Std140, Transform>(&transform, 1).unwrap();
+        let bytes = packed.to_bytes::<Std140, Transform>(&[Transform {
+            weights: [1.0, 2.0],
+            matrix: Mat2::new(Vec2::new(3.0, 4.0), Vec2::new(5.0, 6.0)),
+        }]);
+        let floats: Vec<f32> = bytes
+            .chunks(4)
+            .map(|chunk| f32::from_ne_bytes(chunk.try_into().unwrap()))
+            .collect();
+        assert_eq!(floats, [1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+    }
+}
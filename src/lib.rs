@@ -1,20 +1,35 @@
+// Lets the derive macros refer to `::cs6600` from inside of this crate too
+extern crate self as cs6600;
+pub use cs6600_derive::{Std140, Std430, UniformStruct};
+
 // Opening a window with an OpenGL conteext
 pub mod error;
 pub use error::{GLError, GLStatus, GLUtilityError};
 pub mod window;
-pub use program::lights::{LightColor, Position};
+pub use program::clusters::ClusterSettings;
+pub use program::lights::{LightColor, LightSource, Position};
+pub use program::shadows::ShadowSettings;
 
 // Linking shaders to crete a GL Program
 pub mod program;
 pub use program::builder;
 pub use program::GLProgram;
+pub use program::{ProgramInterface, Scene};
 
 // Compiling shaders into OpenGL programs
 pub mod shader;
 pub use shader::Shader;
 
 pub mod materials;
-pub use materials::Material;
+pub use materials::{Material, PbrMaterial};
+
+// Loading images into textures
+pub mod texture;
+pub use texture::{ColorSpace, Texture};
+
+// Rendering in multiple passes, and post-processing
+pub mod render_graph;
+pub use render_graph::{PostProcessing, RenderGraph};
 // Loading shader, object, texture files
 // Creating and managing Vertex Array Objects
 // Types and Setting Uniform Values
@@ -1,12 +1,16 @@
+use crate::texture::Texture;
 use std::rc::Rc;
 use ultraviolet::vec::{Vec3, Vec4};
 
+// Blinn-Phong material
 #[derive(Debug, Clone)]
 pub struct Material {
-    specular_coeficient: f32,
-    color_ambient: Vec3,
-    color_diffuse: Vec3,
-    color_specular: Vec3,
+    pub(crate) specular_coeficient: f32,
+    pub(crate) color_ambient: Vec3,
+    pub(crate) color_diffuse: Vec3,
+    pub(crate) color_specular: Vec3,
+    // How much of the skybox the surface mirrors (0.0 = none, 1.0 = a perfect mirror)
+    pub(crate) reflectivity: f32,
 }
 
 impl Material {
@@ -21,6 +25,96 @@ impl Material {
             color_ambient,
             color_diffuse,
             color_specular,
+            reflectivity: 0.0,
+        }
+    }
+
+    pub fn with_reflectivity(mut self, reflectivity: f32) -> Self {
+        self.reflectivity = reflectivity.clamp(0.0, 1.0);
+        self
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self::new(
+            Vec3::broadcast(0.9),
+            Vec3::broadcast(0.9),
+            Vec3::one(),
+            1000.0,
+        )
+    }
+}
+
+// Metallic-Roughness material used by the physically based program. Every factor is multiplied
+// with its texture map, if one is set.
+#[derive(Debug, Clone)]
+pub struct PbrMaterial {
+    // Linear RGB base color, and alpha
+    pub(crate) albedo: Vec4,
+    pub(crate) metallic: f32,
+    pub(crate) roughness: f32,
+    pub(crate) ambient_occlusion: f32,
+    pub(crate) albedo_map: Option<Rc<Texture>>,
+    // Roughness is read from the green channel, metalness from the blue channel (as in glTF)
+    pub(crate) metallic_roughness_map: Option<Rc<Texture>>,
+    pub(crate) ambient_occlusion_map: Option<Rc<Texture>>,
+    // Tangent-Space normals
+    pub(crate) normal_map: Option<Rc<Texture>>,
+}
+
+impl PbrMaterial {
+    pub fn new(albedo: Vec3, metallic: f32, roughness: f32) -> Self {
+        Self {
+            albedo: Vec4::new(albedo.x, albedo.y, albedo.z, 1.0),
+            metallic: metallic.clamp(0.0, 1.0),
+            roughness: roughness.clamp(0.0, 1.0),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_alpha(mut self, alpha: f32) -> Self {
+        self.albedo.w = alpha.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_ambient_occlusion(mut self, ambient_occlusion: f32) -> Self {
+        self.ambient_occlusion = ambient_occlusion.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_albedo_map(mut self, map: Rc<Texture>) -> Self {
+        self.albedo_map = Some(map);
+        self
+    }
+
+    pub fn with_metallic_roughness_map(mut self, map: Rc<Texture>) -> Self {
+        self.metallic_roughness_map = Some(map);
+        self
+    }
+
+    pub fn with_ambient_occlusion_map(mut self, map: Rc<Texture>) -> Self {
+        self.ambient_occlusion_map = Some(map);
+        self
+    }
+
+    pub fn with_normal_map(mut self, map: Rc<Texture>) -> Self {
+        self.normal_map = Some(map);
+        self
+    }
+}
+
+impl Default for PbrMaterial {
+    fn default() -> Self {
+        Self {
+            albedo: Vec4::one(),
+            metallic: 0.0,
+            roughness: 0.5,
+            ambient_occlusion: 1.0,
+            albedo_map: None,
+            metallic_roughness_map: None,
+            ambient_occlusion_map: None,
+            normal_map: None,
         }
     }
 }
@@ -1,18 +1,24 @@
-use super::{BlinnPhong, CustomShader, FragmentOnly, GLProgram, GLWindow, ProgramError};
+use super::{
+    BlinnPhong, Compute, CustomShader, FragmentOnly, GLProgram, GLWindow, Pbr, ProgramError,
+};
 
 // All GLPrograms have a ShaderPipline which is composed of at least a VertexShader and
 // FragmentShader and may optionally have additional types of shaders
 use crate::{
     interface_blocks,
     shader::{
-        FragmentShader, GeometryShader, Shader, ShaderPipeline, TesselationShader, VertexShader,
+        blinn_phong, pbr, ComputeShader, Defines, FragmentShader, GeometryShader, Shader,
+        ShaderPipeline, TessControlShader, TessEvaluationShader, VertexShader,
     },
 };
 
 // // Convenience Error Type Alias
 type Result<T> = std::result::Result<T, ProgramError>;
 
+use crate::window::object::GLObject;
+use gl::types::GLenum;
 use std::collections::HashMap;
+use std::rc::Rc;
 
 // Dummy types to create a builder system for GLProgram
 #[derive(Debug)]
@@ -28,7 +34,8 @@ pub struct GLProgramBuilder<'a, W, V, F> {
     vertex_shader: V,
     fragment_shader: F,
     geometry_shader: Option<Shader<'a, GeometryShader>>,
-    tessellation_shader: Option<Shader<'a, TesselationShader>>,
+    tess_control_shader: Option<Shader<'a, TessControlShader>>,
+    tess_evaluation_shader: Option<Shader<'a, TessEvaluationShader>>,
 }
 
 impl<'a> GLProgram<'a, CustomShader> {
@@ -39,7 +46,8 @@ impl<'a> GLProgram<'a, CustomShader> {
             vertex_shader: NoVS,
             fragment_shader: NoFS,
             geometry_shader: None,
-            tessellation_shader: None,
+            tess_control_shader: None,
+            tess_evaluation_shader: None,
         }
     }
 
@@ -49,12 +57,13 @@ impl<'a> GLProgram<'a, CustomShader> {
         S: AsRef<str>,
     {
         // Initialize a window, context and OpenGL program
-        let (id, context) = initialize()?;
+        let (program, context) = initialize("fragment only")?;
+        let id = program.id();
         // Initialize and link shaders to the program
         let vs = Shader::<VertexShader>::fragment_only()?;
         let fragment_shader: Shader<'a, FragmentShader> =
             Shader::<'a, FragmentShader>::new(fragment_shader_source.as_ref())?;
-        let shaders = ShaderPipeline::new(id, vs, fragment_shader, None, None)?;
+        let shaders = ShaderPipeline::new(id, vs, fragment_shader, None, None, None)?;
         let uniforms = HashMap::new();
         let interface_blocks = HashMap::new();
         // Initialize the sub-structure of this <Type> of GLProgram
@@ -64,8 +73,10 @@ impl<'a> GLProgram<'a, CustomShader> {
 
         Ok(GLProgram {
             id,
+            program,
             uniforms,
             interface_blocks,
+            binding_leases: HashMap::new(),
             context,
             shaders,
             data,
@@ -74,56 +85,80 @@ impl<'a> GLProgram<'a, CustomShader> {
 
     // Shortcut to creating a GLProgram that users Blinn-Phong Shading
     pub fn phong() -> Result<GLProgram<'a, BlinnPhong>> {
-        let (id, context) = initialize()?;
-        let vs = Shader::<VertexShader>::blinn_phong()?;
-        let fs = Shader::<FragmentShader>::phong()?;
-        let shaders = ShaderPipeline::new(id, vs, fs, None, None)?;
-        let uniforms = HashMap::new();
-        let interface_blocks = HashMap::new();
-        let data = BlinnPhong::new();
-
-        let mut phong = GLProgram {
-            id,
-            context,
-            shaders,
-            uniforms,
-            interface_blocks,
-            data,
-        };
-        phong.initialize()?;
-        Ok(phong)
+        Self::new().use_default_window()?.phong()
     }
     pub fn blinn() -> Result<GLProgram<'a, BlinnPhong>> {
-        let (id, context) = initialize()?;
-        let vs = Shader::<VertexShader>::blinn_phong()?;
-        let fs = Shader::<FragmentShader>::blinn()?;
-        let shaders = ShaderPipeline::new(id, vs, fs, None, None)?;
-        let uniforms = HashMap::new();
-        let interface_blocks = HashMap::new();
-        let data = BlinnPhong::new();
+        Self::new().use_default_window()?.blinn()
+    }
 
-        let mut blinn = GLProgram {
-            id,
-            context,
-            shaders,
-            uniforms,
-            interface_blocks,
-            data,
-        };
-        blinn.initialize()?;
-        Ok(blinn)
+    // Shortcut to creating a GLProgram that uses physically based (metallic-roughness) shading
+    pub fn pbr() -> Result<GLProgram<'a, Pbr>> {
+        Self::new().use_default_window()?.pbr()
+    }
+
+    // Shortcut to creating a GLProgram from a lone compute shader
+    pub fn compute<S>(compute_shader_source: &'a S) -> Result<GLProgram<'a, Compute>>
+    where
+        S: AsRef<str>,
+    {
+        let (program, context) = initialize("compute")?;
+        compute(program, context, compute_shader_source.as_ref())
+    }
+
+    // Same as above, but in the window provided
+    pub fn compute_with_window<S>(
+        mut window: GLWindow,
+        compute_shader_source: &'a S,
+    ) -> Result<GLProgram<'a, Compute>>
+    where
+        S: AsRef<str>,
+    {
+        window.load_gl();
+        compute(
+            create_program("compute"),
+            window,
+            compute_shader_source.as_ref(),
+        )
     }
 }
 
-// Create a new window, and OpenGL context.
-impl<'a, NoWindow, V, F> GLProgramBuilder<'a, NoWindow, V, F> {
+// Compiles and links a compute shader on its own
+fn compute<'a>(
+    program: Rc<GLObject>,
+    context: GLWindow,
+    source: &'a str,
+) -> Result<GLProgram<'a, Compute>> {
+    let id = program.id();
+    let compute_shader = Shader::<ComputeShader>::new(source)?;
+    let shaders = ShaderPipeline::compute(id, compute_shader)?;
+    let uniforms = HashMap::new();
+    let interface_blocks = HashMap::new();
+    let data = Compute::new(id);
+
+    Ok(GLProgram {
+        id,
+        program,
+        context,
+        shaders,
+        uniforms,
+        interface_blocks,
+        binding_leases: HashMap::new(),
+        data,
+    })
+}
+
+// Create a new window, and OpenGL context. Shaders can only be compiled once there is one.
+impl<'a, V, F> GLProgramBuilder<'a, NoWindow, V, F> {
     // User provides the window
-    pub fn use_window(self, window: GLWindow) -> Result<GLProgramBuilder<'a, GLWindow, V, F>> {
+    pub fn use_window(self, mut window: GLWindow) -> Result<GLProgramBuilder<'a, GLWindow, V, F>> {
+        // Load pointers, using the context
+        window.load_gl();
         let GLProgramBuilder {
             vertex_shader,
             fragment_shader,
             geometry_shader,
-            tessellation_shader,
+            tess_control_shader,
+            tess_evaluation_shader,
             ..
         } = self;
         Ok(GLProgramBuilder {
@@ -131,7 +166,8 @@ impl<'a, NoWindow, V, F> GLProgramBuilder<'a, NoWindow, V, F> {
             vertex_shader,
             fragment_shader,
             geometry_shader,
-            tessellation_shader,
+            tess_control_shader,
+            tess_evaluation_shader,
         })
     }
 
@@ -141,21 +177,21 @@ impl<'a, NoWindow, V, F> GLProgramBuilder<'a, NoWindow, V, F> {
     }
 }
 
-// Allow the user to attach custom shaders to different parts of the graphics pipeline.
-impl<'a, W, NoVS, NoFS> GLProgramBuilder<'a, W, NoVS, NoFS> {
+// Allow the user to attach custom shaders to different parts of the graphics pipeline. Shaders are
+// compiled in the window's context, so it has to come first.
+impl<'a> GLProgramBuilder<'a, GLWindow, NoVS, NoFS> {
     pub fn with_shaders(
         self,
         vertex_shader: Shader<'a, VertexShader>,
         fragment_shader: Shader<'a, FragmentShader>,
         geometry_shader: Option<Shader<'a, GeometryShader>>,
-        tessellation_shader: Option<Shader<'a, TesselationShader>>,
-    ) -> GLProgramBuilder<'a, W, Shader<'a, VertexShader>, Shader<'a, FragmentShader>> {
+    ) -> GLProgramBuilder<'a, GLWindow, Shader<'a, VertexShader>, Shader<'a, FragmentShader>> {
         let gs = geometry_shader;
-        let ts = tessellation_shader;
         let GLProgramBuilder {
             window,
             geometry_shader,
-            tessellation_shader,
+            tess_control_shader,
+            tess_evaluation_shader,
             ..
         } = self;
         GLProgramBuilder {
@@ -163,22 +199,231 @@ impl<'a, W, NoVS, NoFS> GLProgramBuilder<'a, W, NoVS, NoFS> {
             vertex_shader,
             fragment_shader,
             geometry_shader: gs.or(geometry_shader),
-            tessellation_shader: ts.or(tessellation_shader),
+            tess_control_shader,
+            tess_evaluation_shader,
+        }
+    }
+}
+
+// The built-in programs, linked in the window provided (and loaded from its program binary cache,
+// if it has one)
+impl<'a> GLProgramBuilder<'a, GLWindow, NoVS, NoFS> {
+    pub fn phong(self) -> Result<GLProgram<'a, BlinnPhong>> {
+        blinn_phong(
+            self.window,
+            Defines::new().flag(blinn_phong::HALF_VECTOR_SPECULAR),
+        )
+    }
+
+    pub fn blinn(self) -> Result<GLProgram<'a, BlinnPhong>> {
+        blinn_phong(self.window, Defines::new())
+    }
+
+    pub fn pbr(self) -> Result<GLProgram<'a, Pbr>> {
+        let mut context = self.window;
+        let sources = [
+            (gl::VERTEX_SHADER, pbr::VERTEX_SHADER_SOURCE),
+            (gl::FRAGMENT_SHADER, pbr::FRAGMENT_SHADER_SOURCE),
+        ];
+        let (program, shaders) = built_in(&mut context, "pbr", &sources, &Defines::new())?;
+        let id = program.id();
+        let uniforms = HashMap::new();
+        let interface_blocks = HashMap::new();
+        let data = Pbr::new();
+
+        let mut pbr = GLProgram {
+            id,
+            program,
+            context,
+            shaders,
+            uniforms,
+            interface_blocks,
+            binding_leases: HashMap::new(),
+            data,
+        };
+        pbr.initialize()?;
+        pbr.initialize_materials()?;
+        Ok(pbr)
+    }
+}
+
+// Blinn and Phong are variants of the same shaders
+fn blinn_phong<'a>(mut context: GLWindow, defines: Defines) -> Result<GLProgram<'a, BlinnPhong>> {
+    let sources = [
+        (gl::VERTEX_SHADER, blinn_phong::VERTEX_SHADER_SOURCE),
+        (gl::FRAGMENT_SHADER, blinn_phong::FRAGMENT_SHADER_SOURCE),
+    ];
+    let (program, shaders) = built_in(&mut context, "blinn-phong", &sources, &defines)?;
+    let id = program.id();
+    let uniforms = HashMap::new();
+    let interface_blocks = HashMap::new();
+    let data = BlinnPhong::new();
+
+    let mut program = GLProgram {
+        id,
+        program,
+        context,
+        shaders,
+        uniforms,
+        interface_blocks,
+        binding_leases: HashMap::new(),
+        data,
+    };
+    program.initialize()?;
+    program.initialize_materials()?;
+    Ok(program)
+}
+
+// The built-in programs are variants like any other: compiled and linked once per context, and
+// loaded from the window's program binary cache the first time, if it has one
+fn built_in<'a>(
+    context: &mut GLWindow,
+    name: &str,
+    sources: &[(GLenum, &str); 2],
+    defines: &Defines,
+) -> Result<(Rc<GLObject>, ShaderPipeline<'a>)> {
+    context.make_current();
+    let vertex_shader = context.variants.shader(sources[0].1, defines)?;
+    let fragment_shader = context.variants.shader(sources[1].1, defines)?;
+    let shaders = ShaderPipeline::linked(vertex_shader, fragment_shader, None, None, None);
+    let shader_ids = shaders.shader_ids();
+    let program = match context.variants.program(&shader_ids) {
+        Some(program) => program,
+        None => {
+            let program = create_program(name);
+            let cache = context.program_binaries.as_ref();
+            shaders.link_cached(program.id(), sources, defines, cache)?;
+            context.variants.insert_program(&shader_ids, &program);
+            program
         }
+    };
+    Ok((program, shaders))
+}
+
+// Variants of shared sources are compiled once per context, so they need the window first. A
+// program owns its window, so the variants are only reused by programs built one after another
+// from it, handed on with `into_window()`.
+impl<'a> GLProgramBuilder<'a, GLWindow, NoVS, NoFS> {
+    pub fn with_variant(
+        self,
+        vertex_source: &str,
+        fragment_source: &str,
+        defines: &Defines,
+    ) -> Result<GLProgramBuilder<'a, GLWindow, Shader<'a, VertexShader>, Shader<'a, FragmentShader>>>
+    {
+        let GLProgramBuilder {
+            mut window,
+            geometry_shader,
+            tess_control_shader,
+            tess_evaluation_shader,
+            ..
+        } = self;
+        window.make_current();
+        let vertex_shader = window.variants.shader(vertex_source, defines)?;
+        let fragment_shader = window.variants.shader(fragment_source, defines)?;
+        Ok(GLProgramBuilder {
+            window,
+            vertex_shader,
+            fragment_shader,
+            geometry_shader,
+            tess_control_shader,
+            tess_evaluation_shader,
+        })
+    }
+}
+
+// Tessellates the patches drawn with `DrawStyle::Patches`. The control shader is optional.
+impl<'a, V, F> GLProgramBuilder<'a, GLWindow, V, F> {
+    pub fn with_tessellation(
+        self,
+        tess_control_shader: Option<Shader<'a, TessControlShader>>,
+        tess_evaluation_shader: Shader<'a, TessEvaluationShader>,
+    ) -> GLProgramBuilder<'a, GLWindow, V, F> {
+        GLProgramBuilder {
+            tess_control_shader,
+            tess_evaluation_shader: Some(tess_evaluation_shader),
+            ..self
+        }
+    }
+}
+
+// Links the shaders against the window's context
+impl<'a> GLProgramBuilder<'a, GLWindow, Shader<'a, VertexShader>, Shader<'a, FragmentShader>> {
+    pub fn build(self) -> Result<GLProgram<'a, CustomShader>> {
+        let GLProgramBuilder {
+            window: mut context,
+            vertex_shader,
+            fragment_shader,
+            geometry_shader,
+            tess_control_shader,
+            tess_evaluation_shader,
+        } = self;
+        context.make_current();
+
+        // Catch shaders compiled for the wrong stage, or that failed to compile, before linking
+        vertex_shader.validate_stage(gl::VERTEX_SHADER)?;
+        fragment_shader.validate_stage(gl::FRAGMENT_SHADER)?;
+        if let Some(geometry_shader) = geometry_shader.as_ref() {
+            geometry_shader.validate_stage(gl::GEOMETRY_SHADER)?;
+        }
+        if let Some(tess_control_shader) = tess_control_shader.as_ref() {
+            tess_control_shader.validate_stage(gl::TESS_CONTROL_SHADER)?;
+        }
+        if let Some(tess_evaluation_shader) = tess_evaluation_shader.as_ref() {
+            tess_evaluation_shader.validate_stage(gl::TESS_EVALUATION_SHADER)?;
+        }
+
+        // Programs linked from the same cached variants are reused
+        let shaders = ShaderPipeline::linked(
+            vertex_shader,
+            fragment_shader,
+            geometry_shader,
+            tess_control_shader,
+            tess_evaluation_shader,
+        );
+        let shader_ids = shaders.shader_ids();
+        let program = match context.variants.program(&shader_ids) {
+            Some(program) => program,
+            None => {
+                let program = create_program("custom shader");
+                shaders.link(program.id())?;
+                context.variants.insert_program(&shader_ids, &program);
+                program
+            }
+        };
+        let id = program.id();
+        let uniforms = HashMap::new();
+        let interface_blocks = HashMap::new();
+        let data = CustomShader::new();
+
+        let mut program = GLProgram {
+            id,
+            program,
+            context,
+            shaders,
+            uniforms,
+            interface_blocks,
+            binding_leases: HashMap::new(),
+            data,
+        };
+        program.initialize()?;
+        program.validate_scene()?;
+        Ok(program)
     }
 }
 
 // Every constructor creates a new program ID, creates a window + context, and initializes the OpenGL pointers
 #[inline(always)]
-fn initialize() -> Result<(gl::types::GLuint, GLWindow)> {
+fn initialize(name: &str) -> Result<(Rc<GLObject>, GLWindow)> {
     // Load pointers, using the context
     let mut context = GLWindow::default()?;
-    gl::load_with(|symbol| context.window.get_proc_address(symbol) as *const _);
-    let id = create_program_id();
-    Ok((id, context))
+    context.load_gl();
+    Ok((create_program(name), context))
 }
 
 #[inline(always)]
-fn create_program_id() -> gl::types::GLuint {
-    unsafe { gl::CreateProgram() }
+fn create_program(name: &str) -> Rc<GLObject> {
+    let program = GLObject::program();
+    program.label(name);
+    Rc::new(program)
 }
@@ -0,0 +1,338 @@
+// Clustered forward lighting: lights are binned on the CPU into a grid of view-space clusters
+// (screen-space tiles, sliced exponentially by depth) so each fragment only shades the handful of
+// lights that can actually reach it.
+use super::lights::LightSource;
+
+// OpenGL Types
+use crate::window::object::GLObject;
+use gl::types::*;
+
+// Linear Algebra Types
+use ultraviolet::mat::Mat4;
+use ultraviolet::vec::{Vec2, Vec3};
+
+use std::ffi::c_void;
+use std::mem::size_of_val;
+
+// Shader Storage binding points the built-in fragment shaders read from
+pub const LIGHTS_BINDING: GLuint = 1;
+pub const CLUSTERS_BINDING: GLuint = 2;
+pub const LIGHT_INDICES_BINDING: GLuint = 3;
+
+// How the view frustum is divided into clusters
+#[derive(Debug, Clone, Copy)]
+pub struct ClusterSettings {
+    // Number of screen-space tiles along the x and y axes
+    pub tiles_x: u32,
+    pub tiles_y: u32,
+    // Number of exponentially spaced depth slices between `near` and `far`
+    pub slices: u32,
+    pub near: f32,
+    pub far: f32,
+    // When false every fragment loops over every light
+    pub enabled: bool,
+}
+
+impl Default for ClusterSettings {
+    fn default() -> Self {
+        ClusterSettings {
+            tiles_x: 16,
+            tiles_y: 9,
+            slices: 24,
+            near: 0.1,
+            far: 10000.0,
+            enabled: true,
+        }
+    }
+}
+
+// Owns the lights, and the per-cluster light lists, as Shader Storage Buffers
+pub(crate) struct LightClusters {
+    settings: ClusterSettings,
+    lights_buffer: GLObject,
+    clusters_buffer: GLObject,
+    indices_buffer: GLObject,
+    // Clusters must be rebuilt when the lights, the camera, or the settings change
+    dirty: bool,
+}
+
+// Inclusive range of clusters along each axis that a light overlaps
+#[derive(Debug, Clone, Copy)]
+struct ClusterRange {
+    x: (usize, usize),
+    y: (usize, usize),
+    z: (usize, usize),
+}
+
+impl LightClusters {
+    pub(crate) fn new() -> Self {
+        let clusters = LightClusters {
+            settings: ClusterSettings::default(),
+            lights_buffer: GLObject::buffer(),
+            clusters_buffer: GLObject::buffer(),
+            indices_buffer: GLObject::buffer(),
+            dirty: true,
+        };
+        buffer_storage::<[u32; 2]>(clusters.clusters_buffer.id(), &[]);
+        buffer_storage::<u32>(clusters.indices_buffer.id(), &[]);
+        // Created empty too, so it can be labelled before there are lights
+        buffer_storage::<LightSource>(clusters.lights_buffer.id(), &[]);
+        // Named after the storage blocks they back
+        clusters.lights_buffer.label("Lights");
+        clusters.clusters_buffer.label("LightClusters");
+        clusters.indices_buffer.label("LightIndices");
+        clusters
+    }
+
+    // Buffers the lights to the GPU, and marks the clusters for rebuilding
+    pub(crate) fn buffer_lights(&mut self, lights: &[LightSource]) {
+        buffer_storage(self.lights_buffer.id(), lights);
+        self.dirty = true;
+    }
+
+    pub(crate) fn set_settings(&mut self, settings: ClusterSettings) {
+        self.settings = ClusterSettings {
+            tiles_x: settings.tiles_x.max(1),
+            tiles_y: settings.tiles_y.max(1),
+            slices: settings.slices.max(1),
+            near: settings.near.max(f32::EPSILON),
+            far: settings.far.max(settings.near + f32::EPSILON),
+            enabled: settings.enabled,
+        };
+        self.dirty = true;
+    }
+
+    // Call when the camera moves
+    pub(crate) fn invalidate(&mut self) {
+        self.dirty = true;
+    }
+
+    // Value of the `cluster_grid` uniform
+    pub(crate) fn grid(&self) -> (GLuint, GLuint, GLuint, GLuint) {
+        let s = &self.settings;
+        (s.tiles_x, s.tiles_y, s.slices, s.enabled as GLuint)
+    }
+
+    // Value of the `cluster_depth` uniform for a framebuffer of the given size
+    pub(crate) fn depth(&self, width: i32, height: i32) -> (f32, f32, f32, f32) {
+        let s = &self.settings;
+        let tile_width = (width.max(1) as f32) / s.tiles_x as f32;
+        let tile_height = (height.max(1) as f32) / s.tiles_y as f32;
+        (tile_width, tile_height, s.near, s.far)
+    }
+
+    // Re-bins the lights if anything changed since the last call
+    pub(crate) fn update(&mut self, lights: &[LightSource], view: &Mat4, projection: &Mat4) {
+        if !self.dirty || !self.settings.enabled {
+            return;
+        }
+
+        let s = self.settings;
+        let (nx, ny, nz) = (s.tiles_x as usize, s.tiles_y as usize, s.slices as usize);
+        let mut bins: Vec<Vec<u32>> = vec![Vec::new(); nx * ny * nz];
+        for (index, light) in lights.iter().enumerate() {
+            if let Some(range) = cluster_range(light, view, projection, &s) {
+                for z in range.z.0..=range.z.1 {
+                    for y in range.y.0..=range.y.1 {
+                        for x in range.x.0..=range.x.1 {
+                            bins[x + y * nx + z * nx * ny].push(index as u32);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Flatten into (offset, count) pairs into one contiguous list of light indices
+        let mut clusters = Vec::with_capacity(bins.len());
+        let mut indices = Vec::new();
+        for bin in bins {
+            clusters.push([indices.len() as u32, bin.len() as u32]);
+            indices.extend(bin);
+        }
+        buffer_storage(self.clusters_buffer.id(), &clusters);
+        buffer_storage(self.indices_buffer.id(), &indices);
+        self.dirty = false;
+    }
+
+    // Binds the buffers to the binding points the built-in fragment shaders expect
+    pub(crate) fn bind(&self) {
+        unsafe {
+            gl::BindBufferBase(
+                gl::SHADER_STORAGE_BUFFER,
+                LIGHTS_BINDING,
+                self.lights_buffer.id(),
+            );
+            gl::BindBufferBase(
+                gl::SHADER_STORAGE_BUFFER,
+                CLUSTERS_BINDING,
+                self.clusters_buffer.id(),
+            );
+            gl::BindBufferBase(
+                gl::SHADER_STORAGE_BUFFER,
+                LIGHT_INDICES_BINDING,
+                self.indices_buffer.id(),
+            );
+        }
+    }
+}
+
+// Replaces the contents of a Shader Storage Buffer. Empty buffers can't be bound, so they're
+// padded out to a single vec4.
+fn buffer_storage<T>(buffer: GLuint, data: &[T]) {
+    let size = size_of_val(data).max(16) as GLsizeiptr;
+    let ptr = match data.is_empty() {
+        true => std::ptr::null(),
+        false => data.as_ptr() as *const c_void,
+    };
+    unsafe {
+        gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, buffer);
+        gl::BufferData(gl::SHADER_STORAGE_BUFFER, size, ptr, gl::DYNAMIC_DRAW);
+        gl::BindBuffer(gl::SHADER_STORAGE_BUFFER, 0);
+    }
+}
+
+// Finds the clusters a light's sphere of influence overlaps, None if it is entirely off screen
+fn cluster_range(
+    light: &LightSource,
+    view: &Mat4,
+    projection: &Mat4,
+    settings: &ClusterSettings,
+) -> Option<ClusterRange> {
+    let (nx, ny, nz) = (
+        settings.tiles_x as usize,
+        settings.tiles_y as usize,
+        settings.slices as usize,
+    );
+    let everywhere = ClusterRange {
+        x: (0, nx - 1),
+        y: (0, ny - 1),
+        z: (0, nz - 1),
+    };
+
+    // Directional lights, and lights without a range, reach every cluster
+    let range = match light.range() {
+        Some(range) => range,
+        None => return Some(everywhere),
+    };
+
+    // View-Space looks down the -z axis
+    let center = (*view * light.position).truncated();
+    let nearest = -center.z - range;
+    let farthest = -center.z + range;
+    if farthest < settings.near || nearest > settings.far {
+        return None;
+    }
+    let z = (
+        depth_slice(nearest, settings),
+        depth_slice(farthest, settings),
+    );
+
+    // Lights surrounding the camera cover the whole screen
+    if nearest <= settings.near {
+        return Some(ClusterRange { z, ..everywhere });
+    }
+
+    // Project the corners of the light's bounding box to find the tiles it covers
+    let mut min = Vec2::broadcast(f32::MAX);
+    let mut max = Vec2::broadcast(f32::MIN);
+    for corner in 0..8 {
+        let offset = Vec3::new(
+            if corner & 1 == 0 { -range } else { range },
+            if corner & 2 == 0 { -range } else { range },
+            if corner & 4 == 0 { -range } else { range },
+        );
+        let clip = *projection * (center + offset).into_homogeneous_point();
+        let ndc = Vec2::new(clip.x / clip.w, clip.y / clip.w);
+        min = min.min_by_component(ndc);
+        max = max.max_by_component(ndc);
+    }
+    if max.x < -1.0 || max.y < -1.0 || min.x > 1.0 || min.y > 1.0 {
+        return None;
+    }
+
+    Some(ClusterRange {
+        x: (tile(min.x, nx), tile(max.x, nx)),
+        y: (tile(min.y, ny), tile(max.y, ny)),
+        z,
+    })
+}
+
+// Normalized Device Coordinate -> tile index
+fn tile(ndc: f32, count: usize) -> usize {
+    let tile = ((ndc * 0.5 + 0.5) * count as f32).floor().max(0.0) as usize;
+    tile.min(count - 1)
+}
+
+// View-Space depth -> exponential depth slice index (must match `cluster_index()` in the shaders)
+fn depth_slice(depth: f32, settings: &ClusterSettings) -> usize {
+    let depth = depth.max(settings.near);
+    let slices = settings.slices as usize;
+    let slice = (depth / settings.near).ln() / (settings.far / settings.near).ln() * slices as f32;
+    (slice.floor().max(0.0) as usize).min(slices - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::program::lights::{LightColor, Position};
+    use ultraviolet::projection::perspective_gl;
+
+    #[test]
+    fn ndc_edges_fall_in_the_outermost_tiles() {
+        assert_eq!(tile(-1.0, 16), 0);
+        assert_eq!(tile(1.0, 16), 15);
+        assert_eq!(tile(0.0, 16), 8);
+        // Off-screen coordinates are clamped to the screen
+        assert_eq!(tile(-1.5, 16), 0);
+        assert_eq!(tile(1.5, 16), 15);
+    }
+
+    #[test]
+    fn depths_at_the_planes_fall_in_the_outermost_slices() {
+        let settings = ClusterSettings::default();
+        let last = settings.slices as usize - 1;
+        assert_eq!(depth_slice(settings.near, &settings), 0);
+        assert_eq!(depth_slice(settings.far, &settings), last);
+        // Depths outside the frustum are clamped to it
+        assert_eq!(depth_slice(0.0, &settings), 0);
+        assert_eq!(depth_slice(settings.far * 2.0, &settings), last);
+    }
+
+    #[test]
+    fn lights_cover_the_tiles_they_project_onto() {
+        let settings = ClusterSettings::default();
+        let view = Mat4::identity();
+        let projection = perspective_gl(
+            std::f32::consts::FRAC_PI_2,
+            1.0,
+            settings.near,
+            settings.far,
+        );
+
+        // A light 10 units in front of the camera reaching 3 units spans several tiles and slices
+        let light =
+            LightSource::point(&LightColor::RED, &Position::new(0.0, 0.0, -10.0)).with_range(3.0);
+        let range = cluster_range(&light, &view, &projection, &settings).unwrap();
+        assert_eq!(range.x, (4, 11));
+        assert_eq!(range.y, (2, 6));
+        assert_eq!(
+            range.z,
+            (depth_slice(7.0, &settings), depth_slice(13.0, &settings))
+        );
+        assert!(range.z.0 < range.z.1);
+
+        // Lights surrounding the camera cover every tile
+        let light =
+            LightSource::point(&LightColor::RED, &Position::new(0.0, 0.0, -1.0)).with_range(3.0);
+        let range = cluster_range(&light, &view, &projection, &settings).unwrap();
+        assert_eq!((range.x, range.y), ((0, 15), (0, 8)));
+
+        // Lights behind the camera cover none, and lights without a range cover them all
+        let light =
+            LightSource::point(&LightColor::RED, &Position::new(0.0, 0.0, 10.0)).with_range(3.0);
+        assert!(cluster_range(&light, &view, &projection, &settings).is_none());
+        let light = LightSource::point(&LightColor::RED, &Position::new(0.0, 0.0, 10.0));
+        let range = cluster_range(&light, &view, &projection, &settings).unwrap();
+        assert_eq!((range.x, range.y, range.z), ((0, 15), (0, 8), (0, 23)));
+    }
+}
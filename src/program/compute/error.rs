@@ -0,0 +1,51 @@
+use gl::types::GLuint;
+
+// Error type for Compute Programs
+#[derive(Debug)]
+pub enum ComputeError {
+    // More work groups than the context can dispatch at once
+    WorkGroupCount {
+        groups: [GLuint; 3],
+        max: [GLuint; 3],
+    },
+    IndirectCommand {
+        index: usize,
+        count: usize,
+    },
+}
+
+impl std::error::Error for ComputeError {}
+impl std::fmt::Display for ComputeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ComputeError::WorkGroupCount { groups, max } => {
+                write!(
+                    f,
+                    "Cannot dispatch {:?} work groups, at most {:?} can be dispatched at once.",
+                    groups, max
+                )
+            }
+            ComputeError::IndirectCommand { index, count } => {
+                write!(
+                    f,
+                    "No indirect dispatch command at index {}, the buffer only holds {}.",
+                    index, count
+                )
+            }
+        }
+    }
+}
+
+// Allows for painless casting into our crate's rollup error
+impl From<ComputeError> for crate::GLError {
+    fn from(error: ComputeError) -> Self {
+        crate::GLError::Compute(error)
+    }
+}
+
+// Allows for painless casting into our crate's rollup error
+impl From<ComputeError> for crate::program::ProgramError {
+    fn from(error: ComputeError) -> Self {
+        crate::program::ProgramError::Compute(error)
+    }
+}
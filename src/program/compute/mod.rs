@@ -0,0 +1,207 @@
+// Programs made of a lone compute shader, e.g. for simulations and culling passes on the GPU. They
+// read and write shader storage blocks and images, and are dispatched rather than drawn.
+pub mod error;
+pub use error::ComputeError;
+
+use super::{GLProgram, ProgramError};
+use crate::interface_blocks::{Attached, BlockLayout, InterfaceBlock, InterfaceBuffer, Shader};
+use crate::interface_blocks::{Std140, Std430};
+use crate::texture::Texture;
+use crate::uniform::ImageUnit;
+
+// OpenGL Types
+use gl::types::*;
+
+use bitflags::bitflags;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+// Convenience Error Type Alias
+type Result<T> = std::result::Result<T, ProgramError>;
+
+// GLProgram sub-type sub-structure
+pub struct Compute {
+    // Invocations per work group, as declared by `layout(local_size_x = ...) in;`
+    work_group_size: [GLuint; 3],
+    // Image units the program's images are bound to, by uniform name
+    image_units: HashMap<Rc<str>, GLuint>,
+}
+
+impl Compute {
+    pub(crate) fn new(program_id: GLuint) -> Self {
+        let mut work_group_size = [0; 3];
+        unsafe {
+            gl::GetProgramiv(
+                program_id,
+                gl::COMPUTE_WORK_GROUP_SIZE,
+                work_group_size.as_mut_ptr(),
+            );
+        }
+        Compute {
+            work_group_size: work_group_size.map(|size| size.max(1) as GLuint),
+            image_units: HashMap::new(),
+        }
+    }
+}
+
+// Laid out the way `glDispatchComputeIndirect()` reads it, so a shader can write how many work
+// groups to dispatch next into a storage block of them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Std140, Std430)]
+#[repr(C)]
+pub struct DispatchIndirectCommand {
+    pub num_groups_x: GLuint,
+    pub num_groups_y: GLuint,
+    pub num_groups_z: GLuint,
+}
+
+// How a shader may access an image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageAccess {
+    ReadOnly,
+    WriteOnly,
+    ReadWrite,
+}
+
+// Which incoherent writes (to storage blocks, images, et al.) later commands have to be able to see
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Barriers: GLbitfield {
+        const VERTEX_ATTRIB_ARRAY = gl::VERTEX_ATTRIB_ARRAY_BARRIER_BIT;
+        const ELEMENT_ARRAY = gl::ELEMENT_ARRAY_BARRIER_BIT;
+        const UNIFORM = gl::UNIFORM_BARRIER_BIT;
+        const TEXTURE_FETCH = gl::TEXTURE_FETCH_BARRIER_BIT;
+        const SHADER_IMAGE_ACCESS = gl::SHADER_IMAGE_ACCESS_BARRIER_BIT;
+        // Indirect draw and dispatch commands
+        const COMMAND = gl::COMMAND_BARRIER_BIT;
+        const PIXEL_BUFFER = gl::PIXEL_BUFFER_BARRIER_BIT;
+        const TEXTURE_UPDATE = gl::TEXTURE_UPDATE_BARRIER_BIT;
+        // Reading buffers back to the CPU
+        const BUFFER_UPDATE = gl::BUFFER_UPDATE_BARRIER_BIT;
+        const FRAMEBUFFER = gl::FRAMEBUFFER_BARRIER_BIT;
+        const TRANSFORM_FEEDBACK = gl::TRANSFORM_FEEDBACK_BARRIER_BIT;
+        const ATOMIC_COUNTER = gl::ATOMIC_COUNTER_BARRIER_BIT;
+        const SHADER_STORAGE = gl::SHADER_STORAGE_BARRIER_BIT;
+        const CLIENT_MAPPED_BUFFER = gl::CLIENT_MAPPED_BUFFER_BARRIER_BIT;
+        const QUERY_BUFFER = gl::QUERY_BUFFER_BARRIER_BIT;
+        const ALL = gl::ALL_BARRIER_BITS;
+    }
+}
+
+// e.g. `memory_barrier(Barriers::SHADER_STORAGE)` between a dispatch writing a storage block and
+// the draw reading it
+pub fn memory_barrier(barriers: Barriers) {
+    unsafe {
+        gl::MemoryBarrier(barriers.bits());
+    }
+}
+
+impl<'a> GLProgram<'a, Compute> {
+    pub fn work_group_size(&self) -> [GLuint; 3] {
+        self.data.work_group_size
+    }
+
+    // Runs x * y * z work groups
+    pub fn dispatch(&self, x: GLuint, y: GLuint, z: GLuint) -> Result<()> {
+        let groups = [x, y, z];
+        let max = max_work_group_count();
+        if groups.iter().zip(max).any(|(groups, max)| *groups > max) {
+            return Err(ComputeError::WorkGroupCount { groups, max }.into());
+        }
+        unsafe {
+            gl::UseProgram(self.id);
+            gl::DispatchCompute(x, y, z);
+        }
+        Ok(())
+    }
+
+    // Runs enough work groups for (at least) x * y * z invocations
+    pub fn dispatch_invocations(&self, x: GLuint, y: GLuint, z: GLuint) -> Result<()> {
+        let [size_x, size_y, size_z] = self.data.work_group_size;
+        self.dispatch(x.div_ceil(size_x), y.div_ceil(size_y), z.div_ceil(size_z))
+    }
+
+    // Runs the number of work groups in `commands[index]`, which a previous dispatch may have
+    // written (after a `Barriers::COMMAND` barrier)
+    pub fn dispatch_indirect<T>(
+        &self,
+        commands: &InterfaceBlock<Shader, T, DispatchIndirectCommand, Attached>,
+        index: usize,
+    ) -> Result<()>
+    where
+        T: BlockLayout + 'static,
+        DispatchIndirectCommand: crate::interface_blocks::Layout<T::Rules>,
+    {
+        if index >= commands.len() {
+            let count = commands.len();
+            return Err(ComputeError::IndirectCommand { index, count }.into());
+        }
+        let stride =
+            crate::interface_blocks::layout::array_stride::<T::Rules, DispatchIndirectCommand>();
+        unsafe {
+            gl::UseProgram(self.id);
+            gl::BindBuffer(gl::DISPATCH_INDIRECT_BUFFER, commands.buffer_id());
+            gl::DispatchComputeIndirect((index * stride) as GLintptr);
+            gl::BindBuffer(gl::DISPATCH_INDIRECT_BUFFER, 0);
+        }
+        Ok(())
+    }
+
+    // Binds level 0 of the texture to an image unit, and sets the `image*` uniform `name` to it
+    pub fn bind_image<S>(&mut self, name: S, texture: &Texture, access: ImageAccess) -> Result<()>
+    where
+        S: AsRef<str>,
+    {
+        let next_unit = self.data.image_units.len() as GLuint;
+        let unit = *self
+            .data
+            .image_units
+            .entry(Rc::from(name.as_ref()))
+            .or_insert(next_unit);
+
+        let access = match access {
+            ImageAccess::ReadOnly => gl::READ_ONLY,
+            ImageAccess::WriteOnly => gl::WRITE_ONLY,
+            ImageAccess::ReadWrite => gl::READ_WRITE,
+        };
+        // Every layer of array, cube, and 3D textures
+        let layered = matches!(
+            texture.target,
+            gl::TEXTURE_2D_ARRAY
+                | gl::TEXTURE_CUBE_MAP
+                | gl::TEXTURE_CUBE_MAP_ARRAY
+                | gl::TEXTURE_3D
+        );
+        let mut format = 0;
+        unsafe {
+            gl::GetTextureLevelParameteriv(
+                texture.id(),
+                0,
+                gl::TEXTURE_INTERNAL_FORMAT,
+                &mut format,
+            );
+            gl::BindImageTexture(
+                unit,
+                texture.id(),
+                0,
+                layered as GLboolean,
+                0,
+                access,
+                format as GLenum,
+            );
+        }
+        self.uniform::<ImageUnit, _>(name)?
+            .set(&ImageUnit(unit as GLint));
+        Ok(())
+    }
+}
+
+// Most work groups that can be dispatched along each axis
+fn max_work_group_count() -> [GLuint; 3] {
+    let mut max = [0; 3];
+    for (axis, max) in max.iter_mut().enumerate() {
+        unsafe {
+            gl::GetIntegeri_v(gl::MAX_COMPUTE_WORK_GROUP_COUNT, axis as GLuint, max);
+        }
+    }
+    max.map(|max| max.max(0) as GLuint)
+}
@@ -0,0 +1,77 @@
+// Programs linked from the user's own shaders. They render a `Scene` like the built-in programs
+// do, through whichever of the `SCENE_INPUTS` their shaders declare.
+use super::scene::{Scene, SceneData};
+use super::{GLDraw, GLProgram, ProgramError};
+
+// A way to easily implement, and update common per-frame GLSL Uniform values
+use crate::uniform::MagicUniform;
+
+// Convenience Error Type Alias
+type Result<T> = std::result::Result<T, ProgramError>;
+
+// GLProgram sub-type sub-structure
+pub struct CustomShader {
+    scene: Scene,
+    uniforms: MagicUniform,
+}
+
+impl CustomShader {
+    pub(crate) fn new() -> Self {
+        CustomShader {
+            scene: Scene::new(),
+            uniforms: MagicUniform::NONE,
+        }
+    }
+}
+
+impl SceneData for CustomShader {
+    fn scene(&self) -> &Scene {
+        &self.scene
+    }
+
+    fn scene_mut(&mut self) -> &mut Scene {
+        &mut self.scene
+    }
+}
+
+impl<'a> GLProgram<'a, CustomShader> {
+    // Enables a magic uniform value, the shaders must declare (and use) it
+    pub fn enable_uniform(mut self, uniform: MagicUniform) -> Result<Self> {
+        if uniform.contains(MagicUniform::TIME) {
+            self.create_uniform("time", &0.0f32)?;
+        }
+        if uniform.contains(MagicUniform::RESOLUTION) {
+            self.create_uniform("resolution", &(1024.0f32, 1024.0f32))?;
+        }
+
+        self.data.uniforms |= uniform;
+        Ok(self)
+    }
+
+    // Checks which magic uniforms are enabled and then sets them accordingly
+    fn update_magic_uniforms(&self) -> Result<()> {
+        let frame_state = &self.context.frame_state;
+        if self.data.uniforms.contains(MagicUniform::TIME) {
+            self.update_uniform("time", &frame_state.time.as_secs_f32())?;
+        }
+        if self.data.uniforms.contains(MagicUniform::RESOLUTION) {
+            if let Some((x, y)) = frame_state.resolution {
+                self.update_uniform("resolution", &(x, y))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> GLDraw for GLProgram<'a, CustomShader> {
+    fn draw(&mut self) -> Result<()> {
+        self.prepare_scene()?;
+        self.update_magic_uniforms()?;
+
+        for mesh in self.data.scene.meshes.values_mut() {
+            mesh.draw()?;
+        }
+
+        self.finish_scene()
+    }
+}
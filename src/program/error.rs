@@ -1,8 +1,14 @@
+use super::compute::ComputeError;
 use super::mesh::MeshError;
 use crate::interface_blocks::InterfaceBlockError;
+use crate::program::pbr::PbrError;
+use crate::program::scene::SceneError;
 use crate::program::scene_object::SceneObjectError;
+use crate::program::shadows::ShadowError;
 use crate::program::vao::VAOError;
+use crate::render_graph::RenderGraphError;
 use crate::shader::error::ShaderError;
+use crate::texture::TextureError;
 use crate::uniform::UniformError;
 use crate::window::WindowError;
 
@@ -18,6 +24,12 @@ pub enum ProgramError {
     VAO(VAOError),
     Window(WindowError),
     SceneObject(SceneObjectError),
+    Scene(SceneError),
+    Shadow(ShadowError),
+    Pbr(PbrError),
+    Texture(TextureError),
+    RenderGraph(RenderGraphError),
+    Compute(ComputeError),
     Mesh(MeshError),
     Uniform(UniformError),
     InterfaceBlock(InterfaceBlockError),
@@ -57,6 +69,24 @@ impl std::fmt::Display for ProgramError {
             ProgramError::SceneObject(error) => {
                 write!(f, "SceneObject ERROR: '{}'.\n", error)
             }
+            ProgramError::Scene(error) => {
+                writeln!(f, "Scene ERROR: '{}'.", error)
+            }
+            ProgramError::Shadow(error) => {
+                writeln!(f, "Shadow ERROR: '{}'.", error)
+            }
+            ProgramError::Pbr(error) => {
+                writeln!(f, "PBR ERROR: '{}'.", error)
+            }
+            ProgramError::Texture(error) => {
+                writeln!(f, "Texture ERROR: '{}'.", error)
+            }
+            ProgramError::RenderGraph(error) => {
+                writeln!(f, "Render Graph ERROR: '{}'.", error)
+            }
+            ProgramError::Compute(error) => {
+                writeln!(f, "Compute ERROR: '{}'.", error)
+            }
             ProgramError::Uniform(error) => {
                 write!(f, "Uniform ERROR: '{}'.\n", error)
             }
@@ -3,6 +3,7 @@ use super::{GLDraw, GLProgram, ProgramError};
 
 // Custom types to play nice with OpenGL
 use crate::types::*;
+use crate::window::object::GLObject;
 use gl::types::*;
 
 // A way to easily implement, and update common per-frame GLSL Uniform values
@@ -21,6 +22,9 @@ type Result<T> = std::result::Result<T, ProgramError>;
 // GLProgram sub-type sub-structure
 pub struct FragmentOnly {
     uniforms: MagicUniform,
+    // The triangle's vertex array and buffer, which stay bound for every draw
+    #[allow(dead_code)]
+    triangle: Option<[GLObject; 2]>,
 }
 
 impl FragmentOnly {
@@ -30,23 +34,26 @@ impl FragmentOnly {
         // Initialize the vertices for the vertex shader since they will never change
         let (ptr, size, stride, location) = FragmentOnly::VERTICES;
 
+        let vao = GLObject::vertex_array();
+        let buffer = GLObject::buffer();
         unsafe {
             gl::UseProgram(program_id);
-            let mut vao: GLuint = 0;
-            gl::GenVertexArrays(1, &mut vao);
-            gl::BindVertexArray(vao);
+            gl::BindVertexArray(vao.id());
 
             // Buffer the vertices to the GPU
-            let mut buffer_id: GLuint = 0;
-            gl::GenBuffers(1, &mut buffer_id);
-            gl::BindBuffer(gl::ARRAY_BUFFER, buffer_id);
+            gl::BindBuffer(gl::ARRAY_BUFFER, buffer.id());
             gl::BufferData(gl::ARRAY_BUFFER, size, ptr, gl::STATIC_DRAW);
 
             // Create our sole Vetex Attray Object
             gl::VertexAttribPointer(location, 3, gl::FLOAT, gl::FALSE, stride, std::ptr::null());
             gl::EnableVertexAttribArray(location);
         }
-        FragmentOnly::default()
+        vao.label("full screen triangle");
+        buffer.label("full screen triangle vertices");
+        FragmentOnly {
+            triangle: Some([vao, buffer]),
+            ..FragmentOnly::default()
+        }
     }
 
     // Since we always draw the same triangle, just store it as a constant, along with it's
@@ -68,6 +75,7 @@ impl Default for FragmentOnly {
     fn default() -> Self {
         FragmentOnly {
             uniforms: MagicUniform::NONE,
+            triangle: None,
         }
     }
 }
@@ -78,10 +86,10 @@ impl<'a> GLProgram<'a, FragmentOnly> {
     pub fn enable_uniform(mut self, uniform: MagicUniform) -> Result<Self> {
         match uniform {
             MagicUniform::TIME => {
-                self.create_uniform("time", &0.0)?;
+                self.create_uniform("time", &0.0f32)?;
             }
             MagicUniform::RESOLUTION => {
-                self.create_uniform("resolution", &(1024.0, 1024.0))?;
+                self.create_uniform("resolution", &(1024.0f32, 1024.0f32))?;
             }
             _ => (),
         };
@@ -116,6 +124,7 @@ impl<'a> GLProgram<'a, FragmentOnly> {
         self.update_magic_uniforms(&self.context.frame_state)?;
 
         self.draw()?;
+        self.context.debug_errors()?;
         self.context.window.swap_buffers();
         Ok(())
     }
@@ -1,3 +1,4 @@
+use ultraviolet::mat::Mat4;
 use ultraviolet::vec::{Vec3, Vec4};
 
 #[derive(Debug, Copy, Clone)]
@@ -74,18 +75,114 @@ impl Position {
     };
 }
 
+// Layout must match the `Light` struct in the built-in fragment shaders (every member is a vec4 or
+// mat4 so std140 and std430 agree, and there are no padding surprises)
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub struct LightSource {
     color: Vec4,
-    position: Vec4,
+    // w = 1.0 for positional (point, spot) lights, w = 0.0 for directional lights
+    pub(crate) position: Vec4,
+    // xyz = direction the light is pointing, w = cosine of the spot light's cutoff angle (-2.0 if
+    // the light has no cone)
+    pub(crate) direction: Vec4,
+    // x = shadow map layer (-1.0 if shadows are disabled), y = depth bias, z = PCF radius in
+    // texels, w = far plane of the cube map (0.0 for 2D shadow maps)
+    pub(crate) shadow: Vec4,
+    // x = range of the light, past which it contributes nothing (0.0 = unlimited)
+    pub(crate) falloff: Vec4,
+    // World-Space -> Light-Space (clip) transform used to sample 2D shadow maps
+    pub(crate) shadow_matrix: Mat4,
 }
 
+// Sentinel stored in `direction.w` when the light does not have a cone
+const NO_CUTOFF: f32 = -2.0;
+
 impl LightSource {
+    // Point light, kept for backwards compatibility
     pub fn new(color: &LightColor, position: &Position) -> Self {
+        Self::point(color, position)
+    }
+
+    // Light that shines in every direction from a single point
+    pub fn point(color: &LightColor, position: &Position) -> Self {
+        LightSource {
+            color: color.to_vec4(),
+            position: position.to_vec4(),
+            direction: Vec4::new(0.0, 0.0, 0.0, NO_CUTOFF),
+            shadow: Vec4::new(-1.0, 0.0, 0.0, 0.0),
+            falloff: Vec4::zero(),
+            shadow_matrix: Mat4::identity(),
+        }
+    }
+
+    // Light infinitely far away, shining uniformly in `direction` (e.g. the sun)
+    pub fn directional(color: &LightColor, direction: &Position) -> Self {
+        let direction = direction.to_vec3().normalized();
+        LightSource {
+            color: color.to_vec4(),
+            position: Vec4::new(0.0, 0.0, 0.0, 0.0),
+            direction: Vec4::new(direction.x, direction.y, direction.z, NO_CUTOFF),
+            shadow: Vec4::new(-1.0, 0.0, 0.0, 0.0),
+            falloff: Vec4::zero(),
+            shadow_matrix: Mat4::identity(),
+        }
+    }
+
+    // Light at `position` shining in `direction` in a cone with a half-angle of `cutoff` radians
+    pub fn spot(
+        color: &LightColor,
+        position: &Position,
+        direction: &Position,
+        cutoff: f32,
+    ) -> Self {
+        let direction = direction.to_vec3().normalized();
         LightSource {
-            color: Vec4::new(color.r, color.g, color.b, color.intensity),
-            position: Vec4::new(position.x, position.y, position.z, 1.0),
+            color: color.to_vec4(),
+            position: position.to_vec4(),
+            direction: Vec4::new(direction.x, direction.y, direction.z, cutoff.cos()),
+            shadow: Vec4::new(-1.0, 0.0, 0.0, 0.0),
+            falloff: Vec4::zero(),
+            shadow_matrix: Mat4::identity(),
         }
     }
+
+    // Limits how far a point or spot light reaches. Lights with a range are only shaded by the
+    // clusters they overlap, which is what makes scenes with thousands of lights affordable.
+    pub fn with_range(mut self, range: f32) -> Self {
+        self.falloff.x = range.max(0.0);
+        self
+    }
+
+    // Range of the light, None if it reaches everywhere
+    pub(crate) fn range(&self) -> Option<f32> {
+        match self.kind() {
+            LightKind::Directional => None,
+            _ if self.falloff.x <= 0.0 => None,
+            _ => Some(self.falloff.x),
+        }
+    }
+
+    pub(crate) fn kind(&self) -> LightKind {
+        if self.position.w == 0.0 {
+            LightKind::Directional
+        } else if self.direction.w == NO_CUTOFF {
+            LightKind::Point
+        } else {
+            LightKind::Spot
+        }
+    }
+
+    // Cone half-angle of a spot light in radians
+    pub(crate) fn cutoff(&self) -> f32 {
+        self.direction.w.acos()
+    }
+}
+
+// Used to decide which projection (and which type of shadow map) a light needs
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum LightKind {
+    Point,
+    Directional,
+    Spot,
 }
@@ -3,7 +3,7 @@
 use super::{DrawStyle, Mesh, MeshError, Unattached};
 
 // Linear algebra types we use in our internal representation
-use ultraviolet::vec::Vec3;
+use ultraviolet::vec::{Vec2, Vec3};
 
 use std::path::Path;
 
@@ -43,7 +43,7 @@ impl From<wavefront_obj::obj::Object> for Mesh<Unattached> {
         let mut vtn_tuples = Vec::new();
 
         // Generating these from the object info
-        let st_coordinates = Vec::new();
+        let mut st_coordinates = Vec::new();
         let mut normals = Vec::new();
         let mut vertices = Vec::new();
         let mut indices = Vec::new();
@@ -63,9 +63,9 @@ impl From<wavefront_obj::obj::Object> for Mesh<Unattached> {
 
             // For each point, construct a tuple of the vertex information and check for matches
             for point in triangle_points {
-                if let (v_index, _, Some(n_index)) = point {
+                if let (v_index, t_index, Some(n_index)) = point {
                     // Grab the indices of the vertex information
-                    let ele = (v_index, n_index);
+                    let ele = (v_index, t_index, n_index);
 
                     // If the tuple exists, add that index to the element position array
                     if let Some(index) = vtn_tuples.iter().position(|&x| x == ele) {
@@ -82,7 +82,7 @@ impl From<wavefront_obj::obj::Object> for Mesh<Unattached> {
 
         // Go through the list of tuples, a split it into separate Vectors with the actual values
         // instead of the indices, and convert the types into our linear algebra types
-        for (v_index, n_index) in vtn_tuples.iter() {
+        for (v_index, t_index, n_index) in vtn_tuples.iter() {
             let vertex = obj.vertices[*v_index];
             let vertex = Vec3::new(vertex.x as f32, vertex.y as f32, vertex.z as f32);
             vertices.push(vertex);
@@ -90,6 +90,15 @@ impl From<wavefront_obj::obj::Object> for Mesh<Unattached> {
             let normal = obj.normals[*n_index];
             let normal = Vec3::new(normal.x as f32, normal.y as f32, normal.z as f32);
             normals.push(normal);
+
+            // Texture coordinates are optional, vertices without them get (0, 0)
+            if !obj.tex_vertices.is_empty() {
+                let st = t_index
+                    .map(|t_index| obj.tex_vertices[t_index])
+                    .map(|st| Vec2::new(st.u as f32, st.v as f32))
+                    .unwrap_or_default();
+                st_coordinates.push(st);
+            }
         }
 
         // Go through the list of vertices and record the largest/smallest point of every basis
@@ -7,6 +7,7 @@ use crate::GLUtilityError;
 pub enum MeshError {
     FailedToParseFile(String),
     UnknownFileType(String),
+    DoesNotExist(String),
     Other(GLUtilityError),
     VAO(VAOError),
 }
@@ -21,6 +22,9 @@ impl std::fmt::Display for MeshError {
             MeshError::UnknownFileType(extension) => {
                 write!(f, "Did not recognize 3D file type: '.{}'", extension)
             }
+            MeshError::DoesNotExist(name) => {
+                write!(f, "No mesh named: '{}' is attached to this program.", name)
+            }
             MeshError::Other(error) => {
                 write!(f, "Encountered a Mesh Error: {}\n", error)
             }
@@ -1,7 +1,10 @@
 // Converts various 3D file types into our internal represntation 'Mesh'
 mod conversions;
 use super::GLDraw;
-use super::{scene_object::SceneObject, vao::VAO};
+use super::{
+    scene_object::SceneObject,
+    vao::{attribute::SetAttributePointer, VAOError, VAO},
+};
 pub use crate::program::Attribute;
 
 // Linear algebra types we use in our internal representation
@@ -14,6 +17,7 @@ pub use error::MeshError;
 type Result<T> = std::result::Result<T, MeshError>;
 
 // Standard Library
+use crate::window::debug::DebugGroup;
 use std::path::Path;
 use std::rc::Weak;
 
@@ -51,6 +55,8 @@ pub struct Attached {
 pub enum DrawStyle {
     Triangles,
     Points,
+    // For programs with tessellation shaders, which read the vertices in patches
+    Patches(Patches),
 }
 
 impl DrawStyle {
@@ -58,6 +64,53 @@ impl DrawStyle {
         match *self {
             DrawStyle::Triangles => gl::TRIANGLES,
             DrawStyle::Points => gl::POINTS,
+            DrawStyle::Patches(_) => gl::PATCHES,
+        }
+    }
+}
+
+// How many vertices make up each patch, and the tessellation levels used when the program has no
+// tessellation control shader to set them
+#[derive(Debug, Clone, PartialEq)]
+pub struct Patches {
+    pub vertices: GLint,
+    pub outer_level: [f32; 4],
+    pub inner_level: [f32; 2],
+}
+
+impl Patches {
+    pub fn new(vertices: GLint) -> Self {
+        Patches {
+            vertices,
+            ..Patches::default()
+        }
+    }
+
+    pub fn with_levels(self, outer_level: [f32; 4], inner_level: [f32; 2]) -> Self {
+        Patches {
+            outer_level,
+            inner_level,
+            ..self
+        }
+    }
+
+    // Patch parameters are context state, so they're set before every draw
+    fn set(&self) {
+        unsafe {
+            gl::PatchParameteri(gl::PATCH_VERTICES, self.vertices);
+            gl::PatchParameterfv(gl::PATCH_DEFAULT_OUTER_LEVEL, self.outer_level.as_ptr());
+            gl::PatchParameterfv(gl::PATCH_DEFAULT_INNER_LEVEL, self.inner_level.as_ptr());
+        }
+    }
+}
+
+// Triangles, left untessellated
+impl Default for Patches {
+    fn default() -> Self {
+        Patches {
+            vertices: 3,
+            outer_level: [1.0; 4],
+            inner_level: [1.0; 2],
         }
     }
 }
@@ -67,10 +120,12 @@ impl DrawStyle {
 // normal varyings.
 impl GLDraw for Mesh<Attached> {
     fn draw(&mut self) -> super::Result<()> {
+        let _group = DebugGroup::push(&self.name);
         // Iterate over all the scene objects, checking if they're enabled, and collating their
         // transforms into a contiguous array to be buffered to the GPU
         let mut transforms = Vec::new();
         let mut normal_transforms = Vec::new();
+        let mut receive_shadows = Vec::new();
         for object in self.data.objects.iter() {
             // The Scene Object may have been removed
             // TODO: Handle the removal by changing the Vec of SceneObjects
@@ -80,6 +135,7 @@ impl GLDraw for Mesh<Attached> {
                     // TODO: Only update the transform data if it has changed / partially buffer it
                     if let Some(transform) = object.transform {
                         transforms.push(transform);
+                        receive_shadows.push(object.receive_shadows.get() as u8 as f32);
                     }
 
                     if let Some(normals) = object.normal_transform {
@@ -88,28 +144,68 @@ impl GLDraw for Mesh<Attached> {
                 }
             }
         }
-        // Buffer the transform data to the GPU
-        if transforms.len() > 1 {
-            self.data
-                .vao
-                .update_attribute("object_mw_transforms", &transforms, true)?;
+        // Buffer the transform data to the GPU (always, since the shadow pass may have replaced it
+        // with only the shadow casting objects)
+        if !transforms.is_empty() {
+            self.update_instances("object_mw_transforms", &transforms)?;
+            self.update_instances("object_receives_shadows", &receive_shadows)?;
         }
         if normal_transforms.len() > 1 {
-            self.data.vao.update_attribute(
-                "object_mw_normal_transforms",
-                &normal_transforms,
-                true,
-            )?;
+            self.update_instances("object_mw_normal_transforms", &normal_transforms)?;
         }
 
-        let vao = &self.data.vao;
         // This might be wrong at some point - i.e. if we start doing partial buffer updates
         let num_instances = transforms.len() as i32;
+        self.draw_instances(num_instances);
+        Ok(())
+    }
+}
+
+impl Mesh<Attached> {
+    // Buffers the transforms of only the objects that cast shadows, and returns how many of them
+    // there are so the shadow pass can draw them once per shadow map face.
+    pub(crate) fn buffer_shadow_casters(&self) -> Result<i32> {
+        let transforms: Vec<_> = self
+            .data
+            .objects
+            .iter()
+            .filter_map(|object| object.upgrade())
+            .filter(|object| object.enabled && object.cast_shadows.get())
+            .filter_map(|object| object.transform)
+            .collect();
+
+        if !transforms.is_empty() {
+            self.update_instances("object_mw_transforms", &transforms)?;
+        }
+        Ok(transforms.len() as i32)
+    }
+
+    // Buffers per-object data, skipping attributes the program's shaders don't declare
+    fn update_instances<B>(&self, name: &str, buffer: &B) -> Result<()>
+    where
+        B: SetAttributePointer + std::fmt::Debug,
+    {
+        if self.data.vao.has_attribute(name) {
+            self.data.vao.update_attribute(name, buffer, true)?;
+        }
+        Ok(())
+    }
+
+    // Draws the first `num_instances` instances of whatever transforms are currently buffered
+    pub(crate) fn draw_instances(&self, num_instances: i32) {
+        if num_instances == 0 {
+            return;
+        }
+
+        let vao = &self.data.vao;
         unsafe {
             gl::BindVertexArray(vao.id);
             // TODO: Update VAO struct to elements_buffer.id (idk, that implies it's more than an
             // id i guess, which it's not :s)
             gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, vao.elements.buffer_id);
+            if let DrawStyle::Patches(patches) = &self.draw_style {
+                patches.set();
+            }
             gl::DrawElementsInstanced(
                 self.draw_style.value(),
                 vao.elements.buffer_length,
@@ -118,7 +214,6 @@ impl GLDraw for Mesh<Attached> {
                 num_instances,
             );
         }
-        Ok(())
     }
 }
 
@@ -150,10 +245,27 @@ impl Mesh<Unattached> {
         // Attach these buffers as attributes when creating our VAO
         let object_transforms = vec![ultraviolet::Mat4::identity()];
         let object_normals = vec![ultraviolet::Mat3::identity()];
+        let object_receives_shadows = vec![1.0f32];
         vao.add_attribute("vertices", &vertices, false)?;
-        vao.add_attribute("normals", &normals, false)?;
-        vao.add_attribute("object_mw_transforms", &object_transforms, false)?;
-        vao.add_attribute("object_mw_normal_transforms", &object_normals, false)?;
+        // Every other attribute is optional, since custom shaders only declare the ones they use.
+        // Only some shaders (e.g. PBR) sample textures, the rest don't declare texture coordinates.
+        optional_attribute(vao.add_attribute("normals", &normals, false))?;
+        optional_attribute(vao.add_attribute("object_mw_transforms", &object_transforms, false))?;
+        optional_attribute(vao.add_attribute(
+            "object_mw_normal_transforms",
+            &object_normals,
+            false,
+        ))?;
+        optional_attribute(vao.add_attribute(
+            "object_receives_shadows",
+            &object_receives_shadows,
+            true,
+        ))?;
+        if !st_coordinates.is_empty() {
+            optional_attribute(vao.add_attribute("st_coordinates", &st_coordinates, false))?;
+        }
+
+        vao.label(&name);
 
         let objects = Vec::new();
         let data = Attached {
@@ -184,3 +296,11 @@ impl Mesh<Unattached> {
         conversions::load_mesh(path)
     }
 }
+
+// Attributes the shaders optimized out, or never declared, are skipped
+fn optional_attribute<T>(result: std::result::Result<T, VAOError>) -> Result<()> {
+    match result {
+        Ok(_) | Err(VAOError::CouldNotFindLocation(_)) => Ok(()),
+        Err(error) => Err(error.into()),
+    }
+}
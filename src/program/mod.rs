@@ -3,25 +3,46 @@
 pub mod blinn_phong;
 pub mod builder;
 pub mod camera;
+pub mod clusters;
+pub mod compute;
+pub mod custom_shader;
 pub mod error;
-mod fragment_only;
+pub(crate) mod fragment_only;
 pub mod lights;
 pub mod mesh;
+pub mod pbr;
+pub mod reflection;
+pub mod scene;
 pub mod scene_object;
+pub mod shadows;
+pub mod skybox;
 pub mod vao;
 
-use crate::interface_blocks::UniformBufferBlock;
+use crate::interface_blocks::{self, Std140, Unattached, UniformBufferBlock};
+use crate::interface_blocks::{
+    Attached, BindingLease, BindingPoints, BlockLayout, BlockType, Layout,
+};
 pub use crate::interface_blocks::{InterfaceBlock, InterfaceBuffer};
 use crate::shader::ShaderPipeline;
+pub use crate::uniform::UniformHandle;
 pub use crate::uniform::UpdateUniform;
-use crate::uniform::{Uniform, UniformValue};
+use crate::uniform::{Uniform, UniformError, UniformStruct, UniformValue};
 use crate::window;
 use blinn_phong::BlinnPhong;
 pub use camera::{Camera, Projection};
+pub use clusters::ClusterSettings;
+pub use compute::{memory_barrier, Barriers, Compute, DispatchIndirectCommand, ImageAccess};
+pub use custom_shader::CustomShader;
 pub use error::ProgramError;
 use fragment_only::FragmentOnly;
 pub use lights::{LightColor, LightSource, Position};
 pub use mesh::Mesh;
+use pbr::Pbr;
+pub use reflection::{
+    ActiveAttribute, ActiveBlock, ActiveUniform, BlockMember, GlslType, ProgramInterface,
+};
+pub use scene::{Scene, SceneData};
+pub use shadows::ShadowSettings;
 pub use vao::attribute::Attribute;
 pub use window::{FrameState, GLWindow};
 
@@ -44,6 +65,8 @@ use std::rc::{Rc, Weak};
 pub struct GLProgram<'a, Type> {
     // OpenGL Program ID
     id: u32,
+    // Deletes the program once neither this, nor the context's variant cache, uses it
+    program: Rc<crate::window::object::GLObject>,
     // Window, Events, and OpenGL context
     context: GLWindow,
     // OpenGL Shaders, e.g. vertex, fragment, et al.
@@ -52,6 +75,8 @@ pub struct GLProgram<'a, Type> {
     // Uniform locations, and their values
     uniforms: HashMap<Rc<str>, Rc<dyn UpdateUniform>>,
     interface_blocks: HashMap<Rc<str>, Rc<dyn InterfaceBuffer>>,
+    // Frees the blocks' binding points once no program in the context uses them
+    binding_leases: HashMap<Rc<str>, BindingLease>,
     data: Type,
 }
 
@@ -61,10 +86,6 @@ pub trait GLDraw {
     fn draw(&mut self) -> Result<()>;
 }
 
-// Dummy types that represent different GLPrograms with different abilities built-in
-#[derive(Debug)]
-pub struct CustomShader;
-
 // Functions commong to all GLProgram types
 impl<'a, Any> GLProgram<'a, Any> {
     //////////////
@@ -88,30 +109,97 @@ impl<'a, Any> GLProgram<'a, Any> {
         Ok(weak)
     }
 
-    pub fn attach_interface_block<'b, Value>(
+    // Binds a uniform block to the next free uniform buffer binding point of the context. Blocks
+    // already attached to another program in the context are shared with
+    // `share_interface_block()` instead.
+    pub fn attach_interface_block<T, Value>(
         &mut self,
-        block: InterfaceBlock<
-            crate::interface_blocks::Uniform,
-            crate::interface_blocks::Std140,
-            Value,
-            crate::interface_blocks::Unattached<Value>,
-        >,
-    ) -> Result<
-        InterfaceBlock<
-            crate::interface_blocks::Uniform,
-            crate::interface_blocks::Std140,
-            Value,
-            crate::interface_blocks::Attached,
-        >,
-    > {
-        unsafe {
-            gl::UseProgram(self.id);
-        }
+        block: InterfaceBlock<interface_blocks::Uniform, T, Value, Unattached<Value>>,
+    ) -> Result<Rc<InterfaceBlock<interface_blocks::Uniform, T, Value, Attached>>>
+    where
+        T: BlockLayout + 'static,
+        Value: Layout<T::Rules> + 'static,
+    {
+        self.attach_block(block)
+    }
+
+    // Same as above, for shader storage blocks
+    pub fn attach_storage_block<T, Value>(
+        &mut self,
+        block: InterfaceBlock<interface_blocks::Shader, T, Value, Unattached<Value>>,
+    ) -> Result<Rc<InterfaceBlock<interface_blocks::Shader, T, Value, Attached>>>
+    where
+        T: BlockLayout + 'static,
+        Value: Layout<T::Rules> + 'static,
+    {
+        self.attach_block(block)
+    }
+
+    fn attach_block<B, T, Value>(
+        &mut self,
+        block: InterfaceBlock<B, T, Value, Unattached<Value>>,
+    ) -> Result<Rc<InterfaceBlock<B, T, Value, Attached>>>
+    where
+        B: BlockType,
+        T: BlockLayout + 'static,
+        Value: Layout<T::Rules> + 'static,
+    {
+        let key = block.key();
+        // Released if attaching fails
+        let lease = self.context.binding_points.lease::<B>(&key)?;
+        let block = Rc::new(block.attach(self.id, lease.binding_point())?);
+        self.interface_blocks.insert(key.clone(), block.clone());
+        self.binding_leases.insert(key, lease);
+        Ok(block)
+    }
 
+    // Points this program's block at a buffer already attached to another program, e.g. the
+    // camera's matrices shared by every program
+    pub fn share_interface_block<B, T, Value>(
+        &mut self,
+        block: &Rc<InterfaceBlock<B, T, Value, Attached>>,
+    ) -> Result<()>
+    where
+        B: BlockType,
+        T: BlockLayout + 'static,
+        Value: Layout<T::Rules> + 'static,
+    {
         let key = block.key();
-        // hardcoded :[
-        let value = block.attach(self.id, 1)?;
-        Ok(value)
+        let lease = self
+            .context
+            .binding_points
+            .lease_shared::<B>(&key, block.binding_point())?;
+        block.share(self.id)?;
+        self.interface_blocks.insert(key.clone(), block.clone());
+        self.binding_leases.insert(key, lease);
+        Ok(())
+    }
+
+    // Looks up an attached block by name, None if there isn't one or it holds different values
+    pub fn interface_block<B, T, Value, S>(
+        &self,
+        name: S,
+    ) -> Option<Rc<InterfaceBlock<B, T, Value, Attached>>>
+    where
+        B: BlockType,
+        T: 'static,
+        Value: 'static,
+        S: AsRef<str>,
+    {
+        self.interface_blocks
+            .get(name.as_ref())
+            .and_then(|block| block.clone().as_any().downcast().ok())
+    }
+
+    // The context's binding points, e.g. to reserve ones the shaders hardcode
+    pub fn binding_points(&mut self) -> &mut BindingPoints {
+        &mut self.context.binding_points
+    }
+
+    // Hands the window back, e.g. to build another program in the same context, which reuses the
+    // binding points and shader variants this one's set up
+    pub fn into_window(self) -> GLWindow {
+        self.context
     }
 
     // Creates a new uniform, initializes it in the GLProgram and adds it to the HashMap
@@ -131,25 +219,19 @@ impl<'a, Any> GLProgram<'a, Any> {
     // Creates a new Interface Block, initializes it in the GLProgram and adds it to the HashMap
     pub fn create_interface_block<S, Value>(
         &mut self,
+        name: S,
         value: Vec<Value>,
-    ) -> Result<
-        InterfaceBlock<
-            crate::interface_blocks::Uniform,
-            crate::interface_blocks::Std140,
-            Value,
-            crate::interface_blocks::Attached,
-        >,
-    >
+    ) -> Result<Rc<InterfaceBlock<interface_blocks::Uniform, Std140, Value, Attached>>>
     where
         S: AsRef<str>,
-        Value: UniformValue + 'static,
+        Value: Layout<Std140> + 'static,
     {
-        let interface_block = UniformBufferBlock::new_std140("lights", value)?;
+        let interface_block = UniformBufferBlock::new_std140(name, value)?;
         self.attach_interface_block(interface_block)
     }
 
     // Returns a weak reference to an existing Uniform in the program (which the caller can then
-    // call .update() on to update the value in the program).
+    // call .update() on to update the value in the program, if it's of a type the uniform takes).
     pub fn get_uniform<S>(&self, name: &S) -> Option<Weak<dyn UpdateUniform>>
     where
         S: AsRef<str>,
@@ -159,22 +241,45 @@ impl<'a, Any> GLProgram<'a, Any> {
             .map(|uniform| Rc::downgrade(uniform))
     }
 
-    // Lazy way to combine getting and updating and existing uniform
-    pub fn update_uniform<S, Value>(&self, name: S, value: &Value) -> Result<()>
+    // A handle to a uniform the shaders declare, which can only be set with values of type Value.
+    // Fails if the uniform's GLSL type can't hold a Value.
+    pub fn uniform<Value, S>(&self, name: S) -> Result<UniformHandle<Value>>
     where
         S: AsRef<str>,
         Value: UniformValue,
     {
-        unsafe {
-            gl::UseProgram(self.id);
-        }
-
-        self.get_uniform(&name)
-            .and_then(|uniform| uniform.upgrade())
-            .and_then(|uniform| Some(uniform.update(value)))
-            .ok_or(ProgramError::UniformNotAttachedToProgram(
+        let c_name = CString::new(name.as_ref()).map_err(|_| {
+            UniformError::Other(crate::GLUtilityError::FailedToConvertToCString(
                 name.as_ref().to_string(),
             ))
+        })?;
+        Ok(UniformHandle::new(self.id, &c_name)?)
+    }
+
+    // Sets a uniform whose GLSL type is a `struct`, field by field
+    pub fn set_uniform_struct<S, Value>(&self, name: S, value: &Value) -> Result<()>
+    where
+        S: AsRef<str>,
+        Value: UniformStruct,
+    {
+        match value.set_fields(self.id, name.as_ref())? {
+            0 => Err(UniformError::CouldNotFindUniformIndex(name.as_ref().to_string()).into()),
+            _ => Ok(()),
+        }
+    }
+
+    // Lazy way to combine getting and updating and existing uniform. Fails if the uniform's GLSL
+    // type can't be set with a Value.
+    pub fn update_uniform<S, Value>(&self, name: S, value: &Value) -> Result<()>
+    where
+        S: AsRef<str>,
+        Value: UniformValue,
+    {
+        let uniform = self
+            .uniforms
+            .get(name.as_ref())
+            .ok_or_else(|| ProgramError::UniformNotAttachedToProgram(name.as_ref().to_string()))?;
+        Ok(uniform.update(value)?)
     }
 
     // Similar to get_uniform_location but for block indices
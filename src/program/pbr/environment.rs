@@ -0,0 +1,107 @@
+// Image Based Lighting: precomputes, from an environment cube map, the maps the PBR fragment
+// shader uses to light objects with their surroundings. The diffuse irradiance, the specular
+// reflections prefiltered by roughness (one roughness per mip level), and the BRDF lookup table
+// for the split-sum approximation.
+use super::error::PbrError;
+use super::{BRDF_LUT_TEXTURE_UNIT, IRRADIANCE_MAP_TEXTURE_UNIT, PREFILTERED_MAP_TEXTURE_UNIT};
+use crate::program::fragment_only::FragmentOnly;
+use crate::shader::{FragmentShader, Shader, VertexShader};
+use crate::texture::{Pass, RenderTarget, Texture, Triangles};
+
+// Error Types
+type Result<T> = std::result::Result<T, PbrError>;
+
+// OpenGL Types
+use gl::types::*;
+
+// Width and height (in texels) of the faces of the generated cube maps
+const IRRADIANCE_RESOLUTION: GLsizei = 32;
+const PREFILTERED_RESOLUTION: GLsizei = 128;
+// Roughness 0.0 -> 1.0 is spread across this many mip levels of the prefiltered map
+const PREFILTERED_LEVELS: GLsizei = 5;
+const BRDF_LUT_RESOLUTION: GLsizei = 512;
+
+// The precomputed maps, ready to be sampled by the PBR fragment shader
+pub(crate) struct Environment {
+    pub(crate) irradiance_map: Texture,
+    pub(crate) prefiltered_map: Texture,
+    pub(crate) brdf_lut: Texture,
+}
+
+impl Environment {
+    // The cube map should be mipmapped, the prefilter pass samples blurrier levels of it
+    pub(crate) fn new(cube_map: &Texture) -> Result<Self> {
+        // The render target restores the state it changed once it's dropped
+        let target = RenderTarget::new();
+        let cube = Triangles::unit_cube();
+        let (irradiance_map, prefiltered_map, brdf_lut) =
+            Environment::generate(cube_map, &target, &cube)?;
+        Ok(Environment {
+            irradiance_map,
+            prefiltered_map,
+            brdf_lut,
+        })
+    }
+
+    // Renders every map in turn into the render target
+    fn generate(
+        cube_map: &Texture,
+        target: &RenderTarget,
+        cube: &Triangles,
+    ) -> Result<(Texture, Texture, Texture)> {
+        // Diffuse irradiance
+        let irradiance_map = Texture::empty_cube(IRRADIANCE_RESOLUTION, 1);
+        let pass = Pass::new(
+            Shader::<VertexShader>::cube_map_face()?,
+            Shader::<FragmentShader>::ibl_irradiance()?,
+        )?;
+        pass.label("irradiance");
+        pass.uniform("environment_map", &0)?;
+        cube_map.bind(0);
+        pass.render_cube(cube, target, &irradiance_map, 0)?;
+
+        // Specular reflections, one roughness per mip level
+        let prefiltered_map = Texture::empty_cube(PREFILTERED_RESOLUTION, PREFILTERED_LEVELS);
+        let pass = Pass::new(
+            Shader::<VertexShader>::cube_map_face()?,
+            Shader::<FragmentShader>::ibl_prefilter()?,
+        )?;
+        pass.label("prefilter");
+        pass.uniform("environment_map", &0)?;
+        pass.uniform("environment_resolution", &(cube_map.width as f32))?;
+        let roughness = pass.uniform("roughness", &0.0f32)?;
+        cube_map.bind(0);
+        for level in 0..PREFILTERED_LEVELS {
+            roughness.update(&(level as f32 / (PREFILTERED_LEVELS - 1) as f32))?;
+            pass.render_cube(cube, target, &prefiltered_map, level)?;
+        }
+
+        // BRDF lookup table, drawn with a single full screen triangle
+        let brdf_lut = Texture::empty_rg(BRDF_LUT_RESOLUTION, BRDF_LUT_RESOLUTION);
+        // Bound (and kept alive) while the triangle's drawn
+        let pass = Pass::new(
+            Shader::<VertexShader>::full_screen()?,
+            Shader::<FragmentShader>::ibl_brdf()?,
+        )?;
+        pass.label("brdf lut");
+        target.attach(gl::TEXTURE_2D, &brdf_lut, 0)?;
+        let triangle = Triangles::new(&FragmentOnly::TRIANGLE);
+        triangle.draw();
+
+        Ok((irradiance_map, prefiltered_map, brdf_lut))
+    }
+
+    // Highest mip level of the prefiltered map, which holds the roughest reflections
+    pub(crate) fn prefiltered_levels(&self) -> f32 {
+        (PREFILTERED_LEVELS - 1) as f32
+    }
+
+    // Binds the maps to the texture units the PBR fragment shader samples from
+    pub(crate) fn bind(&self) {
+        self.irradiance_map
+            .bind(IRRADIANCE_MAP_TEXTURE_UNIT as GLuint);
+        self.prefiltered_map
+            .bind(PREFILTERED_MAP_TEXTURE_UNIT as GLuint);
+        self.brdf_lut.bind(BRDF_LUT_TEXTURE_UNIT as GLuint);
+    }
+}
@@ -0,0 +1,68 @@
+use crate::shader::ShaderError;
+use crate::texture::TextureError;
+use crate::uniform::UniformError;
+
+// Error type for the physically based program
+#[derive(Debug)]
+pub enum PbrError {
+    Shader(ShaderError),
+    Uniform(UniformError),
+    Texture(TextureError),
+}
+
+impl std::error::Error for PbrError {}
+impl std::fmt::Display for PbrError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PbrError::Shader(error) => {
+                write!(
+                    f,
+                    "Could not create an environment precomputation shader.\n{}",
+                    error
+                )
+            }
+            PbrError::Uniform(error) => {
+                write!(
+                    f,
+                    "Could not set an environment precomputation uniform.\n{}",
+                    error
+                )
+            }
+            PbrError::Texture(error) => {
+                write!(f, "Could not render the environment maps.\n{}", error)
+            }
+        }
+    }
+}
+
+impl From<ShaderError> for PbrError {
+    fn from(error: ShaderError) -> Self {
+        PbrError::Shader(error)
+    }
+}
+
+impl From<UniformError> for PbrError {
+    fn from(error: UniformError) -> Self {
+        PbrError::Uniform(error)
+    }
+}
+
+impl From<TextureError> for PbrError {
+    fn from(error: TextureError) -> Self {
+        PbrError::Texture(error)
+    }
+}
+
+// Allows for painless casting into our crate's rollup error
+impl From<PbrError> for crate::GLError {
+    fn from(error: PbrError) -> Self {
+        crate::GLError::Pbr(error)
+    }
+}
+
+// Allows for painless casting into our crate's rollup error
+impl From<PbrError> for crate::program::ProgramError {
+    fn from(error: PbrError) -> Self {
+        crate::program::ProgramError::Pbr(error)
+    }
+}
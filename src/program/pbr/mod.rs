@@ -0,0 +1,193 @@
+// Physically based shading: a Cook-Torrance GGX metallic-roughness program, lit by the same
+// lights, shadows and clusters as the Blinn-Phong programs, and optionally by its surroundings
+// through image based lighting.
+pub mod error;
+pub use error::PbrError;
+mod environment;
+use environment::Environment;
+
+// Trait that all GLProgram<Types> must implement
+use super::mesh::MeshError;
+use super::scene::{Scene, SceneData};
+use super::{GLDraw, GLProgram, ProgramError};
+use crate::materials::PbrMaterial;
+use crate::texture::Texture;
+
+// OpenGL Types
+use gl::types::*;
+
+// Linear Algebra Types
+use ultraviolet::vec::Vec4;
+
+use std::collections::HashMap;
+use std::path::Path;
+
+// Convenience Error Type Alias
+type Result<T> = std::result::Result<T, ProgramError>;
+
+// Texture units the PBR fragment shader expects the material maps, and environment maps, to be
+// bound to
+pub const ALBEDO_MAP_TEXTURE_UNIT: GLint = 0;
+pub const METALLIC_ROUGHNESS_MAP_TEXTURE_UNIT: GLint = 1;
+pub const AMBIENT_OCCLUSION_MAP_TEXTURE_UNIT: GLint = 2;
+pub const NORMAL_MAP_TEXTURE_UNIT: GLint = 3;
+pub const IRRADIANCE_MAP_TEXTURE_UNIT: GLint = 4;
+pub const PREFILTERED_MAP_TEXTURE_UNIT: GLint = 5;
+pub const BRDF_LUT_TEXTURE_UNIT: GLint = 6;
+
+// GLProgram sub-type sub-structure
+pub struct Pbr {
+    // Camera, meshes, objects and lights
+    scene: Scene,
+    environment: Option<Environment>,
+    // Material each mesh is drawn with, keyed by the mesh name
+    materials: HashMap<String, PbrMaterial>,
+    // Used for meshes without a material
+    default_material: PbrMaterial,
+}
+
+impl Pbr {
+    pub(crate) fn new() -> Self {
+        Pbr {
+            scene: Scene::new(),
+            environment: None,
+            materials: HashMap::new(),
+            default_material: PbrMaterial::default(),
+        }
+    }
+}
+
+impl SceneData for Pbr {
+    const TONE_MAPPED: bool = true;
+
+    fn scene(&self) -> &Scene {
+        &self.scene
+    }
+
+    fn scene_mut(&mut self) -> &mut Scene {
+        &mut self.scene
+    }
+}
+
+impl<'a> GLProgram<'a, Pbr> {
+    // Lights the scene with its surroundings: loads an equirectangular HDR image and precomputes
+    // the irradiance, prefiltered specular and BRDF lookup maps from it
+    pub fn environment<P>(&mut self, path: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let cube_map = Texture::cube_map_from_equirectangular(path)?;
+        self.environment_cube_map(&cube_map)
+    }
+
+    // Same as `environment()`, but with a cube map that's already loaded (e.g. from six images)
+    pub fn environment_cube_map(&mut self, cube_map: &Texture) -> Result<()> {
+        let environment = Environment::new(cube_map)?;
+        self.update_uniform("prefiltered_levels", &environment.prefiltered_levels())?;
+        self.update_uniform("use_environment", &1u32)?;
+        self.data.environment = Some(environment);
+        Ok(())
+    }
+
+    // Sets the material every object using the mesh is drawn with
+    pub fn set_material<S>(&mut self, mesh: S, material: PbrMaterial) -> Result<()>
+    where
+        S: AsRef<str>,
+    {
+        let mesh = mesh.as_ref();
+        if !self.data.scene.meshes.contains_key(mesh) {
+            return Err(MeshError::DoesNotExist(mesh.to_string()).into());
+        }
+        self.data.materials.insert(mesh.to_string(), material);
+        Ok(())
+    }
+
+    // Creates the material and environment uniforms (on top of the ones every lit program has)
+    pub(crate) fn initialize_materials(&mut self) -> Result<()> {
+        unsafe {
+            gl::Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS);
+        }
+
+        let material = PbrMaterial::default();
+        self.create_uniform("albedo_factor", &material.albedo)?;
+        self.create_uniform("material_factors", &Vec4::zero())?;
+        self.create_uniform("material_maps", &(0u32, 0u32, 0u32, 0u32))?;
+        self.create_uniform("albedo_map", &ALBEDO_MAP_TEXTURE_UNIT)?;
+        self.create_uniform(
+            "metallic_roughness_map",
+            &METALLIC_ROUGHNESS_MAP_TEXTURE_UNIT,
+        )?;
+        self.create_uniform("ambient_occlusion_map", &AMBIENT_OCCLUSION_MAP_TEXTURE_UNIT)?;
+        self.create_uniform("normal_map", &NORMAL_MAP_TEXTURE_UNIT)?;
+        self.create_uniform("irradiance_map", &IRRADIANCE_MAP_TEXTURE_UNIT)?;
+        self.create_uniform("prefiltered_map", &PREFILTERED_MAP_TEXTURE_UNIT)?;
+        self.create_uniform("brdf_lut", &BRDF_LUT_TEXTURE_UNIT)?;
+        self.create_uniform("use_environment", &0u32)?;
+        self.create_uniform("prefiltered_levels", &0.0f32)?;
+        self.create_uniform("tone_map", &1u32)?;
+        Ok(())
+    }
+
+    // Sets the material uniforms, and binds its maps
+    fn use_material(&self, material: &PbrMaterial) -> Result<()> {
+        let factors = Vec4::new(
+            material.metallic,
+            material.roughness,
+            material.ambient_occlusion,
+            0.0,
+        );
+        let maps = [
+            (&material.albedo_map, ALBEDO_MAP_TEXTURE_UNIT),
+            (
+                &material.metallic_roughness_map,
+                METALLIC_ROUGHNESS_MAP_TEXTURE_UNIT,
+            ),
+            (
+                &material.ambient_occlusion_map,
+                AMBIENT_OCCLUSION_MAP_TEXTURE_UNIT,
+            ),
+            (&material.normal_map, NORMAL_MAP_TEXTURE_UNIT),
+        ];
+        let mut enabled = [0u32; 4];
+        for (flag, (map, unit)) in enabled.iter_mut().zip(maps) {
+            if let Some(texture) = map {
+                texture.bind(unit as GLuint);
+                *flag = 1;
+            }
+        }
+
+        self.update_uniform("albedo_factor", &material.albedo)?;
+        self.update_uniform("material_factors", &factors)?;
+        self.update_uniform(
+            "material_maps",
+            &(enabled[0], enabled[1], enabled[2], enabled[3]),
+        )?;
+        Ok(())
+    }
+}
+
+impl<'a> GLDraw for GLProgram<'a, Pbr> {
+    fn draw(&mut self) -> Result<()> {
+        self.prepare_scene()?;
+        self.update_uniform("tone_map", &(self.tone_mapped() as GLuint))?;
+        if let Some(environment) = self.data.environment.as_ref() {
+            environment.bind();
+        }
+
+        // Meshes are drawn one at a time, each with its own material
+        let names: Vec<String> = self.data.scene.meshes.keys().cloned().collect();
+        for name in names.iter() {
+            let material = self
+                .data
+                .materials
+                .get(name)
+                .unwrap_or(&self.data.default_material);
+            self.use_material(material)?;
+            if let Some(mesh) = self.data.scene.meshes.get_mut(name) {
+                mesh.draw()?;
+            }
+        }
+
+        self.finish_scene()
+    }
+}
@@ -0,0 +1,663 @@
+// Reflection of a linked program: every active uniform, attribute, uniform block and storage block
+// its shaders declare, as OpenGL reports them through the program interface queries. Useful for
+// catching misspelled names before they turn into `CouldNotFindLocation` errors at runtime.
+use super::GLProgram;
+
+// OpenGL Types
+use gl::types::*;
+
+use std::ffi::CStr;
+
+// Dumping the interface for tools and debugging
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+// Every type an active resource can have, and its name in GLSL source
+const GLSL_TYPES: [(GLenum, &str); 112] = [
+    (gl::FLOAT, "float"),
+    (gl::FLOAT_VEC2, "vec2"),
+    (gl::FLOAT_VEC3, "vec3"),
+    (gl::FLOAT_VEC4, "vec4"),
+    (gl::DOUBLE, "double"),
+    (gl::DOUBLE_VEC2, "dvec2"),
+    (gl::DOUBLE_VEC3, "dvec3"),
+    (gl::DOUBLE_VEC4, "dvec4"),
+    (gl::INT, "int"),
+    (gl::INT_VEC2, "ivec2"),
+    (gl::INT_VEC3, "ivec3"),
+    (gl::INT_VEC4, "ivec4"),
+    (gl::UNSIGNED_INT, "uint"),
+    (gl::UNSIGNED_INT_VEC2, "uvec2"),
+    (gl::UNSIGNED_INT_VEC3, "uvec3"),
+    (gl::UNSIGNED_INT_VEC4, "uvec4"),
+    (gl::BOOL, "bool"),
+    (gl::BOOL_VEC2, "bvec2"),
+    (gl::BOOL_VEC3, "bvec3"),
+    (gl::BOOL_VEC4, "bvec4"),
+    (gl::FLOAT_MAT2, "mat2"),
+    (gl::FLOAT_MAT3, "mat3"),
+    (gl::FLOAT_MAT4, "mat4"),
+    (gl::FLOAT_MAT2x3, "mat2x3"),
+    (gl::FLOAT_MAT2x4, "mat2x4"),
+    (gl::FLOAT_MAT3x2, "mat3x2"),
+    (gl::FLOAT_MAT3x4, "mat3x4"),
+    (gl::FLOAT_MAT4x2, "mat4x2"),
+    (gl::FLOAT_MAT4x3, "mat4x3"),
+    (gl::DOUBLE_MAT2, "dmat2"),
+    (gl::DOUBLE_MAT3, "dmat3"),
+    (gl::DOUBLE_MAT4, "dmat4"),
+    (gl::DOUBLE_MAT2x3, "dmat2x3"),
+    (gl::DOUBLE_MAT2x4, "dmat2x4"),
+    (gl::DOUBLE_MAT3x2, "dmat3x2"),
+    (gl::DOUBLE_MAT3x4, "dmat3x4"),
+    (gl::DOUBLE_MAT4x2, "dmat4x2"),
+    (gl::DOUBLE_MAT4x3, "dmat4x3"),
+    (gl::SAMPLER_1D, "sampler1D"),
+    (gl::SAMPLER_2D, "sampler2D"),
+    (gl::SAMPLER_3D, "sampler3D"),
+    (gl::SAMPLER_CUBE, "samplerCube"),
+    (gl::SAMPLER_1D_SHADOW, "sampler1DShadow"),
+    (gl::SAMPLER_2D_SHADOW, "sampler2DShadow"),
+    (gl::SAMPLER_1D_ARRAY, "sampler1DArray"),
+    (gl::SAMPLER_2D_ARRAY, "sampler2DArray"),
+    (gl::SAMPLER_CUBE_MAP_ARRAY, "samplerCubeArray"),
+    (gl::SAMPLER_1D_ARRAY_SHADOW, "sampler1DArrayShadow"),
+    (gl::SAMPLER_2D_ARRAY_SHADOW, "sampler2DArrayShadow"),
+    (gl::SAMPLER_CUBE_SHADOW, "samplerCubeShadow"),
+    (gl::SAMPLER_CUBE_MAP_ARRAY_SHADOW, "samplerCubeArrayShadow"),
+    (gl::SAMPLER_2D_MULTISAMPLE, "sampler2DMS"),
+    (gl::SAMPLER_2D_MULTISAMPLE_ARRAY, "sampler2DMSArray"),
+    (gl::SAMPLER_BUFFER, "samplerBuffer"),
+    (gl::SAMPLER_2D_RECT, "sampler2DRect"),
+    (gl::SAMPLER_2D_RECT_SHADOW, "sampler2DRectShadow"),
+    (gl::INT_SAMPLER_1D, "isampler1D"),
+    (gl::INT_SAMPLER_2D, "isampler2D"),
+    (gl::INT_SAMPLER_3D, "isampler3D"),
+    (gl::INT_SAMPLER_CUBE, "isamplerCube"),
+    (gl::INT_SAMPLER_1D_ARRAY, "isampler1DArray"),
+    (gl::INT_SAMPLER_2D_ARRAY, "isampler2DArray"),
+    (gl::INT_SAMPLER_CUBE_MAP_ARRAY, "isamplerCubeArray"),
+    (gl::INT_SAMPLER_2D_MULTISAMPLE, "isampler2DMS"),
+    (gl::INT_SAMPLER_2D_MULTISAMPLE_ARRAY, "isampler2DMSArray"),
+    (gl::INT_SAMPLER_BUFFER, "isamplerBuffer"),
+    (gl::INT_SAMPLER_2D_RECT, "isampler2DRect"),
+    (gl::UNSIGNED_INT_SAMPLER_1D, "usampler1D"),
+    (gl::UNSIGNED_INT_SAMPLER_2D, "usampler2D"),
+    (gl::UNSIGNED_INT_SAMPLER_3D, "usampler3D"),
+    (gl::UNSIGNED_INT_SAMPLER_CUBE, "usamplerCube"),
+    (gl::UNSIGNED_INT_SAMPLER_1D_ARRAY, "usampler1DArray"),
+    (gl::UNSIGNED_INT_SAMPLER_2D_ARRAY, "usampler2DArray"),
+    (gl::UNSIGNED_INT_SAMPLER_CUBE_MAP_ARRAY, "usamplerCubeArray"),
+    (gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE, "usampler2DMS"),
+    (
+        gl::UNSIGNED_INT_SAMPLER_2D_MULTISAMPLE_ARRAY,
+        "usampler2DMSArray",
+    ),
+    (gl::UNSIGNED_INT_SAMPLER_BUFFER, "usamplerBuffer"),
+    (gl::UNSIGNED_INT_SAMPLER_2D_RECT, "usampler2DRect"),
+    (gl::IMAGE_1D, "image1D"),
+    (gl::IMAGE_2D, "image2D"),
+    (gl::IMAGE_3D, "image3D"),
+    (gl::IMAGE_2D_RECT, "image2DRect"),
+    (gl::IMAGE_CUBE, "imageCube"),
+    (gl::IMAGE_BUFFER, "imageBuffer"),
+    (gl::IMAGE_1D_ARRAY, "image1DArray"),
+    (gl::IMAGE_2D_ARRAY, "image2DArray"),
+    (gl::IMAGE_CUBE_MAP_ARRAY, "imageCubeArray"),
+    (gl::IMAGE_2D_MULTISAMPLE, "image2DMS"),
+    (gl::IMAGE_2D_MULTISAMPLE_ARRAY, "image2DMSArray"),
+    (gl::INT_IMAGE_1D, "iimage1D"),
+    (gl::INT_IMAGE_2D, "iimage2D"),
+    (gl::INT_IMAGE_3D, "iimage3D"),
+    (gl::INT_IMAGE_2D_RECT, "iimage2DRect"),
+    (gl::INT_IMAGE_CUBE, "iimageCube"),
+    (gl::INT_IMAGE_BUFFER, "iimageBuffer"),
+    (gl::INT_IMAGE_1D_ARRAY, "iimage1DArray"),
+    (gl::INT_IMAGE_2D_ARRAY, "iimage2DArray"),
+    (gl::INT_IMAGE_CUBE_MAP_ARRAY, "iimageCubeArray"),
+    (gl::INT_IMAGE_2D_MULTISAMPLE, "iimage2DMS"),
+    (gl::INT_IMAGE_2D_MULTISAMPLE_ARRAY, "iimage2DMSArray"),
+    (gl::UNSIGNED_INT_IMAGE_1D, "uimage1D"),
+    (gl::UNSIGNED_INT_IMAGE_2D, "uimage2D"),
+    (gl::UNSIGNED_INT_IMAGE_3D, "uimage3D"),
+    (gl::UNSIGNED_INT_IMAGE_2D_RECT, "uimage2DRect"),
+    (gl::UNSIGNED_INT_IMAGE_CUBE, "uimageCube"),
+    (gl::UNSIGNED_INT_IMAGE_BUFFER, "uimageBuffer"),
+    (gl::UNSIGNED_INT_IMAGE_1D_ARRAY, "uimage1DArray"),
+    (gl::UNSIGNED_INT_IMAGE_2D_ARRAY, "uimage2DArray"),
+    (gl::UNSIGNED_INT_IMAGE_CUBE_MAP_ARRAY, "uimageCubeArray"),
+    (gl::UNSIGNED_INT_IMAGE_2D_MULTISAMPLE, "uimage2DMS"),
+    (
+        gl::UNSIGNED_INT_IMAGE_2D_MULTISAMPLE_ARRAY,
+        "uimage2DMSArray",
+    ),
+    (gl::UNSIGNED_INT_ATOMIC_COUNTER, "atomic_uint"),
+];
+
+// A GLSL type, as an OpenGL type enum
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GlslType(pub GLenum);
+
+impl GlslType {
+    // The name of the type in GLSL source, e.g. "vec3"
+    pub fn name(&self) -> &'static str {
+        GLSL_TYPES
+            .iter()
+            .find(|(glsl_type, _)| *glsl_type == self.0)
+            .map_or("unknown", |(_, name)| name)
+    }
+
+    // Inverse of `name()`, which also reads back how unknown types are displayed
+    pub fn from_name(name: &str) -> Option<GlslType> {
+        if let Some(hex) = name
+            .strip_prefix("unknown (0x")
+            .and_then(|name| name.strip_suffix(')'))
+        {
+            return GLenum::from_str_radix(hex, 16).ok().map(GlslType);
+        }
+        GLSL_TYPES
+            .iter()
+            .find(|(_, type_name)| *type_name == name)
+            .map(|(glsl_type, _)| GlslType(*glsl_type))
+    }
+
+    pub fn is_sampler(&self) -> bool {
+        self.name().contains("sampler")
+    }
+
+    pub fn is_image(&self) -> bool {
+        self.name().contains("image")
+    }
+
+    // Samplers, images and atomic counters, which are bound to units rather than set by value
+    pub fn is_opaque(&self) -> bool {
+        self.is_sampler() || self.is_image() || self.0 == gl::UNSIGNED_INT_ATOMIC_COUNTER
+    }
+}
+
+impl std::fmt::Display for GlslType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.name() {
+            "unknown" => write!(f, "unknown (0x{:X})", self.0),
+            name => write!(f, "{}", name),
+        }
+    }
+}
+
+// Dumped as the GLSL type name
+impl Serialize for GlslType {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for GlslType {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let name = String::deserialize(deserializer)?;
+        GlslType::from_name(&name)
+            .ok_or_else(|| serde::de::Error::custom(format!("unknown GLSL type \"{}\"", name)))
+    }
+}
+
+// A uniform in the default block, i.e. one not declared inside of a uniform block
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActiveUniform {
+    pub name: String,
+    pub glsl_type: GlslType,
+    // 1 if the uniform isn't an array
+    pub array_size: GLint,
+    // None for atomic counters
+    pub location: Option<GLint>,
+    // Texture/image unit of samplers and images
+    pub binding: Option<GLint>,
+}
+
+// A vertex shader input
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActiveAttribute {
+    pub name: String,
+    pub glsl_type: GlslType,
+    pub array_size: GLint,
+    pub location: GLint,
+}
+
+// A variable declared inside of a uniform or storage block
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockMember {
+    pub name: String,
+    pub glsl_type: GlslType,
+    // 0 for the unsized array at the end of a storage block
+    pub array_size: GLint,
+    // In bytes, from the start of the block
+    pub offset: GLint,
+    pub array_stride: GLint,
+    pub matrix_stride: GLint,
+    pub row_major: bool,
+    // Storage blocks only report the first element of an array of structs, e.g. "particles[0].mass",
+    // these are the size (0 if unsized) and stride of that outermost array. Uniform blocks report
+    // every element, so these are always 1 and 0.
+    pub top_level_array_size: GLint,
+    pub top_level_array_stride: GLint,
+}
+
+// A uniform block, or a shader storage block
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActiveBlock {
+    pub name: String,
+    // The buffer binding point the block reads from
+    pub binding: GLint,
+    // Minimum size in bytes of a buffer backing the block
+    pub data_size: GLint,
+    pub members: Vec<BlockMember>,
+}
+
+impl ActiveBlock {
+    pub fn member<S>(&self, name: S) -> Option<&BlockMember>
+    where
+        S: AsRef<str>,
+    {
+        find(&self.members, name.as_ref(), |member| &member.name)
+    }
+}
+
+// Everything a linked program's shaders declare, and are actually using
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProgramInterface {
+    pub uniforms: Vec<ActiveUniform>,
+    pub attributes: Vec<ActiveAttribute>,
+    pub uniform_blocks: Vec<ActiveBlock>,
+    pub storage_blocks: Vec<ActiveBlock>,
+}
+
+impl ProgramInterface {
+    // Queries the interface of a linked program
+    pub(crate) fn new(program_id: GLuint) -> Self {
+        let resources = Resources { program_id };
+        ProgramInterface {
+            uniforms: resources.uniforms(),
+            attributes: resources.attributes(),
+            uniform_blocks: resources.blocks(gl::UNIFORM_BLOCK, gl::UNIFORM),
+            storage_blocks: resources.blocks(gl::SHADER_STORAGE_BLOCK, gl::BUFFER_VARIABLE),
+        }
+    }
+
+    // Array uniforms can be looked up with, or without, their "[0]" suffix
+    pub fn uniform<S>(&self, name: S) -> Option<&ActiveUniform>
+    where
+        S: AsRef<str>,
+    {
+        find(&self.uniforms, name.as_ref(), |uniform| &uniform.name)
+    }
+
+    pub fn attribute<S>(&self, name: S) -> Option<&ActiveAttribute>
+    where
+        S: AsRef<str>,
+    {
+        find(&self.attributes, name.as_ref(), |attribute| &attribute.name)
+    }
+
+    pub fn uniform_block<S>(&self, name: S) -> Option<&ActiveBlock>
+    where
+        S: AsRef<str>,
+    {
+        find(&self.uniform_blocks, name.as_ref(), |block| &block.name)
+    }
+
+    pub fn storage_block<S>(&self, name: S) -> Option<&ActiveBlock>
+    where
+        S: AsRef<str>,
+    {
+        find(&self.storage_blocks, name.as_ref(), |block| &block.name)
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    // Reads back a dump from `to_json()`, e.g. to compare against a later build's
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+}
+
+impl<'a, Any> GLProgram<'a, Any> {
+    // Reflects the program's active uniforms, attributes and blocks
+    pub fn interface(&self) -> ProgramInterface {
+        ProgramInterface::new(self.id)
+    }
+}
+
+// The declared type of a single uniform, without reflecting the whole program
+pub(crate) fn uniform_type(program_id: GLuint, name: &CStr) -> Option<GlslType> {
+    let index = unsafe { gl::GetProgramResourceIndex(program_id, gl::UNIFORM, name.as_ptr()) };
+    if index == gl::INVALID_INDEX {
+        return None;
+    }
+    let [glsl_type] = Resources { program_id }.properties(gl::UNIFORM, index, [gl::TYPE]);
+    Some(GlslType(glsl_type as GLenum))
+}
+
+// A single uniform (or with `gl::SHADER_STORAGE_BLOCK`, storage) block, and its members
+pub(crate) fn block(program_id: GLuint, interface: GLenum, name: &CStr) -> Option<ActiveBlock> {
+    let variables = match interface {
+        gl::SHADER_STORAGE_BLOCK => gl::BUFFER_VARIABLE,
+        _ => gl::UNIFORM,
+    };
+    let index = unsafe { gl::GetProgramResourceIndex(program_id, interface, name.as_ptr()) };
+    if index == gl::INVALID_INDEX {
+        return None;
+    }
+    Some(Resources { program_id }.block(interface, variables, index))
+}
+
+// Looks a resource up by name, ignoring the "[0]" OpenGL appends to the names of arrays
+fn find<'b, T, F>(resources: &'b [T], name: &str, resource_name: F) -> Option<&'b T>
+where
+    F: Fn(&T) -> &String,
+{
+    let trimmed = |name: &'b str| name.strip_suffix("[0]").unwrap_or(name);
+    let name = name.strip_suffix("[0]").unwrap_or(name);
+    resources
+        .iter()
+        .find(|resource| trimmed(resource_name(resource)) == name)
+}
+
+// Wraps the glGetProgramInterface/glGetProgramResource calls
+struct Resources {
+    program_id: GLuint,
+}
+
+impl Resources {
+    fn uniforms(&self) -> Vec<ActiveUniform> {
+        let properties = [gl::TYPE, gl::ARRAY_SIZE, gl::LOCATION, gl::BLOCK_INDEX];
+        (0..self.count(gl::UNIFORM))
+            .filter_map(|index| {
+                let [glsl_type, array_size, location, block_index] =
+                    self.properties(gl::UNIFORM, index, properties);
+                // Members of uniform blocks are listed with their block
+                if block_index != -1 {
+                    return None;
+                }
+                let glsl_type = GlslType(glsl_type as GLenum);
+                let location = (location >= 0).then_some(location);
+                let binding = match (glsl_type.is_opaque(), location) {
+                    (true, Some(location)) => {
+                        let mut unit = 0;
+                        unsafe {
+                            gl::GetUniformiv(self.program_id, location, &mut unit);
+                        }
+                        Some(unit)
+                    }
+                    _ => None,
+                };
+                Some(ActiveUniform {
+                    name: self.name(gl::UNIFORM, index),
+                    glsl_type,
+                    array_size,
+                    location,
+                    binding,
+                })
+            })
+            .collect()
+    }
+
+    fn attributes(&self) -> Vec<ActiveAttribute> {
+        let properties = [gl::TYPE, gl::ARRAY_SIZE, gl::LOCATION];
+        (0..self.count(gl::PROGRAM_INPUT))
+            .filter_map(|index| {
+                let [glsl_type, array_size, location] =
+                    self.properties(gl::PROGRAM_INPUT, index, properties);
+                // Built-in inputs, e.g. gl_VertexID, have no location
+                (location >= 0).then(|| ActiveAttribute {
+                    name: self.name(gl::PROGRAM_INPUT, index),
+                    glsl_type: GlslType(glsl_type as GLenum),
+                    array_size,
+                    location,
+                })
+            })
+            .collect()
+    }
+
+    // Blocks of either interface, and their members from the matching variable interface
+    fn blocks(&self, interface: GLenum, variables: GLenum) -> Vec<ActiveBlock> {
+        (0..self.count(interface))
+            .map(|index| self.block(interface, variables, index))
+            .collect()
+    }
+
+    fn block(&self, interface: GLenum, variables: GLenum, index: GLuint) -> ActiveBlock {
+        let properties = [
+            gl::BUFFER_BINDING,
+            gl::BUFFER_DATA_SIZE,
+            gl::NUM_ACTIVE_VARIABLES,
+        ];
+        let [binding, data_size, num_members] = self.properties(interface, index, properties);
+        let mut member_indices = vec![0; num_members.max(0) as usize];
+        if !member_indices.is_empty() {
+            unsafe {
+                gl::GetProgramResourceiv(
+                    self.program_id,
+                    interface,
+                    index,
+                    1,
+                    &gl::ACTIVE_VARIABLES,
+                    member_indices.len() as GLsizei,
+                    std::ptr::null_mut(),
+                    member_indices.as_mut_ptr(),
+                );
+            }
+        }
+        let mut members: Vec<BlockMember> = member_indices
+            .into_iter()
+            .map(|member| self.member(variables, member as GLuint))
+            .collect();
+        members.sort_by_key(|member| member.offset);
+
+        ActiveBlock {
+            name: self.name(interface, index),
+            binding,
+            data_size,
+            members,
+        }
+    }
+
+    fn member(&self, interface: GLenum, index: GLuint) -> BlockMember {
+        let properties = [
+            gl::TYPE,
+            gl::ARRAY_SIZE,
+            gl::OFFSET,
+            gl::ARRAY_STRIDE,
+            gl::MATRIX_STRIDE,
+            gl::IS_ROW_MAJOR,
+        ];
+        let [glsl_type, array_size, offset, array_stride, matrix_stride, row_major] =
+            self.properties(interface, index, properties);
+        let [top_level_array_size, top_level_array_stride] = match interface {
+            gl::BUFFER_VARIABLE => self.properties(
+                interface,
+                index,
+                [gl::TOP_LEVEL_ARRAY_SIZE, gl::TOP_LEVEL_ARRAY_STRIDE],
+            ),
+            _ => [1, 0],
+        };
+        BlockMember {
+            name: self.name(interface, index),
+            glsl_type: GlslType(glsl_type as GLenum),
+            array_size,
+            offset,
+            array_stride,
+            matrix_stride,
+            row_major: row_major != 0,
+            top_level_array_size,
+            top_level_array_stride,
+        }
+    }
+
+    // Number of active resources in the interface
+    fn count(&self, interface: GLenum) -> GLuint {
+        let mut count = 0;
+        unsafe {
+            gl::GetProgramInterfaceiv(self.program_id, interface, gl::ACTIVE_RESOURCES, &mut count);
+        }
+        count.max(0) as GLuint
+    }
+
+    fn properties<const N: usize>(
+        &self,
+        interface: GLenum,
+        index: GLuint,
+        properties: [GLenum; N],
+    ) -> [GLint; N] {
+        let mut values = [0; N];
+        unsafe {
+            gl::GetProgramResourceiv(
+                self.program_id,
+                interface,
+                index,
+                N as GLsizei,
+                properties.as_ptr(),
+                N as GLsizei,
+                std::ptr::null_mut(),
+                values.as_mut_ptr(),
+            );
+        }
+        values
+    }
+
+    fn name(&self, interface: GLenum, index: GLuint) -> String {
+        let [length] = self.properties(interface, index, [gl::NAME_LENGTH]);
+        let mut name = vec![0u8; length.max(1) as usize];
+        let mut written = 0;
+        unsafe {
+            gl::GetProgramResourceName(
+                self.program_id,
+                interface,
+                index,
+                name.len() as GLsizei,
+                &mut written,
+                name.as_mut_ptr() as *mut GLchar,
+            );
+        }
+        name.truncate(written.max(0) as usize);
+        String::from_utf8_lossy(&name).into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn interface() -> ProgramInterface {
+        let uniform = |name: &str, glsl_type, array_size| ActiveUniform {
+            name: name.to_string(),
+            glsl_type: GlslType(glsl_type),
+            array_size,
+            location: Some(0),
+            binding: None,
+        };
+        ProgramInterface {
+            uniforms: vec![
+                uniform("time", gl::FLOAT, 1),
+                uniform("weights[0]", gl::FLOAT_VEC4, 8),
+                ActiveUniform {
+                    binding: Some(3),
+                    ..uniform("shadow_maps", gl::SAMPLER_2D_ARRAY_SHADOW, 1)
+                },
+            ],
+            attributes: vec![ActiveAttribute {
+                name: "vertices".to_string(),
+                glsl_type: GlslType(gl::FLOAT_VEC3),
+                array_size: 1,
+                location: 0,
+            }],
+            uniform_blocks: vec![ActiveBlock {
+                name: "Camera".to_string(),
+                binding: 0,
+                data_size: 64,
+                members: vec![BlockMember {
+                    name: "view[0]".to_string(),
+                    glsl_type: GlslType(gl::FLOAT_MAT4),
+                    array_size: 1,
+                    offset: 0,
+                    array_stride: 0,
+                    matrix_stride: 16,
+                    row_major: false,
+                    top_level_array_size: 1,
+                    top_level_array_stride: 0,
+                }],
+            }],
+            storage_blocks: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn json_round_trips() {
+        let mut interface = interface();
+        interface.attributes[0].glsl_type = GlslType(0x1234);
+        let json = interface.to_json().unwrap();
+        assert!(json.contains("\"glsl_type\": \"sampler2DArrayShadow\""));
+        assert!(json.contains("\"glsl_type\": \"unknown (0x1234)\""));
+        assert_eq!(ProgramInterface::from_json(&json).unwrap(), interface);
+
+        let misspelled = json.replace("\"vec4\"", "\"vec5\"");
+        assert!(ProgramInterface::from_json(&misspelled).is_err());
+    }
+
+    #[test]
+    fn types_are_named_as_in_glsl() {
+        let names = [
+            (gl::FLOAT, "float"),
+            (gl::INT, "int"),
+            (gl::UNSIGNED_INT, "uint"),
+            (gl::BOOL, "bool"),
+            (gl::DOUBLE, "double"),
+            (gl::FLOAT_VEC3, "vec3"),
+            (gl::INT_VEC2, "ivec2"),
+            (gl::UNSIGNED_INT_VEC4, "uvec4"),
+            (gl::BOOL_VEC3, "bvec3"),
+            (gl::FLOAT_MAT4, "mat4"),
+            (gl::FLOAT_MAT2x3, "mat2x3"),
+            (gl::DOUBLE_MAT4x3, "dmat4x3"),
+            (gl::SAMPLER_2D, "sampler2D"),
+            (gl::SAMPLER_CUBE_MAP_ARRAY, "samplerCubeArray"),
+            (gl::INT_SAMPLER_3D, "isampler3D"),
+            (gl::UNSIGNED_INT_SAMPLER_2D_ARRAY, "usampler2DArray"),
+        ];
+        for (glsl_type, name) in names {
+            assert_eq!(GlslType(glsl_type).name(), name);
+            assert_eq!(GlslType::from_name(name), Some(GlslType(glsl_type)));
+        }
+        assert_eq!(GlslType(0xBEEF).to_string(), "unknown (0xBEEF)");
+
+        assert!(GlslType(gl::SAMPLER_2D_SHADOW).is_sampler());
+        assert!(GlslType(gl::UNSIGNED_INT_IMAGE_2D).is_opaque());
+        assert!(GlslType(gl::UNSIGNED_INT_ATOMIC_COUNTER).is_opaque());
+        assert!(!GlslType(gl::FLOAT_MAT3).is_opaque());
+    }
+
+    #[test]
+    fn array_names_are_found_without_their_suffix() {
+        let interface = interface();
+        assert_eq!(
+            interface
+                .uniform("weights")
+                .map(|uniform| uniform.array_size),
+            Some(8)
+        );
+        assert!(interface.uniform("weights[0]").is_some());
+        // Only the first element's name is stripped
+        assert!(interface.uniform("weights[1]").is_none());
+        assert!(interface.uniform("time").is_some());
+        assert!(interface.uniform("tim").is_none());
+
+        let camera = interface.uniform_block("Camera").unwrap();
+        assert!(camera.member("view").is_some());
+        assert!(interface.uniform_block("Camera[0]").is_some());
+        assert!(interface.storage_block("Camera").is_none());
+    }
+}
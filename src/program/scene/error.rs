@@ -0,0 +1,39 @@
+use super::SceneInput;
+
+// Error type for Scenes
+#[derive(Debug)]
+pub enum SceneError {
+    MissingInputs(Vec<SceneInput>),
+}
+
+impl std::error::Error for SceneError {}
+impl std::fmt::Display for SceneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SceneError::MissingInputs(inputs) => {
+                writeln!(
+                    f,
+                    "The program's shaders don't declare inputs the scene needs to be drawn:"
+                )?;
+                for input in inputs {
+                    writeln!(f, "    {:?} {} {}", input.kind, input.glsl_type, input.name)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+// Allows for painless casting into our crate's rollup error
+impl From<SceneError> for crate::GLError {
+    fn from(error: SceneError) -> Self {
+        crate::GLError::Scene(error)
+    }
+}
+
+// Allows for painless casting into our crate's rollup error
+impl From<SceneError> for crate::program::ProgramError {
+    fn from(error: SceneError) -> Self {
+        crate::program::ProgramError::Scene(error)
+    }
+}
@@ -0,0 +1,622 @@
+// A scene of meshes, the objects instancing them, lights and a camera, that any GLProgram can
+// render. The built-in programs are all scenes, and custom shaders become one by declaring (some of)
+// the inputs listed in `SCENE_INPUTS`.
+pub mod error;
+pub use error::SceneError;
+
+use super::mesh::{Attached, Mesh, Unattached};
+use super::GLDraw;
+use super::GLProgram;
+use crate::program::camera::ArcBallCamera;
+use crate::program::clusters::{ClusterSettings, LightClusters};
+use crate::program::scene_object::{SceneObject, SceneObjectError};
+use crate::program::shadows::{
+    ShadowError, ShadowMaps, ShadowSettings, SHADOW_CUBE_MAP_TEXTURE_UNIT, SHADOW_MAP_TEXTURE_UNIT,
+};
+use crate::program::skybox::Skybox;
+use crate::program::Camera;
+use crate::program::ProgramError;
+use crate::program::{LightColor, LightSource};
+use crate::render_graph::{PostProcessing, RenderGraph};
+use crate::texture::Texture;
+use crate::types::*;
+use crate::Position;
+
+use gl::types::*;
+use glfw::Context;
+
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::io::{self, Write};
+use std::rc::{Rc, Weak};
+type Result<T> = std::result::Result<T, ProgramError>;
+
+use ultraviolet::mat::Mat4;
+use ultraviolet::vec::{Vec3, Vec4};
+
+// Shader inputs the scene provides, each checked for by `missing_scene_inputs()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputKind {
+    // Vertex attribute, e.g. `in vec3 vertices`
+    Attribute,
+    Uniform,
+    // Shader Storage Block, e.g. `buffer Lights { ... }`
+    StorageBlock,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SceneInput {
+    pub name: &'static str,
+    pub kind: InputKind,
+    // How the shaders should declare it
+    pub glsl_type: &'static str,
+    // The scene can't be drawn without it; the rest enable features (lighting, shadows, ...)
+    pub required: bool,
+}
+
+impl SceneInput {
+    const fn new(
+        name: &'static str,
+        kind: InputKind,
+        glsl_type: &'static str,
+        required: bool,
+    ) -> Self {
+        SceneInput {
+            name,
+            kind,
+            glsl_type,
+            required,
+        }
+    }
+}
+
+// The documented set of names a program's shaders use to receive the scene
+pub const SCENE_INPUTS: [SceneInput; 18] = [
+    // Per-vertex
+    SceneInput::new("vertices", InputKind::Attribute, "vec3", true),
+    SceneInput::new("normals", InputKind::Attribute, "vec3", false),
+    SceneInput::new("st_coordinates", InputKind::Attribute, "vec2", false),
+    // Per-object (instanced)
+    SceneInput::new("object_mw_transforms", InputKind::Attribute, "mat4", true),
+    SceneInput::new(
+        "object_mw_normal_transforms",
+        InputKind::Attribute,
+        "mat3",
+        false,
+    ),
+    SceneInput::new(
+        "object_receives_shadows",
+        InputKind::Attribute,
+        "float",
+        false,
+    ),
+    // Camera
+    SceneInput::new("view_projection_matrix", InputKind::Uniform, "mat4", true),
+    SceneInput::new("view_matrix", InputKind::Uniform, "mat4", false),
+    SceneInput::new("camera_position", InputKind::Uniform, "vec3", false),
+    // Lights, binned into clusters
+    SceneInput::new("ambient_light_color", InputKind::Uniform, "vec4", false),
+    SceneInput::new("num_lights", InputKind::Uniform, "uint", false),
+    SceneInput::new("cluster_grid", InputKind::Uniform, "uvec4", false),
+    SceneInput::new("cluster_depth", InputKind::Uniform, "vec4", false),
+    SceneInput::new(
+        "Lights",
+        InputKind::StorageBlock,
+        "std430, binding = 1",
+        false,
+    ),
+    SceneInput::new(
+        "LightClusters",
+        InputKind::StorageBlock,
+        "std430, binding = 2",
+        false,
+    ),
+    SceneInput::new(
+        "LightIndices",
+        InputKind::StorageBlock,
+        "std430, binding = 3",
+        false,
+    ),
+    // Shadows
+    SceneInput::new(
+        "shadow_maps",
+        InputKind::Uniform,
+        "sampler2DArrayShadow",
+        false,
+    ),
+    SceneInput::new(
+        "shadow_cube_maps",
+        InputKind::Uniform,
+        "samplerCubeArray",
+        false,
+    ),
+];
+
+pub struct Scene {
+    pub(crate) camera: Box<dyn Camera>,
+    pub(crate) lights: Vec<LightSource>,
+    // Storage buffers for the lights, and the clusters they're binned into
+    pub(crate) clusters: Option<LightClusters>,
+    // Created the first time a light has its shadows enabled
+    pub(crate) shadows: Option<ShadowMaps>,
+    pub(crate) scene_objects: HashMap<String, Rc<SceneObject>>,
+    pub(crate) meshes: HashMap<String, Mesh<Attached>>,
+    // Drawn behind everything else, and reflected by materials
+    pub(crate) skybox: Option<Skybox>,
+    // Renders the scene off-screen, then post-processes it onto the window
+    pub(crate) render_graph: Option<RenderGraph>,
+    pub(crate) stdout: std::io::StdoutLock<'static>,
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Scene::default()
+    }
+
+    pub fn camera(&self) -> &dyn Camera {
+        self.camera.as_ref()
+    }
+
+    // Replaces the camera the scene is viewed through
+    pub fn set_camera<C>(&mut self, camera: C)
+    where
+        C: Camera + 'static,
+    {
+        self.camera = Box::new(camera);
+    }
+
+    pub fn lights(&self) -> &[LightSource] {
+        &self.lights
+    }
+
+    // Names of the attached meshes
+    pub fn meshes(&self) -> impl Iterator<Item = &str> {
+        self.meshes.keys().map(|name| name.as_str())
+    }
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Scene {
+            camera: Box::new(ArcBallCamera::new()),
+            lights: Vec::new(),
+            clusters: None,
+            shadows: None,
+            scene_objects: HashMap::new(),
+            meshes: HashMap::new(),
+            skybox: None,
+            render_graph: None,
+            stdout: io::stdout().lock(),
+        }
+    }
+}
+
+// GLProgram data types that render a scene (e.g. Blinn-Phong, PBR, custom shaders) hand it out
+// through this trait, and get all of the scene machinery below
+pub trait SceneData {
+    // True if the program's fragment shader tone maps and gamma corrects its own output
+    const TONE_MAPPED: bool = false;
+
+    fn scene(&self) -> &Scene;
+    fn scene_mut(&mut self) -> &mut Scene;
+}
+
+impl<'a, Data: SceneData> GLProgram<'a, Data> {
+    // Add a mesh to the scene
+    pub fn attach_mesh(&mut self, mesh: Mesh<Unattached>) -> Result<()> {
+        let key = mesh.name.clone();
+        let value = mesh.attach(self.id)?;
+        self.data.scene_mut().meshes.insert(key, value);
+        Ok(())
+    }
+
+    // Instantiates a mesh as a new, named, object. Transform is a Mat4 representing the affine
+    // transformation of the object in world space.
+    pub fn create_object<S>(&mut self, name: S, mesh_name: S, transform: Mat4) -> ()
+    where
+        S: AsRef<str>,
+    {
+        // Create a new SceneObject
+        let object = SceneObject::new(transform);
+        let object = Rc::new(object);
+        let mesh_object = Rc::downgrade(&object);
+
+        // Look up the mesh
+        let key = mesh_name.as_ref();
+        let mesh = self.data.scene_mut().meshes.get_mut(key).unwrap();
+
+        // Insert a weak reference to the object into the Mesh's own storage
+        mesh.data.objects.push(mesh_object);
+
+        // Insert the object into GLProgram's own map
+        let key = name.as_ref().to_string();
+        let value = object;
+        self.data.scene_mut().scene_objects.insert(key, value);
+    }
+
+    // Adds a point light to the scene
+    pub fn add_light(&mut self, position: &Position, color: &LightColor) -> Result<()> {
+        self.add_light_source(LightSource::new(color, position))?;
+        Ok(())
+    }
+
+    // Adds any type of light to the scene, and returns its index (used to toggle its shadows)
+    pub fn add_light_source(&mut self, light: LightSource) -> Result<usize> {
+        let scene = self.data.scene_mut();
+        scene.lights.push(light);
+        // Since we're buffering to the GPU we don't want extra mememory at the end
+        // Not sure this matters at all lmao, might be triggering extra allocations per light add
+        // self.data.lights.shrink_to_fit();
+        self.update_lights()?;
+        Ok(self.data.scene().lights.len() - 1)
+    }
+
+    // Replaces the light at index `light`, e.g. to move it. Its shadows, if enabled, follow it.
+    pub fn set_light(&mut self, light: usize, source: LightSource) -> Result<()> {
+        let scene = self.data.scene_mut();
+        let slot = scene
+            .lights
+            .get_mut(light)
+            .ok_or(ShadowError::LightDoesNotExist(light))?;
+        *slot = source;
+        if let Some(shadows) = scene.shadows.as_mut() {
+            shadows.allocate(&mut scene.lights);
+        }
+        self.update_lights()
+    }
+
+    // Removes the light at index `light`, along with its shadows. The lights after it move down
+    // an index.
+    pub fn remove_light(&mut self, light: usize) -> Result<LightSource> {
+        let scene = self.data.scene_mut();
+        if light >= scene.lights.len() {
+            return Err(ShadowError::LightDoesNotExist(light).into());
+        }
+        let removed = scene.lights.remove(light);
+        if let Some(shadows) = scene.shadows.as_mut() {
+            shadows.remove_light(&mut scene.lights, light);
+        }
+        self.update_lights()?;
+        Ok(removed)
+    }
+
+    // Buffers the lights to the GPU, and updates how many there are
+    fn update_lights(&mut self) -> Result<()> {
+        // Initialize the Storage Buffers for the lights, if we haven't alreaady
+        let scene = self.data.scene_mut();
+        let clusters = scene.clusters.get_or_insert_with(LightClusters::new);
+        clusters.buffer_lights(&scene.lights);
+        let grid = clusters.grid();
+        let num_lights: GLuint = scene.lights.len() as u32;
+        self.update_scene_uniform("cluster_grid", &grid)?;
+
+        // Update the number of lights
+        self.update_scene_uniform("num_lights", &num_lights)?;
+
+        Ok(())
+    }
+
+    // Changes how lights are binned into clusters (or turns the culling off)
+    pub fn light_clusters(&mut self, settings: ClusterSettings) -> Result<()> {
+        let clusters = self
+            .data
+            .scene_mut()
+            .clusters
+            .get_or_insert_with(LightClusters::new);
+        clusters.set_settings(settings);
+        let grid = clusters.grid();
+        self.update_scene_uniform("cluster_grid", &grid)
+    }
+
+    // Renders a shadow map for the light at index `light` every frame, and applies it to every
+    // object that receives shadows
+    pub fn enable_shadows(&mut self, light: usize, settings: ShadowSettings) -> Result<()> {
+        let scene = self.data.scene_mut();
+        if light >= scene.lights.len() {
+            return Err(ShadowError::LightDoesNotExist(light).into());
+        }
+        let shadows = match scene.shadows.take() {
+            Some(shadows) => shadows,
+            None => ShadowMaps::new()?,
+        };
+        let shadows = scene.shadows.insert(shadows);
+        shadows.enable(&mut scene.lights, light, settings)?;
+        self.update_lights()
+    }
+
+    // Stops rendering the shadow map for the light at index `light`
+    pub fn disable_shadows(&mut self, light: usize) -> Result<()> {
+        let scene = self.data.scene_mut();
+        match scene.shadows.as_mut() {
+            Some(shadows) => shadows.disable(&mut scene.lights, light)?,
+            None if light < scene.lights.len() => return Ok(()),
+            None => return Err(ShadowError::LightDoesNotExist(light).into()),
+        }
+        self.update_lights()
+    }
+
+    // Sets the width and height, in texels, of every shadow map face
+    pub fn shadow_map_resolution(&mut self, resolution: u32) -> Result<()> {
+        let scene = self.data.scene_mut();
+        if let Some(shadows) = scene.shadows.as_mut() {
+            shadows.set_resolution(&mut scene.lights, resolution as GLsizei);
+        }
+        Ok(())
+    }
+
+    // Sets whether the named object is drawn into shadow maps, and whether shadows fall onto it
+    pub fn object_shadows<S>(&mut self, name: S, cast: bool, receive: bool) -> Result<()>
+    where
+        S: AsRef<str>,
+    {
+        let object = self
+            .data
+            .scene()
+            .scene_objects
+            .get(name.as_ref())
+            .ok_or(SceneObjectError::DoesNotExist(name.as_ref().to_string()))?;
+        object.cast_shadows.set(cast);
+        object.receive_shadows.set(receive);
+        Ok(())
+    }
+
+    // Set the ambient light for the scene
+    pub fn ambient_light(&mut self, color: &LightColor) -> Result<Weak<dyn UpdateUniform>> {
+        self.create_uniform("ambient_light_color", &color.clone().to_vec4())
+    }
+
+    // Sets the OpenGL state the scene is drawn with, and creates its uniforms
+    pub(crate) fn initialize(&mut self) -> Result<()> {
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            gl::CullFace(gl::BACK);
+            gl::PointSize(3.0);
+        }
+
+        // Create the uniforms the program's shaders declare
+        let zero_vector = Vec4::default();
+        let identity_matrix = Mat4::identity();
+        self.create_scene_uniform("ambient_light_color", &zero_vector)?;
+        self.create_scene_uniform("view_projection_matrix", &identity_matrix)?;
+        self.create_scene_uniform("camera_position", &Vec3::zero())?;
+        self.create_scene_uniform("num_lights", &0u32)?;
+        self.create_scene_uniform("shadow_maps", &SHADOW_MAP_TEXTURE_UNIT)?;
+        self.create_scene_uniform("shadow_cube_maps", &SHADOW_CUBE_MAP_TEXTURE_UNIT)?;
+        self.create_scene_uniform("view_matrix", &identity_matrix)?;
+        self.create_scene_uniform("cluster_grid", &(0u32, 0u32, 0u32, 0u32))?;
+        self.create_scene_uniform("cluster_depth", &(1.0f32, 1.0f32, 0.1f32, 10000.0f32))?;
+
+        self.context
+            .glfw
+            .set_swap_interval(glfw::SwapInterval::None);
+        Ok(())
+    }
+
+    // Draws a cube map behind the scene (replacing the current one, if any)
+    pub fn skybox(&mut self, cube_map: Rc<Texture>) -> Result<()> {
+        let skybox = Skybox::new(cube_map)?;
+        self.data.scene_mut().skybox = Some(skybox);
+        Ok(())
+    }
+
+    pub fn remove_skybox(&mut self) {
+        self.data.scene_mut().skybox = None;
+    }
+
+    // Renders the scene into the graph's `SCENE_TARGET`, then runs its passes each frame
+    pub fn render_graph(&mut self, graph: RenderGraph) {
+        self.data.scene_mut().render_graph = Some(graph);
+    }
+
+    // Post-processes the scene with a chain of effects. Programs stop tone mapping their own
+    // output, so the chain should include tone mapping (and gamma correction).
+    pub fn post_processing(&mut self, chain: &PostProcessing) -> Result<()> {
+        let graph = chain.build()?;
+        self.render_graph(graph);
+        Ok(())
+    }
+
+    // Goes back to rendering straight to the window
+    pub fn remove_render_graph(&mut self) {
+        self.data.scene_mut().render_graph = None;
+    }
+
+    // Whether the program should tone map its own output, or leave it to post-processing
+    pub(crate) fn tone_mapped(&self) -> bool {
+        Data::TONE_MAPPED && self.data.scene().render_graph.is_none()
+    }
+
+    // Everything a scene program does each frame before drawing its meshes: renders the shadow maps,
+    // clears the screen, and updates the camera and light cluster uniforms
+    pub(crate) fn prepare_scene(&mut self) -> Result<()> {
+        // Render the shadow maps first, since they're sampled by the main pass
+        let scene = self.data.scene_mut();
+        if let Some(shadows) = scene.shadows.as_ref().filter(|s| !s.is_empty()) {
+            // The lights may have moved since the last frame
+            if shadows.update(&mut scene.lights) {
+                if let Some(clusters) = scene.clusters.as_mut() {
+                    clusters.buffer_lights(&scene.lights);
+                }
+            }
+            shadows.render(&scene.lights, &scene.meshes)?;
+            let (width, height) = self.context.window.get_framebuffer_size();
+            unsafe {
+                gl::Viewport(0, 0, width, height);
+            }
+            shadows.bind();
+        }
+
+        // Render into the graph's scene target instead of the window
+        let (mut width, mut height) = self.context.window.get_framebuffer_size();
+        if let Some(graph) = self.data.scene_mut().render_graph.as_mut() {
+            (width, height) = graph.begin(width, height)?;
+        }
+
+        // Set OpenGL State for this Program
+        unsafe {
+            gl::UseProgram(self.id);
+            gl::ClearColor(0.0, 0.0, 0.0, 0.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+
+        // Set uniforms for vertex view-perspective transform, and camera position
+        if let Some(vpm) = self.data.scene_mut().camera.view_projection_matrix() {
+            let camera = &self.data.scene().camera;
+            let camera_position = camera.position();
+            let view_matrix = camera.view_matrix();
+            self.update_scene_uniform("view_projection_matrix", &vpm)?;
+            self.update_scene_uniform("camera_position", &camera_position)?;
+            self.update_scene_uniform("view_matrix", &view_matrix)?;
+            if let Some(clusters) = self.data.scene_mut().clusters.as_mut() {
+                clusters.invalidate();
+            }
+        }
+
+        // Re-bin the lights into clusters if the lights or the camera changed
+        let scene = self.data.scene_mut();
+        if let Some(clusters) = scene.clusters.as_mut() {
+            let view = scene.camera.view_matrix();
+            let projection = scene.camera.projection_matrix();
+            clusters.update(&scene.lights, &view, &projection);
+            clusters.bind();
+
+            let depth = clusters.depth(width, height);
+            self.update_scene_uniform("cluster_depth", &depth)?;
+        }
+
+        Ok(())
+    }
+
+    // Everything a scene program does each frame after drawing its meshes: draws the skybox into
+    // whatever is left uncovered, then runs the render graph's passes
+    pub(crate) fn finish_scene(&self) -> Result<()> {
+        let scene = self.data.scene();
+        if let Some(skybox) = scene.skybox.as_ref() {
+            let view = scene.camera.view_matrix();
+            let projection = scene.camera.projection_matrix();
+            skybox.draw(&view, &projection, self.tone_mapped())?;
+        }
+        if let Some(graph) = scene.render_graph.as_ref() {
+            graph.execute()?;
+        }
+        unsafe {
+            gl::UseProgram(self.id);
+        }
+        Ok(())
+    }
+
+    // Draws the next frame of the program
+    pub fn render(&mut self) -> Result<()>
+    where
+        Self: GLDraw,
+    {
+        self.context.glfw.poll_events();
+        // Sets up 'self.context.frame_state' based on polled events
+        self.context.process_events();
+        // Check if we should exit
+        if self.context.window.should_close() {
+            return Err(ProgramError::End);
+        }
+
+        // Update our camera based off of keyboard input
+        // TODO: Generate the matrices in here, and only return them if they have changed
+        self.data
+            .scene_mut()
+            .camera
+            .update(&mut self.context.frame_state.camera_events);
+
+        self.draw()?;
+        self.context.debug_errors()?;
+
+        // FPS / Frame Interval Counter
+        if self.context.frame_state.frame % 60 == 0 {
+            let dt_60 = self.context.frame_state.delta_t_60.as_secs_f64();
+            let dt = self.context.frame_state.delta_t.as_secs_f64();
+            let stdout = &mut self.data.scene_mut().stdout;
+            write!(
+                stdout,
+                "frame: {}\tinterval: {:.4}ms\tfps: {:.2}\r",
+                self.context.frame_state.frame,
+                dt * 1000.0,
+                60.0 / dt_60,
+            )
+            .unwrap();
+            stdout.flush().unwrap();
+        }
+
+        self.context.window.swap_buffers();
+        Ok(())
+    }
+
+    pub fn scene(&self) -> &Scene {
+        self.data.scene()
+    }
+
+    pub fn scene_mut(&mut self) -> &mut Scene {
+        self.data.scene_mut()
+    }
+
+    // Every input in `SCENE_INPUTS` the program's shaders don't declare (or optimized out)
+    pub fn missing_scene_inputs(&self) -> Vec<SceneInput> {
+        SCENE_INPUTS
+            .iter()
+            .filter(|input| !self.declares(input.name, input.kind))
+            .copied()
+            .collect()
+    }
+
+    // Checks the program declares every input the scene can't be drawn without
+    pub fn validate_scene(&self) -> Result<()> {
+        let missing: Vec<SceneInput> = self
+            .missing_scene_inputs()
+            .into_iter()
+            .filter(|input| input.required)
+            .collect();
+        match missing.is_empty() {
+            true => Ok(()),
+            false => Err(SceneError::MissingInputs(missing).into()),
+        }
+    }
+
+    // Looks the input up in the linked program
+    fn declares(&self, name: &str, kind: InputKind) -> bool {
+        let name = match CString::new(name) {
+            Ok(name) => name,
+            Err(_) => return false,
+        };
+        unsafe {
+            match kind {
+                InputKind::Attribute => gl::GetAttribLocation(self.id, name.as_ptr()) >= 0,
+                InputKind::Uniform => gl::GetUniformLocation(self.id, name.as_ptr()) >= 0,
+                InputKind::StorageBlock => {
+                    gl::GetProgramResourceIndex(self.id, gl::SHADER_STORAGE_BLOCK, name.as_ptr())
+                        != gl::INVALID_INDEX
+                }
+            }
+        }
+    }
+
+    // Scene uniforms are optional for custom shaders, so they're only created when declared
+    fn create_scene_uniform<Value>(&mut self, name: &str, value: &Value) -> Result<()>
+    where
+        Value: UniformValue + 'static,
+    {
+        if self.declares(name, InputKind::Uniform) {
+            self.create_uniform(name, value)?;
+        }
+        Ok(())
+    }
+
+    // ...and only updated if they were created
+    fn update_scene_uniform<Value>(&self, name: &str, value: &Value) -> Result<()>
+    where
+        Value: UniformValue,
+    {
+        match self.uniforms.contains_key(name) {
+            true => self.update_uniform(name, value),
+            false => Ok(()),
+        }
+    }
+}
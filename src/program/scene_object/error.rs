@@ -7,6 +7,7 @@ use crate::GLUtilityError;
 pub enum SceneObjectError {
     FailedToParseFile(String),
     UnknownFileType(String),
+    DoesNotExist(String),
     Other(GLUtilityError),
     VAO(VAOError),
 }
@@ -21,6 +22,9 @@ impl std::fmt::Display for SceneObjectError {
             SceneObjectError::UnknownFileType(extension) => {
                 write!(f, "Did not recognize 3D file type: '.{}'", extension)
             }
+            SceneObjectError::DoesNotExist(name) => {
+                write!(f, "No SceneObject named '{}' exists in this program", name)
+            }
             SceneObjectError::Other(error) => {
                 write!(f, "Encountered a Mesh Error: {}\n", error)
             }
@@ -6,6 +6,9 @@ type Result<T> = std::result::Result<T, SceneObjectError>;
 // Linear Algebra Types
 use ultraviolet::mat::{Mat3, Mat4};
 
+// Objects are shared with their Mesh behind an Rc, so flags that change after creation are Cells
+use std::cell::Cell;
+
 // If the option is set, then the SceneObject has updated its transformation. If it's None then it
 // does not need to be updated
 #[derive(Debug)]
@@ -16,6 +19,9 @@ pub(crate) struct SceneObject {
     // Pre-multiplied: world * model transforms. None if it hasn't changed since last query.
     pub(crate) transform: Option<Mat4>,
     pub(crate) normal_transform: Option<Mat3>, // Might not need be an option
+    // Whether the object is drawn into shadow maps, and whether shadows are applied to it
+    pub(crate) cast_shadows: Cell<bool>,
+    pub(crate) receive_shadows: Cell<bool>,
 }
 
 impl SceneObject {
@@ -28,6 +34,8 @@ impl SceneObject {
             world_transform: Mat4::identity(),
             transform: Some(model_transform),
             normal_transform,
+            cast_shadows: Cell::new(true),
+            receive_shadows: Cell::new(true),
         }
     }
 }
@@ -40,6 +48,8 @@ impl Default for SceneObject {
             world_transform: Mat4::identity(),
             transform: Some(Mat4::identity()),
             normal_transform: Some(Mat3::identity()),
+            cast_shadows: Cell::new(true),
+            receive_shadows: Cell::new(true),
         }
     }
 }
@@ -0,0 +1,73 @@
+use crate::program::mesh::MeshError;
+use crate::shader::ShaderError;
+use crate::uniform::UniformError;
+use gl::types::GLenum;
+
+// Error type for Shadow Maps
+#[derive(Debug)]
+pub enum ShadowError {
+    LightDoesNotExist(usize),
+    FramebufferIncomplete(GLenum),
+    DepthShader(ShaderError),
+    Uniform(UniformError),
+    Mesh(MeshError),
+}
+
+impl std::error::Error for ShadowError {}
+impl std::fmt::Display for ShadowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ShadowError::LightDoesNotExist(index) => {
+                write!(f, "No light exists at index {} in this program.", index)
+            }
+            ShadowError::FramebufferIncomplete(status) => {
+                write!(
+                    f,
+                    "The shadow map framebuffer is incomplete (status: {:#x}).",
+                    status
+                )
+            }
+            ShadowError::DepthShader(error) => {
+                write!(f, "Could not create the shadow depth shader.\n{}", error)
+            }
+            ShadowError::Uniform(error) => {
+                write!(f, "Could not set a shadow depth uniform.\n{}", error)
+            }
+            ShadowError::Mesh(error) => {
+                write!(f, "Could not draw a mesh into the shadow map.\n{}", error)
+            }
+        }
+    }
+}
+
+impl From<ShaderError> for ShadowError {
+    fn from(error: ShaderError) -> Self {
+        ShadowError::DepthShader(error)
+    }
+}
+
+impl From<UniformError> for ShadowError {
+    fn from(error: UniformError) -> Self {
+        ShadowError::Uniform(error)
+    }
+}
+
+impl From<MeshError> for ShadowError {
+    fn from(error: MeshError) -> Self {
+        ShadowError::Mesh(error)
+    }
+}
+
+// Allows for painless casting into our crate's rollup error
+impl From<ShadowError> for crate::GLError {
+    fn from(error: ShadowError) -> Self {
+        crate::GLError::Shadow(error)
+    }
+}
+
+// Allows for painless casting into our crate's rollup error
+impl From<ShadowError> for crate::program::ProgramError {
+    fn from(error: ShadowError) -> Self {
+        crate::program::ProgramError::Shadow(error)
+    }
+}
@@ -0,0 +1,440 @@
+// Error Types
+pub mod error;
+pub use error::ShadowError;
+type Result<T> = std::result::Result<T, ShadowError>;
+
+// Lights cast the shadows, meshes are drawn into the shadow maps
+use super::lights::{LightKind, LightSource};
+use super::mesh::{Attached, Mesh};
+use crate::shader::{FragmentShader, Shader, ShaderPipeline, VertexShader};
+use crate::uniform::{Uniform, UpdateUniform};
+use crate::window::debug::DebugGroup;
+use crate::window::object::GLObject;
+
+// OpenGL Types
+use gl::types::*;
+
+// Linear Algebra Types
+use ultraviolet::mat::Mat4;
+use ultraviolet::vec::{Vec3, Vec4};
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+// Texture units the built-in fragment shaders expect the shadow maps to be bound to
+pub const SHADOW_MAP_TEXTURE_UNIT: GLint = 10;
+pub const SHADOW_CUBE_MAP_TEXTURE_UNIT: GLint = 11;
+
+// Width and height (in texels) of every shadow map face
+const DEFAULT_SHADOW_MAP_RESOLUTION: GLsizei = 1024;
+
+const PI: f32 = std::f32::consts::PI;
+
+// Per light settings for how its shadows are rendered and sampled
+#[derive(Debug, Clone, Copy)]
+pub struct ShadowSettings {
+    // Depth offset used to prevent shadow acne, scaled up on surfaces facing away from the light
+    pub bias: f32,
+    // Percentage-Closer Filtering kernel radius in texels (0 = a single hardware filtered sample)
+    pub pcf_radius: u32,
+    // Near and far planes of the light's projection
+    pub near: f32,
+    pub far: f32,
+    // Half-width of the orthographic box used by directional lights
+    pub extent: f32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        ShadowSettings {
+            bias: 0.005,
+            pcf_radius: 1,
+            near: 0.1,
+            far: 1000.0,
+            extent: 50.0,
+        }
+    }
+}
+
+// A light that has shadows enabled, and where its depth is rendered to. Where it's rendered from
+// is worked out from the light every frame, so the shadows follow it when it moves.
+#[derive(Debug)]
+struct ShadowCaster {
+    // Index into the program's lights
+    light: usize,
+    settings: ShadowSettings,
+    // Point lights render into a layer of the cube map array, everything else into the 2D array
+    cube: bool,
+    // Layer of the 2D array, or index of the cube in the cube map array
+    layer: GLint,
+}
+
+// Owns the depth-only program, framebuffer and textures used to render every shadow map
+pub(crate) struct ShadowMaps {
+    program: GLObject,
+    #[allow(dead_code)]
+    shaders: ShaderPipeline<'static>,
+    light_view_projection: Rc<dyn UpdateUniform>,
+    light_position: Rc<dyn UpdateUniform>,
+    framebuffer: GLObject,
+    // GL_TEXTURE_2D_ARRAY for directional and spot lights
+    maps: GLObject,
+    // GL_TEXTURE_CUBE_MAP_ARRAY for point lights
+    cube_maps: GLObject,
+    resolution: GLsizei,
+    casters: Vec<ShadowCaster>,
+}
+
+impl ShadowMaps {
+    pub(crate) fn new() -> Result<Self> {
+        // Depth-only program
+        let program = GLObject::program();
+        let program_id = program.id();
+        let vs = Shader::<VertexShader>::shadow_depth()?;
+        let fs = Shader::<FragmentShader>::shadow_depth()?;
+        let shaders = ShaderPipeline::new(program_id, vs, fs, None, None, None)?;
+        let light_view_projection =
+            Uniform::new("light_view_projection", &Mat4::identity())?.attach(program_id)?;
+        let light_position = Uniform::new("light_position", &Vec4::zero())?.attach(program_id)?;
+
+        let framebuffer = GLObject::framebuffer();
+        let maps = GLObject::texture();
+        let cube_maps = GLObject::texture();
+        unsafe {
+            // Depth only, there is no color attachment to draw or read from
+            gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer.id());
+            gl::DrawBuffer(gl::NONE);
+            gl::ReadBuffer(gl::NONE);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+
+            // 2D maps use hardware depth comparison, which also gives us a free 2x2 PCF tap
+            let border = [1.0f32, 1.0, 1.0, 1.0];
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, maps.id());
+            set_texture_parameters(gl::TEXTURE_2D_ARRAY, gl::LINEAR, gl::CLAMP_TO_BORDER);
+            gl::TexParameterfv(
+                gl::TEXTURE_2D_ARRAY,
+                gl::TEXTURE_BORDER_COLOR,
+                border.as_ptr(),
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_2D_ARRAY,
+                gl::TEXTURE_COMPARE_MODE,
+                gl::COMPARE_REF_TO_TEXTURE as GLint,
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_2D_ARRAY,
+                gl::TEXTURE_COMPARE_FUNC,
+                gl::LEQUAL as GLint,
+            );
+
+            // Cube maps store the normalized distance to the light and are compared in the shader
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP_ARRAY, cube_maps.id());
+            set_texture_parameters(gl::TEXTURE_CUBE_MAP_ARRAY, gl::NEAREST, gl::CLAMP_TO_EDGE);
+        }
+        program.label("shadow depth");
+        framebuffer.label("shadow maps");
+        maps.label("shadow_maps");
+        cube_maps.label("shadow_cube_maps");
+
+        let mut shadow_maps = ShadowMaps {
+            program,
+            shaders,
+            light_view_projection,
+            light_position,
+            framebuffer,
+            maps,
+            cube_maps,
+            resolution: DEFAULT_SHADOW_MAP_RESOLUTION,
+            casters: Vec::new(),
+        };
+        shadow_maps.allocate(&mut []);
+        Ok(shadow_maps)
+    }
+
+    // Turns on (or updates the settings of) shadows for the light at index `light`
+    pub(crate) fn enable(
+        &mut self,
+        lights: &mut [LightSource],
+        light: usize,
+        settings: ShadowSettings,
+    ) -> Result<()> {
+        let source = lights
+            .get(light)
+            .ok_or(ShadowError::LightDoesNotExist(light))?;
+        let caster = ShadowCaster {
+            light,
+            settings,
+            cube: source.kind() == LightKind::Point,
+            layer: 0,
+        };
+
+        match self.casters.iter_mut().find(|caster| caster.light == light) {
+            Some(existing) => *existing = caster,
+            None => self.casters.push(caster),
+        }
+        self.allocate(lights);
+        Ok(())
+    }
+
+    // Turns off shadows for the light at index `light`
+    pub(crate) fn disable(&mut self, lights: &mut [LightSource], light: usize) -> Result<()> {
+        let source = lights
+            .get_mut(light)
+            .ok_or(ShadowError::LightDoesNotExist(light))?;
+        source.shadow = Vec4::new(-1.0, 0.0, 0.0, 0.0);
+        self.casters.retain(|caster| caster.light != light);
+        self.allocate(lights);
+        Ok(())
+    }
+
+    // Forgets the light at index `light`, which was just removed from `lights`, and moves the
+    // casters of the lights after it down to their new indices
+    pub(crate) fn remove_light(&mut self, lights: &mut [LightSource], light: usize) {
+        self.casters.retain(|caster| caster.light != light);
+        for caster in self.casters.iter_mut() {
+            if caster.light > light {
+                caster.light -= 1;
+            }
+        }
+        self.allocate(lights);
+    }
+
+    // Changes the size of every shadow map face
+    pub(crate) fn set_resolution(&mut self, lights: &mut [LightSource], resolution: GLsizei) {
+        self.resolution = resolution.max(1);
+        self.allocate(lights);
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.casters.is_empty()
+    }
+
+    // Packs the casters into consecutive layers, writes where to find them into the lights, and
+    // resizes the textures to fit. Call whenever a light is added, replaced or removed.
+    pub(crate) fn allocate(&mut self, lights: &mut [LightSource]) {
+        // The light may have been removed since its shadows were enabled
+        self.casters.retain(|caster| caster.light < lights.len());
+        let mut maps = 0;
+        let mut cubes = 0;
+        for caster in self.casters.iter_mut() {
+            let Some(light) = lights.get_mut(caster.light) else {
+                continue;
+            };
+            // Replacing the light may have changed its kind
+            caster.cube = light.kind() == LightKind::Point;
+            if caster.cube {
+                caster.layer = cubes;
+                cubes += 1;
+            } else {
+                caster.layer = maps;
+                maps += 1;
+            }
+            caster.write_shadow(light);
+        }
+
+        // Textures can't have zero layers, so always keep at least one around
+        let resolution = self.resolution;
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, self.maps.id());
+            gl::TexImage3D(
+                gl::TEXTURE_2D_ARRAY,
+                0,
+                gl::DEPTH_COMPONENT32F as GLint,
+                resolution,
+                resolution,
+                maps.max(1),
+                0,
+                gl::DEPTH_COMPONENT,
+                gl::FLOAT,
+                std::ptr::null(),
+            );
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP_ARRAY, self.cube_maps.id());
+            gl::TexImage3D(
+                gl::TEXTURE_CUBE_MAP_ARRAY,
+                0,
+                gl::DEPTH_COMPONENT32F as GLint,
+                resolution,
+                resolution,
+                6 * cubes.max(1),
+                0,
+                gl::DEPTH_COMPONENT,
+                gl::FLOAT,
+                std::ptr::null(),
+            );
+        }
+    }
+
+    // Rewrites the lights' shadow matrices from where they are now. True if any changed, and the
+    // lights need buffering again.
+    pub(crate) fn update(&self, lights: &mut [LightSource]) -> bool {
+        let mut changed = false;
+        for caster in self.casters.iter() {
+            if let Some(light) = lights.get_mut(caster.light) {
+                changed |= caster.write_shadow(light);
+            }
+        }
+        changed
+    }
+
+    // Depth pass: renders every shadow casting object into every shadow map. Leaves the depth
+    // program bound, and the viewport sized to the shadow maps; the caller restores both.
+    pub(crate) fn render(
+        &self,
+        lights: &[LightSource],
+        meshes: &HashMap<String, Mesh<Attached>>,
+    ) -> Result<()> {
+        let _group = DebugGroup::push("shadow maps");
+        // Buffer the casting objects' transforms once, and reuse them for every face
+        let mut instances = Vec::with_capacity(meshes.len());
+        for mesh in meshes.values() {
+            instances.push((mesh, mesh.buffer_shadow_casters()?));
+        }
+
+        unsafe {
+            gl::UseProgram(self.program.id());
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer.id());
+            gl::Viewport(0, 0, self.resolution, self.resolution);
+        }
+
+        for caster in self.casters.iter() {
+            let Some(light) = lights.get(caster.light) else {
+                continue;
+            };
+            self.light_position.update(&caster.light_position(light))?;
+            let view_projections = light_view_projections(light, &caster.settings);
+            for (face, view_projection) in view_projections.iter().enumerate() {
+                let (texture, layer) = match caster.cube {
+                    true => (self.cube_maps.id(), caster.layer * 6 + face as GLint),
+                    false => (self.maps.id(), caster.layer),
+                };
+                unsafe {
+                    gl::FramebufferTextureLayer(
+                        gl::FRAMEBUFFER,
+                        gl::DEPTH_ATTACHMENT,
+                        texture,
+                        0,
+                        layer,
+                    );
+                    let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+                    if status != gl::FRAMEBUFFER_COMPLETE {
+                        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+                        return Err(ShadowError::FramebufferIncomplete(status));
+                    }
+                    gl::Clear(gl::DEPTH_BUFFER_BIT);
+                }
+
+                self.light_view_projection.update(view_projection)?;
+                for (mesh, num_instances) in instances.iter() {
+                    mesh.draw_instances(*num_instances);
+                }
+            }
+        }
+
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+        }
+        Ok(())
+    }
+
+    // Binds the shadow maps to the texture units the built-in fragment shaders sample from
+    pub(crate) fn bind(&self) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + SHADOW_MAP_TEXTURE_UNIT as GLuint);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, self.maps.id());
+            gl::ActiveTexture(gl::TEXTURE0 + SHADOW_CUBE_MAP_TEXTURE_UNIT as GLuint);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP_ARRAY, self.cube_maps.id());
+            gl::ActiveTexture(gl::TEXTURE0);
+        }
+    }
+}
+
+impl ShadowCaster {
+    // xyz = light position, w = far plane for cube maps (0.0 for 2D maps)
+    fn light_position(&self, light: &LightSource) -> Vec4 {
+        let far = if self.cube { self.settings.far } else { 0.0 };
+        Vec4::new(light.position.x, light.position.y, light.position.z, far)
+    }
+
+    // Tells the light where its shadow map is, and how to sample it. True if anything changed.
+    fn write_shadow(&self, light: &mut LightSource) -> bool {
+        let settings = self.settings;
+        let shadow = Vec4::new(
+            self.layer as f32,
+            settings.bias,
+            settings.pcf_radius as f32,
+            self.light_position(light).w,
+        );
+        let shadow_matrix = light_view_projections(light, &settings)[0];
+        let changed = light.shadow != shadow || light.shadow_matrix != shadow_matrix;
+        light.shadow = shadow;
+        light.shadow_matrix = shadow_matrix;
+        changed
+    }
+}
+
+// Filtering and wrapping shared by both shadow map textures
+unsafe fn set_texture_parameters(target: GLenum, filter: GLenum, wrap: GLenum) {
+    gl::TexParameteri(target, gl::TEXTURE_MIN_FILTER, filter as GLint);
+    gl::TexParameteri(target, gl::TEXTURE_MAG_FILTER, filter as GLint);
+    gl::TexParameteri(target, gl::TEXTURE_WRAP_S, wrap as GLint);
+    gl::TexParameteri(target, gl::TEXTURE_WRAP_T, wrap as GLint);
+    gl::TexParameteri(target, gl::TEXTURE_WRAP_R, wrap as GLint);
+}
+
+// Computes the World-Space -> Light-Space transforms a light renders its shadow map(s) with
+fn light_view_projections(light: &LightSource, settings: &ShadowSettings) -> Vec<Mat4> {
+    let position = light.position.truncated();
+    let direction = light.direction.truncated();
+    match light.kind() {
+        LightKind::Directional => {
+            // Pull the light back along its direction so the box is centered on the origin
+            let eye = -direction * (settings.far / 2.0);
+            let view = Mat4::look_at(eye, Vec3::zero(), up_vector(direction));
+            let extent = settings.extent;
+            let projection = ultraviolet::projection::orthographic_gl(
+                -extent,
+                extent,
+                -extent,
+                extent,
+                settings.near,
+                settings.far,
+            );
+            vec![projection * view]
+        }
+        LightKind::Spot => {
+            let view = Mat4::look_at(position, position + direction, up_vector(direction));
+            let fov = (light.cutoff() * 2.0).min(PI - 0.01);
+            let projection =
+                ultraviolet::projection::perspective_gl(fov, 1.0, settings.near, settings.far);
+            vec![projection * view]
+        }
+        LightKind::Point => cube_face_view_projections(position, settings.near, settings.far),
+    }
+}
+
+// Transforms that render each of the six faces of a cube map centered on `position`
+pub(crate) fn cube_face_view_projections(position: Vec3, near: f32, far: f32) -> Vec<Mat4> {
+    // Face order, and up vectors, follow the OpenGL cube map conventions
+    let projection = ultraviolet::projection::perspective_gl(PI / 2.0, 1.0, near, far);
+    let faces = [
+        (Vec3::unit_x(), -Vec3::unit_y()),
+        (-Vec3::unit_x(), -Vec3::unit_y()),
+        (Vec3::unit_y(), Vec3::unit_z()),
+        (-Vec3::unit_y(), -Vec3::unit_z()),
+        (Vec3::unit_z(), -Vec3::unit_y()),
+        (-Vec3::unit_z(), -Vec3::unit_y()),
+    ];
+    faces
+        .iter()
+        .map(|(target, up)| projection * Mat4::look_at(position, position + *target, *up))
+        .collect()
+}
+
+// Any up vector works as long as it isn't parallel to the direction the light is looking
+fn up_vector(direction: Vec3) -> Vec3 {
+    if direction.y.abs() > 0.99 {
+        Vec3::unit_z()
+    } else {
+        Vec3::unit_y()
+    }
+}
@@ -0,0 +1,79 @@
+// Draws a cube map behind the scene
+use crate::shader::{FragmentShader, Shader, VertexShader};
+use crate::texture::{Pass, Texture, TextureError, Triangles};
+use crate::uniform::UpdateUniform;
+use crate::window::debug::DebugGroup;
+
+// OpenGL Types
+use gl::types::*;
+
+// Linear Algebra Types
+use ultraviolet::mat::Mat4;
+
+use std::rc::Rc;
+
+// Texture unit the skybox is bound to while drawn, and that the built-in fragment shaders sample
+// environment reflections from
+pub const SKYBOX_TEXTURE_UNIT: GLint = 7;
+
+pub(crate) struct Skybox {
+    pass: Pass,
+    cube: Triangles,
+    pub(crate) cube_map: Rc<Texture>,
+    view_projection: Rc<dyn UpdateUniform>,
+    tone_map: Rc<dyn UpdateUniform>,
+}
+
+impl Skybox {
+    pub(crate) fn new(cube_map: Rc<Texture>) -> Result<Self, TextureError> {
+        let pass = Pass::new(
+            Shader::<VertexShader>::skybox()?,
+            Shader::<FragmentShader>::skybox()?,
+        )?;
+        pass.label("skybox");
+        pass.uniform("skybox", &SKYBOX_TEXTURE_UNIT)?;
+        let tone_map = pass.uniform("tone_map", &0u32)?;
+        let view_projection = pass.uniform("skybox_view_projection", &Mat4::identity())?;
+        Ok(Skybox {
+            pass,
+            cube: Triangles::unit_cube(),
+            cube_map,
+            view_projection,
+            tone_map,
+        })
+    }
+
+    // Draws after the opaque geometry, so only the fragments nothing else covered are shaded.
+    // Leaves the skybox program bound; the caller restores its own. `tone_map` should match
+    // whether the program it is drawn behind tone maps its own output.
+    pub(crate) fn draw(
+        &self,
+        view: &Mat4,
+        projection: &Mat4,
+        tone_map: bool,
+    ) -> Result<(), TextureError> {
+        let _group = DebugGroup::push("skybox");
+        // Drop the translation so the sky is infinitely far away
+        let rotation = view.truncate().into_homogeneous();
+        unsafe {
+            gl::UseProgram(self.pass.id());
+        }
+        self.view_projection.update(&(*projection * rotation))?;
+        self.tone_map.update(&(tone_map as GLuint))?;
+        self.cube_map.bind(SKYBOX_TEXTURE_UNIT as GLuint);
+        unsafe {
+            // The sky is drawn at a depth of exactly 1.0, which the cleared depth buffer equals
+            let cull_face = gl::IsEnabled(gl::CULL_FACE) == gl::TRUE;
+            gl::Disable(gl::CULL_FACE);
+            gl::DepthFunc(gl::LEQUAL);
+            gl::DepthMask(gl::FALSE);
+            self.cube.draw();
+            gl::DepthMask(gl::TRUE);
+            gl::DepthFunc(gl::LESS);
+            if cull_face {
+                gl::Enable(gl::CULL_FACE);
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,5 +1,6 @@
 use crate::program::vao::VAOError;
 use crate::types::*;
+use crate::window::object::GLObject;
 use gl::types::*;
 use std::ptr;
 use ultraviolet::vec::*;
@@ -12,6 +13,8 @@ pub struct Attribute {
     pub location: GLuint,
     // ID of the backing OpenGL buffer of the attribute
     pub buffer_id: GLuint,
+    // Deletes the buffer once every clone is dropped
+    buffer: std::rc::Rc<GLObject>,
 }
 
 // Data types that implement this trait define how to set their OpenGL
@@ -22,7 +25,6 @@ impl Attribute {
         S: AsRef<str>,
     {
         let location;
-        let buffer_id;
         unsafe {
             // Get the handle to where the attribute is located in the shader
             let name = std::ffi::CString::new(name.as_ref().as_bytes())
@@ -35,26 +37,45 @@ impl Attribute {
                 ));
             }
 
-            // Generate a buffer that we can write to
-            let mut vbo = 0;
-            gl::GenBuffers(1, &mut vbo);
-            buffer_id = vbo;
-
             // Convert to GLuint from GLint
             location = attribute_location
                 .try_into()
                 .map_err(|_| VAOError::FailedIDConversion)?;
         }
+        // Generate a buffer that we can write to
+        let buffer = GLObject::buffer();
         Ok(Attribute {
             location,
-            buffer_id,
+            buffer_id: buffer.id(),
+            buffer: std::rc::Rc::new(buffer),
         })
     }
+
+    // Names the attribute's buffer, once something has been buffered to it
+    pub(crate) fn label(&self, name: &str) {
+        self.buffer.label(name);
+    }
 }
 pub trait SetAttributePointer {
     fn set_attribute_pointer(&self, id: GLuint) -> Result<()>;
 }
 
+impl SetAttributePointer for Vec<f32> {
+    fn set_attribute_pointer(&self, id: GLuint) -> Result<()> {
+        // Pointer to the vector's buffer
+        let ptr = self.as_ptr() as *const std::ffi::c_void;
+        let size = (self.len() * std::mem::size_of::<f32>()) as GLsizeiptr;
+        let element_size = (std::mem::size_of::<f32>()) as GLsizei;
+        unsafe {
+            // Send the data to the GPU
+            gl::BufferData(gl::ARRAY_BUFFER, size, ptr, gl::STATIC_DRAW);
+            // Tell OpenGL how to pull data from the buffer into the attributes inside the shaders
+            gl::VertexAttribPointer(id, 1, gl::FLOAT, gl::FALSE, element_size, ptr::null());
+        }
+        Ok(())
+    }
+}
+
 impl SetAttributePointer for Vec<Vec2> {
     fn set_attribute_pointer(&self, id: GLuint) -> Result<()> {
         // Pointer to the vector's buffer
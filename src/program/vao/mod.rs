@@ -10,10 +10,12 @@ use attribute::SetAttributePointer;
 type Result<T> = std::result::Result<T, VAOError>;
 
 // OpenGL Types
+use crate::window::object::GLObject;
 use gl::types::*;
 
 // Used to map Attributes to their identifiers in shader GLSL code
 use std::collections::hash_map::Entry;
+use std::rc::Rc;
 use std::{collections::HashMap, vec::Vec};
 // Used for casting into the OpenGL library
 use std::ffi::c_void;
@@ -25,6 +27,8 @@ pub struct ElementIndices {
     pub buffer_id: GLuint,
     // The number of elements to render (essentially `ele_buffer.len()`)
     pub buffer_length: GLint,
+    // Deletes the buffer once every clone is dropped
+    buffer: Rc<GLObject>,
 }
 
 // Represents an OpenGL Vertex Array Object - provides a handle to the VAO
@@ -40,6 +44,8 @@ pub struct VAO {
     pub elements: ElementIndices,
     // List of named attributes and their OpenGL locations, and buffer IDs
     pub attributes: HashMap<String, Attribute>,
+    // Deletes the VAO once every clone is dropped
+    object: Rc<GLObject>,
 }
 
 impl VAO {
@@ -47,34 +53,25 @@ impl VAO {
     // list of attributes to be be associated with this VAO, and connected to the element array
     pub fn new(program_id: GLuint, indices: &Vec<u32>) -> Result<Self> {
         // Create our new VAO
-        let id = Self::get_id();
+        let object = Rc::new(GLObject::vertex_array());
         let elements = Self::create_element_array(indices);
         let vao = VAO {
-            id,
+            id: object.id(),
             elements,
             program_id,
             attributes: HashMap::new(),
+            object,
         };
 
         Ok(vao)
     }
 
-    // Convenience
-    #[inline(always)]
-    fn get_id() -> GLuint {
-        let mut id = 0;
-        unsafe {
-            gl::GenVertexArrays(1, &mut id);
-        }
-        id
-    }
-
     fn create_element_array(buffer: &Vec<u32>) -> ElementIndices {
         let ele_buffer_ptr = buffer.as_ptr() as *const c_void;
         let ele_buffer_size = (buffer.len() * size_of::<u32>()) as isize;
-        let mut buffer_id = 0;
+        let object = GLObject::buffer();
+        let buffer_id = object.id();
         unsafe {
-            gl::GenBuffers(1, &mut buffer_id);
             gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, buffer_id);
             gl::BufferData(
                 gl::ELEMENT_ARRAY_BUFFER,
@@ -87,6 +84,7 @@ impl VAO {
         ElementIndices {
             buffer_id,
             buffer_length,
+            buffer: Rc::new(object),
         }
     }
 
@@ -111,6 +109,10 @@ impl VAO {
 
             // Sets up how to pull from the buffer, and how many times to pull from the buffer
             buffer.set_attribute_pointer(attribute.location)?;
+            // Instanced attributes advance once per instance instead of once per vertex
+            if instanced {
+                gl::VertexAttribDivisor(attribute.location, 1);
+            }
 
             // Unbind Targets
             gl::BindBuffer(gl::ARRAY_BUFFER, 0);
@@ -120,6 +122,28 @@ impl VAO {
         Ok(self)
     }
 
+    // Names the VAO and its buffers after `name` (e.g. the mesh's), in debug messages and graphics
+    // debuggers
+    pub fn label<S>(&self, name: S)
+    where
+        S: AsRef<str>,
+    {
+        let name = name.as_ref();
+        self.object.label(name);
+        self.elements.buffer.label(&format!("{} indices", name));
+        for (attribute_name, attribute) in self.attributes.iter() {
+            attribute.label(&format!("{} {}", name, attribute_name));
+        }
+    }
+
+    // True if the attribute was added to this VAO
+    pub fn has_attribute<S>(&self, name: S) -> bool
+    where
+        S: AsRef<str>,
+    {
+        self.attributes.contains_key(name.as_ref())
+    }
+
     // Attaches a buffer to a named attribute location in the shader code, and informs
     // OpenGL how to pull from it.
     pub fn update_attribute<S, B>(&self, name: S, buffer: &B, instanced: bool) -> Result<()>
@@ -140,6 +164,10 @@ impl VAO {
 
             // Sets up how to pull from the buffer, and how many times to pull from the buffer
             buffer.set_attribute_pointer(attribute.location)?;
+            // Instanced attributes advance once per instance instead of once per vertex
+            if instanced {
+                gl::VertexAttribDivisor(attribute.location, 1);
+            }
 
             // Unbind Targets
             gl::BindBuffer(gl::ARRAY_BUFFER, 0);
@@ -0,0 +1,92 @@
+use super::framebuffer::AttachmentFormat;
+use crate::shader::ShaderError;
+use crate::texture::TextureError;
+use crate::uniform::UniformError;
+use gl::types::GLenum;
+
+// Error type for Render Graphs
+#[derive(Debug)]
+pub enum RenderGraphError {
+    TargetDoesNotExist(String),
+    AttachmentDoesNotExist(String),
+    InvalidAttachment(AttachmentFormat),
+    FramebufferIncomplete(String, GLenum),
+    Shader(String, ShaderError),
+    Texture(TextureError),
+    Uniform(UniformError),
+}
+
+impl std::error::Error for RenderGraphError {}
+impl std::fmt::Display for RenderGraphError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RenderGraphError::TargetDoesNotExist(name) => {
+                write!(
+                    f,
+                    "No render target with name: \"{}\" has been added to the render graph.",
+                    name
+                )
+            }
+            RenderGraphError::AttachmentDoesNotExist(name) => {
+                write!(
+                    f,
+                    "The render target: \"{}\" does not have the attachment a pass samples from.",
+                    name
+                )
+            }
+            RenderGraphError::InvalidAttachment(format) => {
+                write!(
+                    f,
+                    "{:?} can't be used for that attachment. Colour attachments need a colour format, and depth attachments a depth format.",
+                    format
+                )
+            }
+            RenderGraphError::FramebufferIncomplete(name, status) => {
+                write!(
+                    f,
+                    "The framebuffer of render target: \"{}\" is incomplete (status: {:#x}).",
+                    name, status
+                )
+            }
+            RenderGraphError::Shader(name, error) => {
+                write!(
+                    f,
+                    "Could not create the shaders of render pass: \"{}\".\n{}",
+                    name, error
+                )
+            }
+            RenderGraphError::Texture(error) => {
+                write!(f, "Could not create a render pass.\n{}", error)
+            }
+            RenderGraphError::Uniform(error) => {
+                write!(f, "Could not set a uniform of a render pass.\n{}", error)
+            }
+        }
+    }
+}
+
+impl From<TextureError> for RenderGraphError {
+    fn from(error: TextureError) -> Self {
+        RenderGraphError::Texture(error)
+    }
+}
+
+impl From<UniformError> for RenderGraphError {
+    fn from(error: UniformError) -> Self {
+        RenderGraphError::Uniform(error)
+    }
+}
+
+// Allows for painless casting into our crate's rollup error
+impl From<RenderGraphError> for crate::GLError {
+    fn from(error: RenderGraphError) -> Self {
+        crate::GLError::RenderGraph(error)
+    }
+}
+
+// Allows for painless casting into our crate's rollup error
+impl From<RenderGraphError> for crate::program::ProgramError {
+    fn from(error: RenderGraphError) -> Self {
+        crate::program::ProgramError::RenderGraph(error)
+    }
+}
@@ -0,0 +1,284 @@
+// Off-screen render targets: Framebuffer Objects with colour, and optionally depth, attachments
+use super::error::RenderGraphError;
+use crate::texture::Texture;
+use crate::window::object::GLObject;
+
+// OpenGL Types
+use gl::types::*;
+
+// Error Types
+type Result<T> = std::result::Result<T, RenderGraphError>;
+
+// Sized internal formats an attachment can be created with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttachmentFormat {
+    // Low dynamic range colour
+    Rgba8,
+    Srgb8Alpha8,
+    // High dynamic range (floating point) colour
+    Rgba16F,
+    Rgba32F,
+    R11FG11FB10F,
+    // Depth
+    Depth24,
+    Depth32F,
+    Depth24Stencil8,
+}
+
+impl AttachmentFormat {
+    pub fn is_depth(&self) -> bool {
+        matches!(
+            self,
+            AttachmentFormat::Depth24
+                | AttachmentFormat::Depth32F
+                | AttachmentFormat::Depth24Stencil8
+        )
+    }
+
+    pub(crate) fn internal_format(&self) -> GLenum {
+        match self {
+            AttachmentFormat::Rgba8 => gl::RGBA8,
+            AttachmentFormat::Srgb8Alpha8 => gl::SRGB8_ALPHA8,
+            AttachmentFormat::Rgba16F => gl::RGBA16F,
+            AttachmentFormat::Rgba32F => gl::RGBA32F,
+            AttachmentFormat::R11FG11FB10F => gl::R11F_G11F_B10F,
+            AttachmentFormat::Depth24 => gl::DEPTH_COMPONENT24,
+            AttachmentFormat::Depth32F => gl::DEPTH_COMPONENT32F,
+            AttachmentFormat::Depth24Stencil8 => gl::DEPTH24_STENCIL8,
+        }
+    }
+
+    // Where a depth format is attached to the framebuffer
+    fn depth_attachment(&self) -> GLenum {
+        match self {
+            AttachmentFormat::Depth24Stencil8 => gl::DEPTH_STENCIL_ATTACHMENT,
+            _ => gl::DEPTH_ATTACHMENT,
+        }
+    }
+}
+
+// What a render target is made of, and how large it is relative to the window
+#[derive(Debug, Clone, PartialEq)]
+pub struct TargetDescription {
+    pub color: Vec<AttachmentFormat>,
+    pub depth: Option<AttachmentFormat>,
+    // e.g. 0.5 for a half resolution target
+    pub scale: f32,
+}
+
+impl TargetDescription {
+    // No attachments, at the resolution of the window
+    pub fn new() -> Self {
+        TargetDescription {
+            color: Vec::new(),
+            depth: None,
+            scale: 1.0,
+        }
+    }
+
+    // A floating point colour attachment with a depth buffer, what the scene is rendered into
+    pub fn hdr() -> Self {
+        TargetDescription::new()
+            .color(AttachmentFormat::Rgba16F)
+            .depth(AttachmentFormat::Depth24)
+    }
+
+    // Adds a colour attachment, sampled by later passes in the order they were added
+    pub fn color(mut self, format: AttachmentFormat) -> Self {
+        self.color.push(format);
+        self
+    }
+
+    pub fn depth(mut self, format: AttachmentFormat) -> Self {
+        self.depth = Some(format);
+        self
+    }
+
+    pub fn scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    // Checks every attachment is of the right kind
+    pub(crate) fn validate(&self) -> Result<()> {
+        if let Some(format) = self.color.iter().find(|format| format.is_depth()) {
+            return Err(RenderGraphError::InvalidAttachment(*format));
+        }
+        match self.depth {
+            Some(format) if !format.is_depth() => Err(RenderGraphError::InvalidAttachment(format)),
+            _ => Ok(()),
+        }
+    }
+
+    // Size of the target for a window of the given size
+    pub(crate) fn size(&self, width: GLsizei, height: GLsizei) -> (GLsizei, GLsizei) {
+        let scale = |length: GLsizei| ((length as f32 * self.scale) as GLsizei).max(1);
+        (scale(width), scale(height))
+    }
+}
+
+impl Default for TargetDescription {
+    fn default() -> Self {
+        TargetDescription::new()
+    }
+}
+
+// A Framebuffer Object, and the textures attached to it
+#[derive(Debug)]
+pub struct Framebuffer {
+    // Deleted when dropped, as are the attached textures
+    object: GLObject,
+    width: GLsizei,
+    height: GLsizei,
+    color: Vec<Texture>,
+    depth: Option<Texture>,
+}
+
+impl Framebuffer {
+    pub(crate) fn new(
+        name: &str,
+        description: &TargetDescription,
+        width: GLsizei,
+        height: GLsizei,
+    ) -> Result<Self> {
+        // Dropped (and deleted) if the framebuffer turns out to be incomplete
+        let mut framebuffer = Framebuffer {
+            object: GLObject::framebuffer(),
+            width,
+            height,
+            color: Vec::new(),
+            depth: None,
+        };
+
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer.id());
+            for (index, format) in description.color.iter().enumerate() {
+                let texture = Texture::empty_2d(width, height, format.internal_format());
+                gl::FramebufferTexture2D(
+                    gl::FRAMEBUFFER,
+                    gl::COLOR_ATTACHMENT0 + index as GLenum,
+                    gl::TEXTURE_2D,
+                    texture.id(),
+                    0,
+                );
+                framebuffer.color.push(texture);
+            }
+            if let Some(format) = description.depth {
+                let texture = Texture::empty_2d(width, height, format.internal_format());
+                gl::FramebufferTexture2D(
+                    gl::FRAMEBUFFER,
+                    format.depth_attachment(),
+                    gl::TEXTURE_2D,
+                    texture.id(),
+                    0,
+                );
+                framebuffer.depth = Some(texture);
+            }
+            framebuffer.set_draw_buffers();
+
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            framebuffer.object.label(name);
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                return Err(RenderGraphError::FramebufferIncomplete(
+                    name.to_string(),
+                    status,
+                ));
+            }
+        }
+        Ok(framebuffer)
+    }
+
+    // Renders into this framebuffer, over its whole area
+    pub(crate) fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.id());
+            gl::Viewport(0, 0, self.width, self.height);
+        }
+    }
+
+    pub fn color(&self, index: usize) -> Option<&Texture> {
+        self.color.get(index)
+    }
+
+    pub fn depth(&self) -> Option<&Texture> {
+        self.depth.as_ref()
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width as u32
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height as u32
+    }
+
+    pub(crate) fn id(&self) -> GLuint {
+        self.object.id()
+    }
+
+    // Every colour attachment is written to, in order
+    unsafe fn set_draw_buffers(&self) {
+        if self.color.is_empty() {
+            gl::DrawBuffer(gl::NONE);
+            gl::ReadBuffer(gl::NONE);
+        } else {
+            let buffers: Vec<GLenum> = (0..self.color.len())
+                .map(|index| gl::COLOR_ATTACHMENT0 + index as GLenum)
+                .collect();
+            gl::DrawBuffers(buffers.len() as GLsizei, buffers.as_ptr());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn targets_are_scaled_and_never_empty() {
+        assert_eq!(TargetDescription::new().size(1024, 768), (1024, 768));
+        assert_eq!(
+            TargetDescription::new().scale(0.5).size(1024, 768),
+            (512, 384)
+        );
+        assert_eq!(TargetDescription::new().scale(2.0).size(3, 5), (6, 10));
+        // Lengths are rounded down, and targets are at least one pixel across
+        assert_eq!(TargetDescription::new().scale(0.5).size(5, 1), (2, 1));
+        assert_eq!(TargetDescription::new().scale(0.0).size(1024, 768), (1, 1));
+        assert_eq!(TargetDescription::new().size(0, 0), (1, 1));
+    }
+
+    #[test]
+    fn attachments_must_be_of_the_right_kind() {
+        assert!(TargetDescription::new().validate().is_ok());
+        assert!(TargetDescription::hdr().validate().is_ok());
+        let target = TargetDescription::new()
+            .color(AttachmentFormat::Rgba8)
+            .color(AttachmentFormat::Srgb8Alpha8)
+            .depth(AttachmentFormat::Depth24Stencil8);
+        assert!(target.validate().is_ok());
+
+        // A depth format as colour
+        let target = TargetDescription::new()
+            .color(AttachmentFormat::Rgba8)
+            .color(AttachmentFormat::Depth32F);
+        assert!(matches!(
+            target.validate(),
+            Err(RenderGraphError::InvalidAttachment(
+                AttachmentFormat::Depth32F
+            ))
+        ));
+
+        // A colour format as depth
+        let target = TargetDescription::new()
+            .color(AttachmentFormat::Rgba16F)
+            .depth(AttachmentFormat::Rgba32F);
+        assert!(matches!(
+            target.validate(),
+            Err(RenderGraphError::InvalidAttachment(
+                AttachmentFormat::Rgba32F
+            ))
+        ));
+    }
+}
@@ -0,0 +1,357 @@
+// Multi-pass rendering: the scene is rendered into an off-screen target, then a sequence of full
+// screen passes, each sampling the targets earlier passes wrote to, produces the final image
+pub mod error;
+pub use error::RenderGraphError;
+pub mod framebuffer;
+pub use framebuffer::{AttachmentFormat, Framebuffer, TargetDescription};
+pub mod post_processing;
+pub use post_processing::{Bloom, Effect, PostProcessing, ToneMapper};
+
+use crate::program::fragment_only::FragmentOnly;
+use crate::shader::{FragmentShader, Shader, VertexShader};
+use crate::texture::{Pass, TextureError, Triangles};
+use crate::uniform::{UniformError, UniformValue, UpdateUniform};
+use crate::window::debug::DebugGroup;
+
+// OpenGL Types
+use gl::types::*;
+
+// Linear Algebra Types
+use ultraviolet::vec::Vec4;
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+// Error Types
+type Result<T> = std::result::Result<T, RenderGraphError>;
+
+// Name of the target a program renders its scene into. Every graph starts with it, as a
+// `TargetDescription::hdr()`, but it can be replaced with `add_target()`.
+pub const SCENE_TARGET: &str = "scene";
+
+// Which attachment of a target a pass samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Attachment {
+    Color(usize),
+    Depth,
+}
+
+// A pass that shades every pixel of its output with a fragment shader. The shader receives the
+// texture coordinates of the pixel as `in vec2 st`, and may declare:
+//  - a sampler2D for each of its inputs
+//  - `uniform vec4 parameters`, set with `parameters()`
+//  - `uniform vec2 resolution`, the size of its output in pixels
+#[derive(Debug, Clone)]
+pub struct FullScreenPass {
+    name: String,
+    source: Cow<'static, str>,
+    // (Target, Attachment, Sampler Name)
+    inputs: Vec<(String, Attachment, String)>,
+    // None renders to the window
+    output: Option<String>,
+    parameters: Vec4,
+}
+
+impl FullScreenPass {
+    pub fn new<N, S>(name: N, fragment_shader_source: S) -> Self
+    where
+        N: Into<String>,
+        S: Into<Cow<'static, str>>,
+    {
+        FullScreenPass {
+            name: name.into(),
+            source: fragment_shader_source.into(),
+            inputs: Vec::new(),
+            output: None,
+            parameters: Vec4::zero(),
+        }
+    }
+
+    // Samples the first colour attachment of `target` through the sampler named `sampler`
+    pub fn input<T, S>(self, target: T, sampler: S) -> Self
+    where
+        T: Into<String>,
+        S: Into<String>,
+    {
+        self.input_attachment(target, Attachment::Color(0), sampler)
+    }
+
+    pub fn input_attachment<T, S>(mut self, target: T, attachment: Attachment, sampler: S) -> Self
+    where
+        T: Into<String>,
+        S: Into<String>,
+    {
+        self.inputs
+            .push((target.into(), attachment, sampler.into()));
+        self
+    }
+
+    // Renders into `target` instead of the window
+    pub fn output<T>(mut self, target: T) -> Self
+    where
+        T: Into<String>,
+    {
+        self.output = Some(target.into());
+        self
+    }
+
+    pub fn parameters(mut self, parameters: Vec4) -> Self {
+        self.parameters = parameters;
+        self
+    }
+}
+
+// A FullScreenPass, linked and ready to draw
+struct CompiledPass {
+    // What its debug group is called
+    name: String,
+    pass: Pass,
+    inputs: Vec<(String, Attachment)>,
+    output: Option<String>,
+    resolution: Option<Rc<dyn UpdateUniform>>,
+}
+
+pub struct RenderGraph {
+    descriptions: HashMap<String, TargetDescription>,
+    // Created (and re-created when the window is resized) right before they're rendered into
+    targets: HashMap<String, Framebuffer>,
+    passes: Vec<CompiledPass>,
+    triangle: Triangles,
+    // Size of the window the targets were created for
+    size: (GLsizei, GLsizei),
+}
+
+impl RenderGraph {
+    pub fn new() -> Self {
+        let mut descriptions = HashMap::new();
+        descriptions.insert(SCENE_TARGET.to_string(), TargetDescription::hdr());
+        RenderGraph {
+            descriptions,
+            targets: HashMap::new(),
+            passes: Vec::new(),
+            triangle: Triangles::new(&FragmentOnly::TRIANGLE),
+            size: (0, 0),
+        }
+    }
+
+    // Adds a render target passes can read from, and write to (replacing any with the same name)
+    pub fn add_target<S>(&mut self, name: S, description: TargetDescription) -> Result<()>
+    where
+        S: Into<String>,
+    {
+        description.validate()?;
+        let name = name.into();
+        self.targets.remove(&name);
+        self.descriptions.insert(name, description);
+        Ok(())
+    }
+
+    // Appends a pass, which is run after every pass added before it
+    pub fn add_pass(&mut self, pass: FullScreenPass) -> Result<()> {
+        for (target, attachment, _) in pass.inputs.iter() {
+            let description = self.description(target)?;
+            let exists = match attachment {
+                Attachment::Color(index) => *index < description.color.len(),
+                Attachment::Depth => description.depth.is_some(),
+            };
+            if !exists {
+                return Err(RenderGraphError::AttachmentDoesNotExist(target.clone()));
+            }
+        }
+        if let Some(target) = pass.output.as_ref() {
+            self.description(target)?;
+        }
+
+        let shader_error = |error| RenderGraphError::Shader(pass.name.clone(), error);
+        let vertex_shader = Shader::<VertexShader>::full_screen().map_err(shader_error)?;
+        let fragment_shader = Shader::<FragmentShader>::new(&pass.source)
+            .map_err(shader_error)?
+            .into_owned();
+        let compiled = Pass::new(vertex_shader, fragment_shader)?;
+        compiled.label(&pass.name);
+
+        for (unit, (_, _, sampler)) in pass.inputs.iter().enumerate() {
+            compiled.uniform(sampler, &(unit as GLint))?;
+        }
+        optional_uniform(&compiled, "parameters", &pass.parameters)?;
+        let resolution = optional_uniform(&compiled, "resolution", &(1.0f32, 1.0f32))?;
+
+        self.passes.push(CompiledPass {
+            name: pass.name,
+            pass: compiled,
+            inputs: pass
+                .inputs
+                .into_iter()
+                .map(|(target, attachment, _)| (target, attachment))
+                .collect(),
+            output: pass.output,
+            resolution,
+        });
+        Ok(())
+    }
+
+    // A target that has been rendered into at least once
+    pub fn target<S>(&self, name: S) -> Option<&Framebuffer>
+    where
+        S: AsRef<str>,
+    {
+        self.targets.get(name.as_ref())
+    }
+
+    // Binds the scene target, (re-)creating the targets first if the window changed size.
+    // Returns the size of the scene target.
+    pub(crate) fn begin(&mut self, width: GLsizei, height: GLsizei) -> Result<(GLsizei, GLsizei)> {
+        self.resize(width, height)?;
+        let scene = self
+            .targets
+            .get(SCENE_TARGET)
+            .ok_or_else(|| RenderGraphError::TargetDoesNotExist(SCENE_TARGET.to_string()))?;
+        scene.bind();
+        Ok((scene.width() as GLsizei, scene.height() as GLsizei))
+    }
+
+    // Runs every pass in order, leaving the window's framebuffer bound. With no passes, the scene
+    // target is copied to the window as-is.
+    pub(crate) fn execute(&self) -> Result<()> {
+        let (width, height) = self.size;
+        if self.passes.is_empty() {
+            if let Some(scene) = self.targets.get(SCENE_TARGET) {
+                unsafe {
+                    gl::BindFramebuffer(gl::READ_FRAMEBUFFER, scene.id());
+                    gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, 0);
+                    gl::BlitFramebuffer(
+                        0,
+                        0,
+                        scene.width() as GLint,
+                        scene.height() as GLint,
+                        0,
+                        0,
+                        width,
+                        height,
+                        gl::COLOR_BUFFER_BIT,
+                        gl::LINEAR,
+                    );
+                    gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+                    gl::Viewport(0, 0, width, height);
+                }
+            }
+            return Ok(());
+        }
+
+        // Every pixel is overwritten, so depth and blending only get in the way
+        let (depth_test, blend) = unsafe {
+            let state = (
+                gl::IsEnabled(gl::DEPTH_TEST) == gl::TRUE,
+                gl::IsEnabled(gl::BLEND) == gl::TRUE,
+            );
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Disable(gl::BLEND);
+            state
+        };
+
+        let result = self.run_passes();
+
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::Viewport(0, 0, width, height);
+            if depth_test {
+                gl::Enable(gl::DEPTH_TEST);
+            }
+            if blend {
+                gl::Enable(gl::BLEND);
+            }
+        }
+        result
+    }
+
+    fn run_passes(&self) -> Result<()> {
+        for pass in self.passes.iter() {
+            let _group = DebugGroup::push(&pass.name);
+            let (width, height) = match pass.output.as_ref() {
+                Some(name) => {
+                    let target = self.framebuffer(name)?;
+                    target.bind();
+                    (target.width() as GLsizei, target.height() as GLsizei)
+                }
+                None => {
+                    unsafe {
+                        gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+                        gl::Viewport(0, 0, self.size.0, self.size.1);
+                    }
+                    self.size
+                }
+            };
+
+            for (unit, (name, attachment)) in pass.inputs.iter().enumerate() {
+                let target = self.framebuffer(name)?;
+                let texture = match attachment {
+                    Attachment::Color(index) => target.color(*index),
+                    Attachment::Depth => target.depth(),
+                };
+                texture
+                    .ok_or_else(|| RenderGraphError::AttachmentDoesNotExist(name.clone()))?
+                    .bind(unit as GLuint);
+            }
+
+            unsafe {
+                gl::UseProgram(pass.pass.id());
+            }
+            if let Some(resolution) = pass.resolution.as_ref() {
+                resolution.update(&(width as f32, height as f32))?;
+            }
+            self.triangle.draw();
+        }
+        Ok(())
+    }
+
+    // Creates any missing targets, and re-creates them all if the window changed size
+    fn resize(&mut self, width: GLsizei, height: GLsizei) -> Result<()> {
+        if self.size != (width, height) {
+            self.targets.clear();
+            self.size = (width, height);
+        }
+        for (name, description) in self.descriptions.iter() {
+            if !self.targets.contains_key(name) {
+                let (target_width, target_height) = description.size(width, height);
+                let framebuffer = Framebuffer::new(name, description, target_width, target_height)?;
+                self.targets.insert(name.clone(), framebuffer);
+            }
+        }
+        Ok(())
+    }
+
+    fn description(&self, name: &str) -> Result<&TargetDescription> {
+        self.descriptions
+            .get(name)
+            .ok_or_else(|| RenderGraphError::TargetDoesNotExist(name.to_string()))
+    }
+
+    fn framebuffer(&self, name: &str) -> Result<&Framebuffer> {
+        self.targets
+            .get(name)
+            .ok_or_else(|| RenderGraphError::TargetDoesNotExist(name.to_string()))
+    }
+}
+
+impl Default for RenderGraph {
+    fn default() -> Self {
+        RenderGraph::new()
+    }
+}
+
+// Sets a uniform the shader may have left out, e.g. `resolution`
+fn optional_uniform<Value>(
+    pass: &Pass,
+    name: &str,
+    value: &Value,
+) -> Result<Option<Rc<dyn UpdateUniform>>>
+where
+    Value: UniformValue + 'static,
+{
+    match pass.uniform(name, value) {
+        Ok(uniform) => Ok(Some(uniform)),
+        Err(TextureError::Uniform(UniformError::CouldNotFindUniformIndex(_))) => Ok(None),
+        Err(error) => Err(error.into()),
+    }
+}
@@ -0,0 +1,197 @@
+// A post-processing chain: effects applied one after another to the rendered scene, built into a
+// render graph that ping-pongs between two full resolution targets and ends on the window
+use super::{
+    AttachmentFormat, FullScreenPass, RenderGraph, RenderGraphError, TargetDescription,
+    SCENE_TARGET,
+};
+use crate::shader::post_processing::*;
+
+// Linear Algebra Types
+use ultraviolet::vec::Vec4;
+
+// Error Types
+type Result<T> = std::result::Result<T, RenderGraphError>;
+
+// Targets the chain alternates between
+const PING: &str = "post processing ping";
+const PONG: &str = "post processing pong";
+// Half resolution targets bloom blurs between
+const BLOOM_PING: &str = "bloom ping";
+const BLOOM_PONG: &str = "bloom pong";
+
+// How high dynamic range colours are mapped into 0.0 -> 1.0
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToneMapper {
+    Reinhard,
+    Aces,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bloom {
+    // Brightness above which pixels bloom
+    pub threshold: f32,
+    // Width of the soft transition around the threshold
+    pub knee: f32,
+    // How much of the blurred bright parts are added back
+    pub intensity: f32,
+    // Number of horizontal + vertical blur passes, more spreads the glow further
+    pub blur_passes: u32,
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Bloom {
+            threshold: 1.0,
+            knee: 0.5,
+            intensity: 0.5,
+            blur_passes: 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Effect {
+    ToneMapping { operator: ToneMapper, exposure: f32 },
+    Gamma(f32),
+    Fxaa,
+    Bloom(Bloom),
+    // A user fragment shader, which samples the previous pass as `uniform sampler2D previous`
+    Shader(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PostProcessing {
+    effects: Vec<Effect>,
+}
+
+impl PostProcessing {
+    pub fn new() -> Self {
+        PostProcessing::default()
+    }
+
+    pub fn effect(mut self, effect: Effect) -> Self {
+        self.effects.push(effect);
+        self
+    }
+
+    pub fn tone_mapping(self, operator: ToneMapper, exposure: f32) -> Self {
+        self.effect(Effect::ToneMapping { operator, exposure })
+    }
+
+    pub fn gamma(self, gamma: f32) -> Self {
+        self.effect(Effect::Gamma(gamma))
+    }
+
+    pub fn fxaa(self) -> Self {
+        self.effect(Effect::Fxaa)
+    }
+
+    pub fn bloom(self, bloom: Bloom) -> Self {
+        self.effect(Effect::Bloom(bloom))
+    }
+
+    pub fn shader<S>(self, fragment_shader_source: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.effect(Effect::Shader(fragment_shader_source.into()))
+    }
+
+    // Compiles the chain into a render graph, with the last effect drawing to the window
+    pub fn build(&self) -> Result<RenderGraph> {
+        let mut graph = RenderGraph::new();
+        let full = TargetDescription::new().color(AttachmentFormat::Rgba16F);
+        graph.add_target(PING, full.clone())?;
+        graph.add_target(PONG, full)?;
+        if self.effects.iter().any(|e| matches!(e, Effect::Bloom(_))) {
+            let half = TargetDescription::new()
+                .color(AttachmentFormat::Rgba16F)
+                .scale(0.5);
+            graph.add_target(BLOOM_PING, half.clone())?;
+            graph.add_target(BLOOM_PONG, half)?;
+        }
+
+        let mut previous = SCENE_TARGET;
+        for (index, effect) in self.effects.iter().enumerate() {
+            let last = index + 1 == self.effects.len();
+            let next = if previous == PING { PONG } else { PING };
+            let output = |pass: FullScreenPass| match last {
+                true => pass,
+                false => pass.output(next),
+            };
+
+            match effect {
+                Effect::ToneMapping { operator, exposure } => {
+                    let operator = match operator {
+                        ToneMapper::Reinhard => 0.0,
+                        ToneMapper::Aces => 1.0,
+                    };
+                    let pass =
+                        FullScreenPass::new("tone mapping", TONE_MAPPING_FRAGMENT_SHADER_SOURCE)
+                            .input(previous, "previous")
+                            .parameters(Vec4::new(*exposure, operator, 0.0, 0.0));
+                    graph.add_pass(output(pass))?;
+                }
+                Effect::Gamma(gamma) => {
+                    let pass = FullScreenPass::new("gamma", GAMMA_FRAGMENT_SHADER_SOURCE)
+                        .input(previous, "previous")
+                        .parameters(Vec4::new(*gamma, 0.0, 0.0, 0.0));
+                    graph.add_pass(output(pass))?;
+                }
+                Effect::Fxaa => {
+                    let pass = FullScreenPass::new("fxaa", FXAA_FRAGMENT_SHADER_SOURCE)
+                        .input(previous, "previous");
+                    graph.add_pass(output(pass))?;
+                }
+                Effect::Bloom(bloom) => {
+                    let pass = FullScreenPass::new(
+                        "bloom bright pass",
+                        BLOOM_BRIGHT_PASS_FRAGMENT_SHADER_SOURCE,
+                    )
+                    .input(previous, "previous")
+                    .output(BLOOM_PING)
+                    .parameters(Vec4::new(
+                        bloom.threshold,
+                        bloom.knee,
+                        0.0,
+                        0.0,
+                    ));
+                    graph.add_pass(pass)?;
+
+                    for _ in 0..bloom.blur_passes {
+                        for (from, to, direction) in [
+                            (BLOOM_PING, BLOOM_PONG, Vec4::unit_x()),
+                            (BLOOM_PONG, BLOOM_PING, Vec4::unit_y()),
+                        ] {
+                            let pass = FullScreenPass::new(
+                                "bloom blur",
+                                GAUSSIAN_BLUR_FRAGMENT_SHADER_SOURCE,
+                            )
+                            .input(from, "previous")
+                            .output(to)
+                            .parameters(direction);
+                            graph.add_pass(pass)?;
+                        }
+                    }
+
+                    let pass = FullScreenPass::new(
+                        "bloom composite",
+                        BLOOM_COMPOSITE_FRAGMENT_SHADER_SOURCE,
+                    )
+                    .input(previous, "previous")
+                    .input(BLOOM_PING, "bloom")
+                    .parameters(Vec4::new(bloom.intensity, 0.0, 0.0, 0.0));
+                    graph.add_pass(output(pass))?;
+                }
+                Effect::Shader(source) => {
+                    let pass =
+                        FullScreenPass::new(format!("post processing {}", index), source.clone())
+                            .input(previous, "previous");
+                    graph.add_pass(output(pass))?;
+                }
+            }
+            previous = next;
+        }
+        Ok(graph)
+    }
+}
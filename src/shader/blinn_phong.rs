@@ -11,6 +11,8 @@ pub const VERTEX_SHADER_SOURCE: &str = r#"
     // Per object model-world normal pseudo-vector transform
     layout (location = 6) in mat3 object_mw_normal_transforms;
     // location = 7,8 reserved by `object_mw_normal_transforms`
+    // Per object flag, 1.0 if shadows should be applied to the object
+    layout (location = 9) in float object_receives_shadows;
 
     // View-Projection transformation matrix
     uniform mat4 view_projection_matrix;
@@ -18,145 +20,130 @@ pub const VERTEX_SHADER_SOURCE: &str = r#"
     // Pass the model-world transformed vertex and normal to the fragment shader for lighting calculations
     out vec4 mv_point;
     out vec3 mv_normal;
+    flat out float receives_shadows;
 
     void main() {
         gl_Position = view_projection_matrix * object_mw_transforms * vec4(vertices, 1.0);
         // Model - View only transforms for shading
         mv_point = object_mw_transforms * vec4(vertices, 1.0);
         mv_normal = normalize(object_mw_normal_transforms * normals);
+        receives_shadows = object_receives_shadows;
     }
 "#;
 
-pub const BLINN_FRAGMENT_SHADER_SOURCE: &str = r#"
+// Both the Blinn and Phong fragment shaders: Phong is compiled with `HALF_VECTOR_SPECULAR` defined,
+// which takes the specular term from the half vector instead of the reflected light
+pub const HALF_VECTOR_SPECULAR: &str = "HALF_VECTOR_SPECULAR";
+pub const FRAGMENT_SHADER_SOURCE: &str = r#"
     #version 460 core
 
-    // Dynamic lighting
-    struct Light {
-        vec4 color;
-        vec4 position;
-    };
-    layout (std140, binding = 1) uniform Lights {
-        Light lights [100];
-    };
-    uniform uint num_lights;
+    #include "cs6600/lights.glsl"
 
     // Ambient Lighting
     uniform vec4 ambient_light_color;
     uniform float ambient_intensity;
 
+    // Material
+    uniform vec3 material_ambient;
+    uniform vec3 material_diffuse;
+    uniform vec3 material_specular;
+    uniform float material_shininess;
+    // How much of the environment is mirrored by the surface (0.0 = none, 1.0 = a perfect mirror)
+    uniform float material_reflectivity;
+
+    // Environment reflections, sampled from the skybox when there is one
+    uniform uint use_reflections;
+    uniform samplerCube reflection_map;
+
     // Camera Position
     uniform vec3 camera_position;
 
+    #ifdef HALF_VECTOR_SPECULAR
+    uniform mat4 mv; // Model-View Matrix transforms the light source positions
+    #endif
+
     // Model-View Position and Normals for shading calculations
     in vec4 mv_point;
     in vec3 mv_normal;
+    flat in float receives_shadows;
 
     // Color of this fragment
     out vec4 fragColor;
 
+    #include "cs6600/lighting.glsl"
+
     void main() {
         // Material Properties
-        vec4 kd = vec4(0.9, 0.9, 0.9, 1.0);
+        vec4 kd = vec4(material_diffuse, 1.0);
+        // Interpolated normals aren't unit length
+        vec3 normal = normalize(mv_normal);
 
         // Loop accumulates color from lights sources in this vec
         vec4 final_color = vec4(0,0,0,1);
-        for(uint i = 0; i < num_lights; i++) {
+
+        // Only visit the lights binned into this fragment's cluster, or every light if culling
+        // is turned off
+        bool culled = cluster_grid.w != 0u;
+        uvec2 cluster = culled ? clusters[cluster_index()] : uvec2(0u, num_lights);
+        for(uint n = 0; n < cluster.y; n++) {
+            uint i = culled ? light_indices[cluster.x + n] : n;
             // LIGHT
             vec4 light_color = vec4(vec3(lights[i].color), 1.0) * lights[i].color.w;
-            // Light's position in World-Space
-            vec4 light_pos = lights[i].position;
             // Direction from the point on the object towards the light source
-            vec3 light_direction = vec3(normalize(light_pos - mv_point));
+        #ifdef HALF_VECTOR_SPECULAR
+            // Phong moves positional lights by the model-view matrix, Blinn uses them as given
+            vec3 light_direction = lights[i].position.w == 0.0
+                ? direction_to_light(lights[i], mv_point)
+                : vec3(normalize(mv * lights[i].position - mv_point));
+        #else
+            vec3 light_direction = direction_to_light(lights[i], mv_point);
+        #endif
+            // Cone falloff and shadowing
+            float visibility = spot_factor(lights[i], light_direction)
+                * range_factor(lights[i], mv_point)
+                * shadow_factor(lights[i], mv_point, normal, light_direction);
 
             // Geometry Term
-            float cos_theta = dot(normalize(mv_normal), light_direction);
+            float cos_theta = dot(normal, light_direction);
             float geometry_term = max(cos_theta, 0.0);
 
             // Diffuse Term
             vec4 diffuse = kd * geometry_term;
 
             // Specular Term
-            vec3 reflection_direction = reflect(-light_direction, mv_normal);
+        #ifdef HALF_VECTOR_SPECULAR
+            vec3 view_direction = normalize(vec3(-mv_point));
+            vec3 half_angle = normalize(light_direction + view_direction);
+
+            float cos_phi = dot(half_angle, normal);
+        #else
+            vec3 reflection_direction = reflect(-light_direction, normal);
             vec3 view_direction = normalize(camera_position - vec3(mv_point));
 
             float cos_phi = dot(reflection_direction, view_direction);
+        #endif
             cos_phi = max(cos_phi, 0.0);
-            vec4 ks = vec4(1.0, 1.0, 1.0, 1.0);
-            vec4 specular = ks * pow(cos_phi, 1000);
+            vec4 ks = vec4(material_specular, 1.0);
+            vec4 specular = ks * pow(cos_phi, material_shininess);
 
 
             // Output to screen
-            final_color += light_color * (diffuse + specular);
+            final_color += light_color * (diffuse + specular) * visibility;
         }
 
         // Ambient Light
-        vec4 ambient = kd * ambient_light_color * ambient_light_color.w;
-
-        fragColor = final_color + ambient;
-    }
-"#;
-
-pub const PHONG_FRAGMENT_SHADER_SOURCE: &str = r#"
-    #version 460 core
-
-    // Dynamic lighting
-    struct Light {
-        vec4 color;
-        vec4 position;
-    };
-    layout (std140, binding = 1) uniform Lights {
-        Light lights [100];
-    };
-    uniform mat4 mv; // Model-View Matrix transforms the light source positions
-    uniform uint num_lights;
-
-    // Ambient Lighting
-    uniform vec4 ambient_light_color;
-    uniform float ambient_intensity;
-
-    // Model-View Position and Normals for shading calculations
-    in vec4 mv_point;
-    in vec3 mv_normal;
-
-    // Color of this fragment
-    out vec4 fragColor;
-
-    void main() {
-        // Material Properties
-        vec4 kd = vec4(0.9, 0.9, 0.9, 1.0);
-
-        // Loop accumulates color from lights sources in this vec
-        vec4 final_color = vec4(0,0,0,1);
-        for(uint i = 0; i < num_lights; i++) {
-            vec4 light_color = vec4(vec3(lights[i].color), 1.0) * lights[i].color.w;
-            vec4 light_pos = mv * lights[i].position;
-            vec3 light_direction = vec3(normalize(light_pos - mv_point));
-
-            // Geometry Term
-            float cos_theta = dot(mv_normal, vec3(light_direction));
-            float geometry_term = max(cos_theta, 0.0);
-
-            // Diffuse Term
-            vec4 diffuse = kd * geometry_term;
-
-            // Specular Term
-            vec3 view_direction = normalize(vec3(-mv_point));
-            vec3 half_angle = normalize(light_direction + view_direction);
-
-            float cos_phi = dot(half_angle, mv_normal);
-            cos_phi = max(cos_phi, 0.0);
-            vec4 ks = vec4(1.0, 1.0, 1.0, 1.0);
-            vec4 specular = ks * pow(cos_phi, 1000);
-
-
-            // Output to screen
-            // Clamp ?
-            final_color += light_color * (diffuse + specular);
+        vec4 ka = vec4(material_ambient, 1.0);
+        vec4 ambient = ka * ambient_light_color * ambient_light_color.w;
+        vec4 color = final_color + ambient;
+
+        // Environment Reflections
+        if (use_reflections != 0u && material_reflectivity > 0.0) {
+            vec3 to_camera = normalize(camera_position - vec3(mv_point));
+            vec3 reflection = reflect(-to_camera, normalize(mv_normal));
+            vec3 environment = texture(reflection_map, reflection).rgb;
+            color.rgb = mix(color.rgb, environment, material_reflectivity);
         }
 
-        // Ambient Light
-        vec4 ambient = kd * ambient_light_color * ambient_light_color.w;
-
-        fragColor = final_color + ambient;
+        fragColor = color;
     }
 "#;
@@ -0,0 +1,78 @@
+// Cube maps: rendering into their faces, and drawing them as a skybox behind the scene
+
+// Renders a unit cube from its center once per cube map face
+pub const FACE_VERTEX_SHADER_SOURCE: &str = r#"
+    #version 460 core
+
+    layout (location = 0) in vec3 vertices;
+
+    // Projection * View of the cube map face being rendered
+    uniform mat4 face_view_projection;
+
+    // Direction from the center of the cube, used to look up the environment
+    out vec3 direction;
+
+    void main() {
+        direction = vertices;
+        gl_Position = face_view_projection * vec4(vertices, 1.0);
+    }
+"#;
+
+// Projects an equirectangular (latitude-longitude) image onto the faces of a cube map
+pub const EQUIRECTANGULAR_FRAGMENT_SHADER_SOURCE: &str = r#"
+    #version 460 core
+
+    uniform sampler2D equirectangular_map;
+
+    in vec3 direction;
+    out vec4 fragColor;
+
+    // 1 / (2 * PI), 1 / PI
+    const vec2 INVERSE_ATAN = vec2(0.1591, 0.3183);
+
+    void main() {
+        vec3 d = normalize(direction);
+        vec2 uv = vec2(atan(d.z, d.x), asin(d.y)) * INVERSE_ATAN + 0.5;
+        fragColor = vec4(texture(equirectangular_map, uv).rgb, 1.0);
+    }
+"#;
+
+// Draws the cube map around the camera. Only the camera's rotation is applied, and the cube is
+// pushed to the far plane so it sits behind everything drawn before it.
+pub const SKYBOX_VERTEX_SHADER_SOURCE: &str = r#"
+    #version 460 core
+
+    layout (location = 0) in vec3 vertices;
+
+    // Projection * rotation of the View matrix
+    uniform mat4 skybox_view_projection;
+
+    out vec3 direction;
+
+    void main() {
+        direction = vertices;
+        vec4 position = skybox_view_projection * vec4(vertices, 1.0);
+        // z = w -> depth of 1.0 after the perspective divide
+        gl_Position = position.xyww;
+    }
+"#;
+
+pub const SKYBOX_FRAGMENT_SHADER_SOURCE: &str = r#"
+    #version 460 core
+
+    uniform samplerCube skybox;
+    // 1 if the program the skybox is drawn behind tone maps and gamma corrects its output
+    uniform uint tone_map;
+
+    in vec3 direction;
+    out vec4 fragColor;
+
+    void main() {
+        vec3 color = texture(skybox, direction).rgb;
+        if (tone_map != 0u) {
+            color = color / (color + vec3(1.0));
+            color = pow(color, vec3(1.0 / 2.2));
+        }
+        fragColor = vec4(color, 1.0);
+    }
+"#;
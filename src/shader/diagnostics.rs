@@ -0,0 +1,332 @@
+// Drivers each format their info logs differently, e.g.
+//  - Mesa:   "0:12(5): error: `x' undeclared"
+//  - NVIDIA: "0(12) : error C1008: undefined variable "x""
+//  - AMD:    "ERROR: 0:12: 'x' : undeclared identifier"
+// so logs are parsed into diagnostics pointing at the file and line (after includes are mapped back)
+// they refer to, which are printed with the offending source lines highlighted.
+use super::preprocessor::{line_reference, Preprocessed};
+
+use bat::line_range::{LineRange, LineRanges};
+use bat::{Input, PrettyPrinter};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    // None for link errors, which can't be traced back to a file
+    pub file: Option<String>,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+// A compile or link log, in full and parsed
+#[derive(Debug, Clone)]
+pub struct ShaderLog {
+    pub log: String,
+    pub diagnostics: Vec<Diagnostic>,
+    // The source of each file the diagnostics point into
+    sources: HashMap<String, String>,
+}
+
+// Lines shown either side of the one a diagnostic points at
+const CONTEXT_LINES: usize = 2;
+
+impl ShaderLog {
+    // A compile log, whose line numbers refer to the expanded source
+    pub(crate) fn compile(log: String, preprocessed: &Preprocessed) -> Self {
+        let diagnostics = parse(&log, Some(preprocessed));
+        ShaderLog::with_sources(log, diagnostics, preprocessed)
+    }
+
+    // Diagnostics already pointing into the files `preprocessed` was expanded from
+    pub(crate) fn with_sources(
+        log: String,
+        diagnostics: Vec<Diagnostic>,
+        preprocessed: &Preprocessed,
+    ) -> Self {
+        let sources = diagnostics
+            .iter()
+            .filter_map(|diagnostic| diagnostic.file.as_deref())
+            .filter_map(|file| Some((file.to_string(), preprocessed.file(file)?.to_string())))
+            .collect();
+        ShaderLog {
+            log,
+            diagnostics,
+            sources,
+        }
+    }
+
+    pub(crate) fn link(log: String) -> Self {
+        ShaderLog {
+            diagnostics: parse(&log, None),
+            log,
+            sources: HashMap::new(),
+        }
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    // Prints each diagnostic followed by the source lines around it, highlighted
+    pub fn print(&self) -> Result<(), bat::error::Error> {
+        for diagnostic in self.diagnostics.iter() {
+            println!("{}", diagnostic);
+            let (Some(file), Some(line)) = (diagnostic.file.as_ref(), diagnostic.line) else {
+                continue;
+            };
+            let Some(source) = self.sources.get(file) else {
+                continue;
+            };
+            let first = line.saturating_sub(CONTEXT_LINES).max(1);
+            PrettyPrinter::new()
+                .input(Input::from_bytes(source.as_bytes()).name(file))
+                .language("glsl")
+                .line_numbers(true)
+                .grid(true)
+                .line_ranges(LineRanges::from(vec![LineRange::new(
+                    first,
+                    line + CONTEXT_LINES,
+                )]))
+                .highlight(line)
+                .print()?;
+        }
+        Ok(())
+    }
+}
+
+// Plain text, so it can be logged or returned as an error: each diagnostic followed by the source
+// lines around it, with the one it points at marked. `print()` shows the same thing highlighted.
+impl std::fmt::Display for ShaderLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for diagnostic in self.diagnostics.iter() {
+            writeln!(f, "{}", diagnostic)?;
+            let (Some(file), Some(line)) = (diagnostic.file.as_ref(), diagnostic.line) else {
+                continue;
+            };
+            let Some(source) = self.sources.get(file) else {
+                continue;
+            };
+            let first = line.saturating_sub(CONTEXT_LINES).max(1);
+            let last = line + CONTEXT_LINES;
+            let width = last.to_string().len();
+            for (number, text) in source
+                .lines()
+                .enumerate()
+                .map(|(index, text)| (index + 1, text))
+            {
+                if number < first || number > last {
+                    continue;
+                }
+                let marker = if number == line { '>' } else { ' ' };
+                writeln!(f, "{} {:>width$} | {}", marker, number, text)?;
+                // Columns count from 1
+                if let (true, Some(column)) = (number == line, diagnostic.column) {
+                    let padding = " ".repeat(column.saturating_sub(1));
+                    writeln!(f, "  {:>width$} | {}^", "", padding)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+// e.g. "error: lighting.glsl:12:5: syntax error"
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}: ", self.severity)?;
+        if let Some(file) = self.file.as_ref() {
+            write!(f, "{}:", file)?;
+        }
+        if let Some(line) = self.line {
+            write!(f, "{}:", line)?;
+        }
+        if let Some(column) = self.column {
+            write!(f, "{}:", column)?;
+        }
+        if self.file.is_some() || self.line.is_some() {
+            write!(f, " ")?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+// Parses each line of a log, mapping line numbers back through `preprocessed` when there is one
+pub(crate) fn parse(log: &str, preprocessed: Option<&Preprocessed>) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    for text in log.lines().filter(|text| !text.trim().is_empty()) {
+        let reference = line_reference(text);
+        let severity = severity(text);
+
+        // Lines without either are the rest of the previous message
+        if reference.is_none() && severity.is_none() {
+            match diagnostics.last_mut() {
+                Some(previous) => {
+                    previous.message.push('\n');
+                    previous.message.push_str(text.trim());
+                }
+                None => diagnostics.push(Diagnostic {
+                    severity: Severity::Note,
+                    file: None,
+                    line: None,
+                    column: None,
+                    message: text.trim().to_string(),
+                }),
+            }
+            continue;
+        }
+
+        // Mesa follows the line with "(column)"
+        let mut message_start = 0;
+        let (mut line, mut column) = (None, None);
+        if let Some((range, number)) = reference {
+            message_start = range.end;
+            line = Some(number);
+            let rest = &text[range.end..];
+            if let Some((digits, _)) = rest.strip_prefix('(').and_then(|rest| rest.split_once(')'))
+            {
+                if let Ok(number) = digits.parse() {
+                    column = Some(number);
+                    message_start += digits.len() + 2;
+                }
+            }
+        }
+        let (severity, severity_end) = severity.unwrap_or((Severity::Note, 0));
+        let message = text[message_start.max(severity_end)..]
+            .trim_start_matches(|c: char| c == ':' || c.is_whitespace())
+            .to_string();
+
+        let located = line.and_then(|line| preprocessed?.locate(line));
+        diagnostics.push(Diagnostic {
+            severity,
+            file: located.map(|source_line| source_line.file.to_string()),
+            line: located.map(|source_line| source_line.line).or(line),
+            column,
+            message,
+        });
+    }
+    diagnostics
+}
+
+// The first "error" or "warning" word in the line, and where it ends
+fn severity(text: &str) -> Option<(Severity, usize)> {
+    let lowercase = text.to_ascii_lowercase();
+    [("error", Severity::Error), ("warning", Severity::Warning)]
+        .into_iter()
+        .filter_map(|(word, severity)| {
+            lowercase
+                .match_indices(word)
+                .find(|(start, _)| {
+                    *start == 0 || !lowercase.as_bytes()[start - 1].is_ascii_alphanumeric()
+                })
+                .map(|(start, _)| (start, severity, start + word.len()))
+        })
+        .min_by_key(|(start, _, _)| *start)
+        .map(|(_, severity, end)| (severity, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shader::Preprocessor;
+
+    fn diagnostic(
+        severity: Severity,
+        line: usize,
+        column: Option<usize>,
+        message: &str,
+    ) -> Diagnostic {
+        Diagnostic {
+            severity,
+            file: None,
+            line: Some(line),
+            column,
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn driver_log_formats() {
+        let mesa = "0:12(5): error: `x' undeclared\n0:3(1): warning: unused variable";
+        assert_eq!(
+            parse(mesa, None),
+            [
+                diagnostic(Severity::Error, 12, Some(5), "`x' undeclared"),
+                diagnostic(Severity::Warning, 3, Some(1), "unused variable"),
+            ]
+        );
+        let nvidia = "0(12) : error C1008: undefined variable \"x\"";
+        assert_eq!(
+            parse(nvidia, None),
+            [diagnostic(
+                Severity::Error,
+                12,
+                None,
+                "C1008: undefined variable \"x\""
+            )]
+        );
+        let amd = "ERROR: 0:12: 'x' : undeclared identifier\nERROR: 1 compilation errors.";
+        let parsed = parse(amd, None);
+        assert_eq!(
+            parsed[0],
+            diagnostic(Severity::Error, 12, None, "'x' : undeclared identifier")
+        );
+        assert_eq!(parsed[1].line, None);
+        assert_eq!(parsed[1].message, "1 compilation errors.");
+    }
+
+    #[test]
+    fn lines_map_through_includes() {
+        let preprocessed = Preprocessor::new()
+            .with_file("common.glsl", "float f() {\n    return x;\n}")
+            .process("#version 460 core\n#include \"common.glsl\"", "main.frag")
+            .unwrap();
+        let log = ShaderLog::compile("0:3(12): error: `x' undeclared".to_string(), &preprocessed);
+        let error = log.errors().next().unwrap();
+        assert_eq!(error.file.as_deref(), Some("common.glsl"));
+        assert_eq!(error.line, Some(2));
+        assert_eq!(error.to_string(), "error: common.glsl:2:12: `x' undeclared");
+        assert!(log.sources.contains_key("common.glsl"));
+    }
+
+    #[test]
+    fn display_shows_source_lines() {
+        let preprocessed = Preprocessor::new()
+            .with_file("common.glsl", "float f() {\n    return x;\n}")
+            .process("#version 460 core\n#include \"common.glsl\"", "main.frag")
+            .unwrap();
+        let log = ShaderLog::compile("0:3(12): error: `x' undeclared".to_string(), &preprocessed);
+        assert_eq!(
+            log.to_string(),
+            "error: common.glsl:2:12: `x' undeclared\n\
+             \x20 1 | float f() {\n\
+             > 2 |     return x;\n\
+             \x20   |            ^\n\
+             \x20 3 | }\n"
+        );
+
+        // Link logs have no source to show
+        let log = ShaderLog::link("error: `main' is not defined".to_string());
+        assert_eq!(log.to_string(), "error: `main' is not defined\n");
+    }
+}
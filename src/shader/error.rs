@@ -1,21 +1,47 @@
+use super::ShaderLog;
 use crate::error::GLUtilityError;
 
 // Error type for Shaders
 #[derive(Debug)]
 pub enum ShaderError {
     FailedToParseSource(GLUtilityError),
-    FailedToCompileShader(GLUtilityError),
-    FailedToLinkShaders(GLUtilityError),
+    FailedToCompileShader(Box<ShaderLog>),
+    FailedToLinkShaders(Box<ShaderLog>),
     FailedToLoadSource(GLUtilityError),
+    InvalidStage(String),
+    // `#include` of a file that isn't virtual, next to the includer, or in a search path
+    IncludeNotFound {
+        include: String,
+        file: String,
+        line: usize,
+    },
+    // The files including each other, starting and ending with the same file
+    IncludeCycle(Vec<String>),
+    MalformedInclude {
+        file: String,
+        line: usize,
+    },
+    // Neither OpenGL 4.6 nor GL_ARB_gl_spirv is available
+    SpirVUnsupported,
+    InvalidSpirV(String),
+    // Compiled before a window loaded the OpenGL functions, e.g. ahead of `use_window()`
+    NoContext,
 }
 
 impl std::error::Error for ShaderError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             ShaderError::FailedToParseSource(error) => Some(error),
-            ShaderError::FailedToCompileShader(error) => Some(error),
-            ShaderError::FailedToLinkShaders(error) => Some(error),
+            ShaderError::FailedToCompileShader(_) => None,
+            ShaderError::FailedToLinkShaders(_) => None,
             ShaderError::FailedToLoadSource(error) => Some(error),
+            ShaderError::InvalidStage(_) => None,
+            ShaderError::IncludeNotFound { .. } => None,
+            ShaderError::IncludeCycle(_) => None,
+            ShaderError::MalformedInclude { .. } => None,
+            ShaderError::SpirVUnsupported => None,
+            ShaderError::InvalidSpirV(_) => None,
+            ShaderError::NoContext => None,
         }
     }
 }
@@ -26,15 +52,54 @@ impl std::fmt::Display for ShaderError {
             ShaderError::FailedToParseSource(_) => {
                 write!(f, "Could not parse the shader's source.")
             }
-            ShaderError::FailedToCompileShader(_) => {
-                write!(f, "Failed to compile the shader from source.",)
+            ShaderError::FailedToCompileShader(log) => {
+                write!(f, "Failed to compile the shader from source.\n{}", log)
             }
-            ShaderError::FailedToLinkShaders(_) => {
-                write!(f, "Failed to link shaders to the GLProgram.")
+            ShaderError::FailedToLinkShaders(log) => {
+                write!(f, "Failed to link shaders to the GLProgram.\n{}", log)
             }
             ShaderError::FailedToLoadSource(_) => {
                 write!(f, "Failed to load the shader's source code.")
             }
+            ShaderError::InvalidStage(error) => {
+                write!(f, "Shader can't be used for that stage: {}", error)
+            }
+            ShaderError::IncludeNotFound {
+                include,
+                file,
+                line,
+            } => {
+                write!(
+                    f,
+                    "{}:{}: could not find \"{}\" to include",
+                    file, line, include
+                )
+            }
+            ShaderError::IncludeCycle(cycle) => {
+                write!(f, "Shaders include each other: {}", cycle.join(" -> "))
+            }
+            ShaderError::MalformedInclude { file, line } => {
+                write!(
+                    f,
+                    "{}:{}: #include must name a \"file\" or <file>",
+                    file, line
+                )
+            }
+            ShaderError::SpirVUnsupported => {
+                write!(
+                    f,
+                    "SPIR-V shaders need OpenGL 4.6 or the GL_ARB_gl_spirv extension"
+                )
+            }
+            ShaderError::InvalidSpirV(error) => {
+                write!(f, "Not a SPIR-V module: {}", error)
+            }
+            ShaderError::NoContext => {
+                write!(
+                    f,
+                    "Shaders can't be compiled until a window has loaded OpenGL"
+                )
+            }
         }
     }
 }
@@ -0,0 +1,134 @@
+// Lighting shared by the built-in shaders, included as "cs6600/lights.glsl" and
+// "cs6600/lighting.glsl"
+
+// The lights, their clusters, and their shadow maps
+pub const LIGHTS_SOURCE: &str = r#"
+    // Dynamic lighting
+    struct Light {
+        vec4 color;
+        // w = 0.0 for directional lights
+        vec4 position;
+        // xyz = direction, w = cos(spot cutoff) or -2.0 if the light has no cone
+        vec4 direction;
+        // x = shadow map layer (-1.0 if disabled), y = bias, z = PCF radius, w = cube map far plane
+        vec4 shadow;
+        // x = range of the light (0.0 = unlimited)
+        vec4 falloff;
+        mat4 shadow_matrix;
+    };
+    layout (std430, binding = 1) readonly buffer Lights {
+        Light lights [];
+    };
+
+    // Lights are binned into view-space clusters so each fragment only visits the lights that can
+    // reach it. `clusters` holds an (offset, count) pair into `light_indices` for every cluster.
+    layout (std430, binding = 2) readonly buffer LightClusters {
+        uvec2 clusters [];
+    };
+    layout (std430, binding = 3) readonly buffer LightIndices {
+        uint light_indices [];
+    };
+    // xyz = number of clusters along each axis, w = 1 if culling is enabled
+    uniform uvec4 cluster_grid;
+    // xy = size of a cluster tile in pixels, z = near plane, w = far plane of the depth slices
+    uniform vec4 cluster_depth;
+    // World-Space -> View-Space, used to find which depth slice a fragment falls in
+    uniform mat4 view_matrix;
+
+    // Shadow maps for directional & spot lights, and for point lights
+    uniform sampler2DArrayShadow shadow_maps;
+    uniform samplerCubeArray shadow_cube_maps;
+    uniform uint num_lights;
+"#;
+
+// Attenuation and shadowing of a light at a point. The including shader declares `mv_point` and
+// `receives_shadows` first.
+pub const LIGHTING_SOURCE: &str = r#"
+    // Direction from the point on the object towards the light source
+    vec3 direction_to_light(Light light, vec4 point) {
+        if (light.position.w == 0.0) {
+            return normalize(-light.direction.xyz);
+        }
+        return normalize(light.position.xyz - point.xyz);
+    }
+
+    // Smoothly fades positional lights out as they approach their range, 1.0 if unlimited
+    float range_factor(Light light, vec4 point) {
+        float range = light.falloff.x;
+        if (range <= 0.0 || light.position.w == 0.0) {
+            return 1.0;
+        }
+        float ratio = length(light.position.xyz - point.xyz) / range;
+        float window = clamp(1.0 - ratio * ratio * ratio * ratio, 0.0, 1.0);
+        return window * window;
+    }
+
+    // Index of the cluster this fragment falls in
+    uint cluster_index() {
+        float near_plane = cluster_depth.z;
+        float far_plane = cluster_depth.w;
+        float depth = max(-(view_matrix * mv_point).z, near_plane);
+        float slice = floor(log(depth / near_plane) / log(far_plane / near_plane) * float(cluster_grid.z));
+        uint z = uint(clamp(slice, 0.0, float(cluster_grid.z - 1u)));
+        uvec2 xy = min(uvec2(gl_FragCoord.xy / cluster_depth.xy), cluster_grid.xy - 1u);
+        return xy.x + xy.y * cluster_grid.x + z * cluster_grid.x * cluster_grid.y;
+    }
+
+    // Smoothly fades spot lights out at the edge of their cone, 1.0 for every other light
+    float spot_factor(Light light, vec3 to_light) {
+        float cos_cutoff = light.direction.w;
+        if (cos_cutoff < -1.5) {
+            return 1.0;
+        }
+        float cos_angle = dot(-to_light, normalize(light.direction.xyz));
+        return smoothstep(cos_cutoff, mix(cos_cutoff, 1.0, 0.1), cos_angle);
+    }
+
+    // Fraction of the light that reaches the point (1.0 = fully lit), filtered with PCF
+    float shadow_factor(Light light, vec4 point, vec3 normal, vec3 to_light) {
+        float layer = light.shadow.x;
+        if (layer < 0.0 || receives_shadows < 0.5) {
+            return 1.0;
+        }
+        // Surfaces at grazing angles to the light need more bias to avoid acne
+        float bias = max(light.shadow.y * (1.0 - dot(normal, to_light)), light.shadow.y * 0.1);
+        int radius = int(light.shadow.z);
+        float far_plane = light.shadow.w;
+        float lit = 0.0;
+        float samples = 0.0;
+
+        // Point lights: compare the distance to the light against the cube map
+        if (far_plane > 0.0) {
+            vec3 from_light = point.xyz - light.position.xyz;
+            float depth = length(from_light) / far_plane;
+            float spread = float(radius) / float(textureSize(shadow_cube_maps, 0).x);
+            for (int x = -radius; x <= radius; x++) {
+                for (int y = -radius; y <= radius; y++) {
+                    for (int z = -radius; z <= radius; z++) {
+                        vec3 offset = vec3(x, y, z) * spread * length(from_light);
+                        float closest = texture(shadow_cube_maps, vec4(from_light + offset, layer)).r;
+                        lit += (depth - bias > closest) ? 0.0 : 1.0;
+                        samples += 1.0;
+                    }
+                }
+            }
+            return lit / samples;
+        }
+
+        // Directional & spot lights: project into the light's clip space and use hardware compare
+        vec4 light_space = light.shadow_matrix * point;
+        vec3 projected = (light_space.xyz / light_space.w) * 0.5 + 0.5;
+        if (projected.z > 1.0) {
+            return 1.0;
+        }
+        vec2 texel = 1.0 / vec2(textureSize(shadow_maps, 0).xy);
+        for (int x = -radius; x <= radius; x++) {
+            for (int y = -radius; y <= radius; y++) {
+                vec2 uv = projected.xy + vec2(x, y) * texel;
+                lit += texture(shadow_maps, vec4(uv, layer, projected.z - bias));
+                samples += 1.0;
+            }
+        }
+        return lit / samples;
+    }
+"#;
@@ -3,17 +3,36 @@ pub mod error;
 use crate::error::GLUtilityError;
 pub use error::ShaderError;
 mod fragment_only;
+mod lighting;
+pub mod preprocessor;
+use preprocessor::Preprocessed;
+pub use preprocessor::{Defines, Preprocessor};
+pub mod program_binary;
+pub use program_binary::ProgramBinaryCache;
+pub mod diagnostics;
+pub(crate) mod variants;
+pub use diagnostics::{Diagnostic, Severity, ShaderLog};
+pub mod validation;
+pub use validation::{assert_valid_glsl, validate_glsl};
+pub mod spirv;
+pub use spirv::{spirv_supported, SpecializationConstants};
 type Result<T> = std::result::Result<T, error::ShaderError>;
 
 // Import our built-in shader types
 pub mod blinn_phong;
+pub mod cube_map;
+pub mod pbr;
+pub mod post_processing;
+pub mod shadows;
 
 // OpenGL Types
+use crate::window::object::{GLObject, ObjectKind};
 use gl::types::*;
 
 // We're calling into the user's OpenGL Library, so we need to work with raw strings and pointers
 use std::ffi::CString;
 use std::ptr;
+use std::rc::Rc;
 use std::str;
 
 // Dummy types to help the compiler catch mistakes
@@ -24,13 +43,42 @@ pub struct FragmentShader;
 #[derive(Debug)]
 pub struct GeometryShader;
 #[derive(Debug)]
-pub struct TesselationShader;
+pub struct TessControlShader;
+#[derive(Debug)]
+pub struct TessEvaluationShader;
+#[derive(Debug)]
+pub struct ComputeShader;
+
+// The OpenGL shader type of each stage
+pub trait ShaderStage {
+    const STAGE: GLenum;
+}
+
+impl ShaderStage for VertexShader {
+    const STAGE: GLenum = gl::VERTEX_SHADER;
+}
+impl ShaderStage for FragmentShader {
+    const STAGE: GLenum = gl::FRAGMENT_SHADER;
+}
+impl ShaderStage for GeometryShader {
+    const STAGE: GLenum = gl::GEOMETRY_SHADER;
+}
+impl ShaderStage for TessControlShader {
+    const STAGE: GLenum = gl::TESS_CONTROL_SHADER;
+}
+impl ShaderStage for TessEvaluationShader {
+    const STAGE: GLenum = gl::TESS_EVALUATION_SHADER;
+}
+impl ShaderStage for ComputeShader {
+    const STAGE: GLenum = gl::COMPUTE_SHADER;
+}
 
 // All shaders must have a defined type corresponding to the part of the graphics pipeline they
 // operate on, and prevents accidentally assigning a Geometry Shader to the Vertex Shader
 #[derive(Debug)]
 pub struct Shader<'a, Type> {
-    pub(crate) id: gl::types::GLuint,
+    // Shared by every Shader of the same cached variant
+    object: Rc<GLObject>,
     // If we have a 'static str as our shader code (e.g. when using a built-in shader), then we
     // skip an allocation. Not used, but pretty sure I'll use it eventually lol. #YAGNI
     #[allow(dead_code)]
@@ -38,12 +86,16 @@ pub struct Shader<'a, Type> {
     _pd: std::marker::PhantomData<Type>,
 }
 
+// Graphics pipelines have (at least) a vertex and fragment shader, compute pipelines only a
+// compute shader
 #[derive(Debug)]
 pub(crate) struct ShaderPipeline<'a> {
-    pub(crate) vertex_shader: Shader<'a, VertexShader>,
-    pub(crate) fragment_shader: Shader<'a, FragmentShader>,
+    pub(crate) vertex_shader: Option<Shader<'a, VertexShader>>,
+    pub(crate) fragment_shader: Option<Shader<'a, FragmentShader>>,
     pub(crate) geometry_shader: Option<Shader<'a, GeometryShader>>,
-    pub(crate) tessellation_shader: Option<Shader<'a, TesselationShader>>,
+    pub(crate) tess_control_shader: Option<Shader<'a, TessControlShader>>,
+    pub(crate) tess_evaluation_shader: Option<Shader<'a, TessEvaluationShader>>,
+    pub(crate) compute_shader: Option<Shader<'a, ComputeShader>>,
 }
 
 impl<'a> ShaderPipeline<'a> {
@@ -52,27 +104,119 @@ impl<'a> ShaderPipeline<'a> {
         vertex_shader: Shader<'a, VertexShader>,
         fragment_shader: Shader<'a, FragmentShader>,
         geometry_shader: Option<Shader<'a, GeometryShader>>,
-        tessellation_shader: Option<Shader<'a, TesselationShader>>,
+        tess_control_shader: Option<Shader<'a, TessControlShader>>,
+        tess_evaluation_shader: Option<Shader<'a, TessEvaluationShader>>,
     ) -> Result<Self> {
-        unsafe {
-            gl::AttachShader(program_id, vertex_shader.id);
-            gl::AttachShader(program_id, fragment_shader.id);
+        let pipeline = ShaderPipeline::linked(
+            vertex_shader,
+            fragment_shader,
+            geometry_shader,
+            tess_control_shader,
+            tess_evaluation_shader,
+        );
+        pipeline.link(program_id)?;
+        Ok(pipeline)
+    }
 
-            if let Some(ref geometry_shader) = geometry_shader {
-                gl::AttachShader(program_id, geometry_shader.id);
-            }
-            if let Some(ref tessellation_shader) = tessellation_shader {
-                gl::AttachShader(program_id, tessellation_shader.id);
+    // Attaches every shader to the program and links it
+    pub(crate) fn link(&self, program_id: GLuint) -> Result<()> {
+        unsafe {
+            for shader_id in self.shader_ids() {
+                gl::AttachShader(program_id, shader_id);
             }
+            gl::LinkProgram(program_id);
+        }
+        // Check that all went well
+        link_shaders_success(program_id)
+    }
+
+    // Links the pipeline's shaders, which were compiled from `sources` with `defines`, or loads the
+    // program from `cache` when it holds a binary of them from this driver
+    pub(crate) fn link_cached(
+        &self,
+        program_id: GLuint,
+        sources: &[(GLenum, &str)],
+        defines: &Defines,
+        cache: Option<&ProgramBinaryCache>,
+    ) -> Result<()> {
+        let Some(cache) = cache else {
+            return self.link(program_id);
+        };
+        let preprocessor = Preprocessor::new().with_defines(defines.clone());
+        let expanded = sources
+            .iter()
+            .map(|(stage, source)| {
+                let name = format!("{} shader", stage_name(*stage));
+                Ok((*stage, preprocessor.process(source, &name)?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let keyed: Vec<(GLenum, &str)> = expanded
+            .iter()
+            .map(|(stage, preprocessed)| (*stage, preprocessed.source.as_str()))
+            .collect();
+        let key = ProgramBinaryCache::key(&keyed);
+
+        if cache.load(program_id, key) {
+            return Ok(());
+        }
+        cache.prepare(program_id);
+        self.link(program_id)?;
+        cache.store(program_id, key);
+        Ok(())
+    }
 
+    // Shaders already linked into a program, e.g. a cached variant
+    pub(crate) fn linked(
+        vertex_shader: Shader<'a, VertexShader>,
+        fragment_shader: Shader<'a, FragmentShader>,
+        geometry_shader: Option<Shader<'a, GeometryShader>>,
+        tess_control_shader: Option<Shader<'a, TessControlShader>>,
+        tess_evaluation_shader: Option<Shader<'a, TessEvaluationShader>>,
+    ) -> Self {
+        ShaderPipeline {
+            vertex_shader: Some(vertex_shader),
+            fragment_shader: Some(fragment_shader),
+            geometry_shader,
+            tess_control_shader,
+            tess_evaluation_shader,
+            compute_shader: None,
+        }
+    }
+
+    // IDs of every shader in the pipeline
+    pub(crate) fn shader_ids(&self) -> Vec<GLuint> {
+        [
+            self.vertex_shader.as_ref().map(|shader| shader.id()),
+            self.fragment_shader.as_ref().map(|shader| shader.id()),
+            self.geometry_shader.as_ref().map(|shader| shader.id()),
+            self.tess_control_shader.as_ref().map(|shader| shader.id()),
+            self.tess_evaluation_shader
+                .as_ref()
+                .map(|shader| shader.id()),
+            self.compute_shader.as_ref().map(|shader| shader.id()),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    // A compute shader is linked on its own
+    pub(crate) fn compute(
+        program_id: GLuint,
+        compute_shader: Shader<'a, ComputeShader>,
+    ) -> Result<Self> {
+        compute_shader.validate_stage(gl::COMPUTE_SHADER)?;
+        unsafe {
+            gl::AttachShader(program_id, compute_shader.id());
             gl::LinkProgram(program_id);
         }
-        // Check that all went well, and return a new ShaderPipline if so
         link_shaders_success(program_id).map(|_| ShaderPipeline {
-            vertex_shader,
-            fragment_shader,
-            geometry_shader,
-            tessellation_shader,
+            vertex_shader: None,
+            fragment_shader: None,
+            geometry_shader: None,
+            tess_control_shader: None,
+            tess_evaluation_shader: None,
+            compute_shader: Some(compute_shader),
         })
     }
 }
@@ -90,6 +234,31 @@ impl<'a> Shader<'a, VertexShader> {
     pub fn fragment_only() -> Result<Shader<'a, VertexShader>> {
         Self::new_shader(fragment_only::VERTEX_SHADER_SOURCE, gl::VERTEX_SHADER)
     }
+
+    pub fn shadow_depth() -> Result<Shader<'a, VertexShader>> {
+        Self::new_shader(shadows::VERTEX_SHADER_SOURCE, gl::VERTEX_SHADER)
+    }
+
+    pub fn pbr() -> Result<Shader<'a, VertexShader>> {
+        Self::new_shader(pbr::VERTEX_SHADER_SOURCE, gl::VERTEX_SHADER)
+    }
+
+    pub fn skybox() -> Result<Shader<'a, VertexShader>> {
+        Self::new_shader(cube_map::SKYBOX_VERTEX_SHADER_SOURCE, gl::VERTEX_SHADER)
+    }
+
+    // Renders a unit cube into each face of a cube map
+    pub(crate) fn cube_map_face() -> Result<Shader<'a, VertexShader>> {
+        Self::new_shader(cube_map::FACE_VERTEX_SHADER_SOURCE, gl::VERTEX_SHADER)
+    }
+
+    // Passes the texture coordinates of the full screen triangle along as `st`
+    pub fn full_screen() -> Result<Shader<'a, VertexShader>> {
+        Self::new_shader(
+            post_processing::FULL_SCREEN_VERTEX_SHADER_SOURCE,
+            gl::VERTEX_SHADER,
+        )
+    }
 }
 
 impl<'a> Shader<'a, FragmentShader> {
@@ -98,126 +267,252 @@ impl<'a> Shader<'a, FragmentShader> {
     }
 
     pub fn blinn() -> Result<Shader<'a, FragmentShader>> {
-        Self::new_shader(
-            blinn_phong::BLINN_FRAGMENT_SHADER_SOURCE,
-            gl::FRAGMENT_SHADER,
-        )
+        Self::with_defines(blinn_phong::FRAGMENT_SHADER_SOURCE, &Defines::new())
     }
 
     pub fn phong() -> Result<Shader<'a, FragmentShader>> {
+        let defines = Defines::new().flag(blinn_phong::HALF_VECTOR_SPECULAR);
+        Self::with_defines(blinn_phong::FRAGMENT_SHADER_SOURCE, &defines)
+    }
+
+    pub fn shadow_depth() -> Result<Shader<'a, FragmentShader>> {
+        Self::new_shader(shadows::FRAGMENT_SHADER_SOURCE, gl::FRAGMENT_SHADER)
+    }
+
+    pub fn pbr() -> Result<Shader<'a, FragmentShader>> {
+        Self::new_shader(pbr::FRAGMENT_SHADER_SOURCE, gl::FRAGMENT_SHADER)
+    }
+
+    pub fn skybox() -> Result<Shader<'a, FragmentShader>> {
+        Self::new_shader(cube_map::SKYBOX_FRAGMENT_SHADER_SOURCE, gl::FRAGMENT_SHADER)
+    }
+
+    pub(crate) fn equirectangular_to_cube_map() -> Result<Shader<'a, FragmentShader>> {
         Self::new_shader(
-            blinn_phong::PHONG_FRAGMENT_SHADER_SOURCE,
+            cube_map::EQUIRECTANGULAR_FRAGMENT_SHADER_SOURCE,
             gl::FRAGMENT_SHADER,
         )
     }
+
+    pub(crate) fn ibl_irradiance() -> Result<Shader<'a, FragmentShader>> {
+        Self::new_shader(pbr::IRRADIANCE_FRAGMENT_SHADER_SOURCE, gl::FRAGMENT_SHADER)
+    }
+
+    pub(crate) fn ibl_prefilter() -> Result<Shader<'a, FragmentShader>> {
+        Self::new_shader(pbr::PREFILTER_FRAGMENT_SHADER_SOURCE, gl::FRAGMENT_SHADER)
+    }
+
+    pub(crate) fn ibl_brdf() -> Result<Shader<'a, FragmentShader>> {
+        Self::new_shader(pbr::BRDF_FRAGMENT_SHADER_SOURCE, gl::FRAGMENT_SHADER)
+    }
+}
+
+impl<'a> Shader<'a, GeometryShader> {
+    pub fn new(source: &'a str) -> Result<Shader<'a, GeometryShader>> {
+        Self::new_shader(source, gl::GEOMETRY_SHADER)
+    }
+}
+
+// Tessellation control shaders are optional, without one patches are tessellated at the default
+// levels set by `DrawStyle::Patches`
+impl<'a> Shader<'a, TessControlShader> {
+    pub fn new(source: &'a str) -> Result<Shader<'a, TessControlShader>> {
+        Self::new_shader(source, gl::TESS_CONTROL_SHADER)
+    }
+}
+
+impl<'a> Shader<'a, TessEvaluationShader> {
+    pub fn new(source: &'a str) -> Result<Shader<'a, TessEvaluationShader>> {
+        Self::new_shader(source, gl::TESS_EVALUATION_SHADER)
+    }
+}
+
+impl<'a> Shader<'a, ComputeShader> {
+    pub fn new(source: &'a str) -> Result<Shader<'a, ComputeShader>> {
+        Self::new_shader(source, gl::COMPUTE_SHADER)
+    }
 }
 
 impl<'a, Type> Shader<'a, Type> {
-    // Create a new shader, of a specified 'Type'
-    fn new_shader(source: &'a str, shader_type: GLuint) -> Result<Shader<Type>> {
-        // Hoisted to make the construction more readable at the end
-        let shader;
-        let src_c_str = CString::new(source).map_err(|_| {
-            ShaderError::FailedToParseSource(GLUtilityError::FailedToConvertToCString(
-                source.to_string(),
-            ))
-        })?;
+    pub(crate) fn id(&self) -> GLuint {
+        self.object.id()
+    }
 
+    // Checks the shader is a successfully compiled shader object of the `stage` (e.g.
+    // GL_VERTEX_SHADER) it's about to be linked as
+    pub(crate) fn validate_stage(&self, stage: GLenum) -> Result<()> {
+        let (mut shader_type, mut compiled) = (0, gl::FALSE as GLint);
         unsafe {
-            // Aske OpenGL for a new shader, and attempt to compile the source
-            shader = gl::CreateShader(shader_type);
-            gl::ShaderSource(shader, 1, &src_c_str.as_ptr(), ptr::null());
-            gl::CompileShader(shader);
-
-            let mut success = gl::FALSE as GLint;
-            gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut success);
-            if success != gl::TRUE as GLint {
-                // Check if the shader compiled, and save the error log if not
-                // Determine how long the log is
-                let mut length = 0 as GLint;
-                gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut length);
-                let log_length: usize = length.try_into().map_err(|_| {
-                    ShaderError::FailedToCompileShader(GLUtilityError::CouldNotCreateErrorLog)
-                })?;
-                // Set up a buffer to receive the log
-                let mut error_log = Vec::<u8>::with_capacity(log_length);
-                error_log.set_len(log_length - 1); // Don't read the NULL terminator
-
-                // Actually get the log itself lol
-                gl::GetShaderInfoLog(
-                    shader,
-                    512,
-                    ptr::null_mut(),
-                    error_log.as_mut_ptr() as *mut GLchar,
-                );
-
-                let log = str::from_utf8(&error_log).unwrap_or_else(|error| {
-                    str::from_utf8(&error_log[..error.valid_up_to()])
-                        .unwrap()
-                        .into()
-                });
-
-                // Return the error log and exit
-                return Err(ShaderError::FailedToCompileShader(
-                    GLUtilityError::ErrorLog(log.to_string()),
-                ));
+            if gl::IsShader(self.id()) != gl::TRUE {
+                return Err(ShaderError::InvalidStage(format!(
+                    "{} is not a shader object (was it compiled before the window was created?)",
+                    self.id()
+                )));
             }
+            gl::GetShaderiv(self.id(), gl::SHADER_TYPE, &mut shader_type);
+            gl::GetShaderiv(self.id(), gl::COMPILE_STATUS, &mut compiled);
+        }
+        if shader_type as GLenum != stage {
+            return Err(ShaderError::InvalidStage(format!(
+                "expected a {} shader, but got a {} shader",
+                stage_name(stage),
+                stage_name(shader_type as GLenum)
+            )));
+        }
+        if compiled != gl::TRUE as GLint {
+            return Err(ShaderError::InvalidStage(format!(
+                "the {} shader did not compile",
+                stage_name(stage)
+            )));
+        }
+        Ok(())
+    }
+
+    // Takes ownership of the source, so the shader can outlive it
+    pub(crate) fn into_owned(self) -> Shader<'static, Type> {
+        Shader {
+            object: self.object,
+            source: std::borrow::Cow::Owned(self.source.into_owned()),
+            _pd: std::marker::PhantomData::<Type>,
         }
+    }
 
+    // Create a new shader, of a specified 'Type'. Only the built-in snippets can be included.
+    fn new_shader(source: &'a str, shader_type: GLuint) -> Result<Shader<Type>> {
+        let name = format!("{} shader", stage_name(shader_type));
+        let preprocessed = Preprocessor::new().process(source, &name)?;
         Ok(Shader {
-            id: shader,
+            object: Rc::new(compile(&preprocessed, shader_type)?),
             source: source.into(),
             _pd: std::marker::PhantomData::<Type>,
         })
     }
 }
 
-// When we're done with the shader, let OpenGL know it can clean it up
-// impl<'a, Type> Drop for Shader<'a, Type> {
-//     // Tell OpenGL we don't need the shader around anymore
-//     fn drop(&mut self) -> () {
-//         unsafe {
-//             gl::DeleteShader(self.id);
-//         }
-//     }
-// }
+impl<'a, Type: ShaderStage> Shader<'a, Type> {
+    // Includes are resolved by `preprocessor`, and compile errors refer to `name`
+    pub fn with_preprocessor(
+        source: &'a str,
+        name: &str,
+        preprocessor: &Preprocessor,
+    ) -> Result<Shader<'a, Type>> {
+        let preprocessed = preprocessor.process(source, name)?;
+        Ok(Shader {
+            object: Rc::new(compile(&preprocessed, Type::STAGE)?),
+            source: source.into(),
+            _pd: std::marker::PhantomData::<Type>,
+        })
+    }
+
+    // One variant of the source, with `defines` injected after its `#version` line
+    pub fn with_defines(source: &'a str, defines: &Defines) -> Result<Shader<'a, Type>> {
+        let name = format!("{} shader", stage_name(Type::STAGE));
+        let preprocessor = Preprocessor::new().with_defines(defines.clone());
+        Self::with_preprocessor(source, &name, &preprocessor)
+    }
+
+    // Loads the shader from disk, its includes are also looked for next to it
+    pub fn from_file<P>(path: P, preprocessor: &Preprocessor) -> Result<Shader<'static, Type>>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let preprocessed = preprocessor.process_file(path)?;
+        Ok(Shader {
+            object: Rc::new(compile(&preprocessed, Type::STAGE)?),
+            source: preprocessed.source.into(),
+            _pd: std::marker::PhantomData::<Type>,
+        })
+    }
+}
+
+// Compiles expanded source, with the error log's line numbers mapped back to the included files
+fn compile(preprocessed: &Preprocessed, shader_type: GLuint) -> Result<GLObject> {
+    let source = preprocessed.source.as_str();
+    let src_c_str = CString::new(source).map_err(|_| {
+        ShaderError::FailedToParseSource(GLUtilityError::FailedToConvertToCString(
+            source.to_string(),
+        ))
+    })?;
+
+    if !gl::CreateShader::is_loaded() {
+        return Err(ShaderError::NoContext);
+    }
+
+    // Aske OpenGL for a new shader, and attempt to compile the source. A failed shader is deleted
+    // when dropped.
+    let shader = GLObject::new(unsafe { gl::CreateShader(shader_type) }, ObjectKind::Shader);
+    let mut success = gl::FALSE as GLint;
+    unsafe {
+        gl::ShaderSource(shader.id(), 1, &src_c_str.as_ptr(), ptr::null());
+        gl::CompileShader(shader.id());
+        gl::GetShaderiv(shader.id(), gl::COMPILE_STATUS, &mut success);
+    }
+    if success != gl::TRUE as GLint {
+        let log = info_log(shader.id(), gl::GetShaderiv, gl::GetShaderInfoLog);
+        return Err(ShaderError::FailedToCompileShader(Box::new(
+            ShaderLog::compile(log, preprocessed),
+        )));
+    }
+    shader.label(preprocessed.name());
+
+    Ok(shader)
+}
+
+// Human readable name of a shader stage
+fn stage_name(stage: GLenum) -> &'static str {
+    match stage {
+        gl::VERTEX_SHADER => "vertex",
+        gl::FRAGMENT_SHADER => "fragment",
+        gl::GEOMETRY_SHADER => "geometry",
+        gl::TESS_CONTROL_SHADER => "tessellation control",
+        gl::TESS_EVALUATION_SHADER => "tessellation evaluation",
+        gl::COMPUTE_SHADER => "compute",
+        _ => "unknown",
+    }
+}
 
 // Helper function that checks if linking the shaders to the program was a success
 pub(crate) fn link_shaders_success(program_id: GLuint) -> Result<()> {
     let mut success = gl::FALSE as GLint;
     unsafe {
         gl::GetProgramiv(program_id, gl::LINK_STATUS, &mut success);
-        if success != gl::TRUE as GLint {
-            // Determine the log's length
-            let mut length = 0 as GLint;
-            gl::GetShaderiv(program_id, gl::INFO_LOG_LENGTH, &mut length);
-            let log_length: usize = length.try_into().map_err(|_| {
-                ShaderError::FailedToLinkShaders(GLUtilityError::CouldNotCreateErrorLog)
-            })?;
-
-            // Set up a buffer to receive the log
-            let mut error_log = Vec::<u8>::with_capacity(log_length);
-            if log_length > 0 {
-                error_log.set_len(log_length - 1);
-            } // Don't read the NULL terminator
-
-            gl::GetProgramInfoLog(
-                program_id,
-                512,
-                std::ptr::null_mut(),
-                error_log.as_mut_ptr() as *mut GLchar,
-            );
-
-            // Return the error log and exit
-            Err(ShaderError::FailedToLinkShaders(GLUtilityError::ErrorLog(
-                std::str::from_utf8(&error_log).unwrap().into(),
-            )))
-        } else {
-            Ok(())
+    }
+    match success == gl::TRUE as GLint {
+        true => Ok(()),
+        false => {
+            let log = info_log(program_id, gl::GetProgramiv, gl::GetProgramInfoLog);
+            Err(ShaderError::FailedToLinkShaders(Box::new(ShaderLog::link(
+                log,
+            ))))
         }
     }
 }
 
+// The whole info log of a shader or program
+pub(crate) fn info_log(
+    id: GLuint,
+    get_parameter: unsafe fn(GLuint, GLenum, *mut GLint),
+    get_log: unsafe fn(GLuint, GLsizei, *mut GLsizei, *mut GLchar),
+) -> String {
+    let mut length = 0;
+    unsafe {
+        get_parameter(id, gl::INFO_LOG_LENGTH, &mut length);
+    }
+    // The length includes the NULL terminator
+    let mut log = vec![0u8; length.max(1) as usize];
+    let mut written = 0;
+    unsafe {
+        get_log(
+            id,
+            log.len() as GLsizei,
+            &mut written,
+            log.as_mut_ptr() as *mut GLchar,
+        );
+    }
+    log.truncate(written.max(0) as usize);
+    String::from_utf8_lossy(&log).into_owned()
+}
+
 // Loads a shader from a path, nothing special
 pub fn load_shader<P>(path: P) -> Result<String>
 where
@@ -0,0 +1,400 @@
+// Physically based (metallic-roughness) shading. Attribute locations match the Blinn-Phong vertex
+// shader, with texture coordinates added, so meshes and the shadow pass work with either program.
+pub const VERTEX_SHADER_SOURCE: &str = r#"
+    #version 460 core
+
+    // Vertices in model space
+    layout (location = 0) in vec3 vertices;
+    // Normal pseudo-vectors in model space
+    layout (location = 1) in vec3 normals;
+    // Per object model-world transforms
+    layout (location = 2) in mat4 object_mw_transforms;
+    // location = 3,4,5 reserved by `object_mw_transforms`
+    // Per object model-world normal pseudo-vector transform
+    layout (location = 6) in mat3 object_mw_normal_transforms;
+    // location = 7,8 reserved by `object_mw_normal_transforms`
+    // Per object flag, 1.0 if shadows should be applied to the object
+    layout (location = 9) in float object_receives_shadows;
+    // Texture coordinates
+    layout (location = 10) in vec2 st_coordinates;
+
+    // View-Projection transformation matrix
+    uniform mat4 view_projection_matrix;
+
+    // Pass the model-world transformed vertex and normal to the fragment shader for lighting calculations
+    out vec4 mv_point;
+    out vec3 mv_normal;
+    out vec2 st;
+    flat out float receives_shadows;
+
+    void main() {
+        gl_Position = view_projection_matrix * object_mw_transforms * vec4(vertices, 1.0);
+        // Model - View only transforms for shading
+        mv_point = object_mw_transforms * vec4(vertices, 1.0);
+        mv_normal = normalize(object_mw_normal_transforms * normals);
+        st = st_coordinates;
+        receives_shadows = object_receives_shadows;
+    }
+"#;
+
+pub const FRAGMENT_SHADER_SOURCE: &str = r#"
+    #version 460 core
+
+    const float PI = 3.14159265359;
+
+    #include "cs6600/lights.glsl"
+
+    // Ambient Lighting, used when there is no environment map
+    uniform vec4 ambient_light_color;
+
+    // Camera Position
+    uniform vec3 camera_position;
+
+    // Material: linear albedo & alpha, and (metallic, roughness, ambient occlusion, unused)
+    uniform vec4 albedo_factor;
+    uniform vec4 material_factors;
+    // 1 if the (albedo, metallic-roughness, ambient occlusion, normal) map should be sampled
+    uniform uvec4 material_maps;
+    uniform sampler2D albedo_map;
+    uniform sampler2D metallic_roughness_map;
+    uniform sampler2D ambient_occlusion_map;
+    uniform sampler2D normal_map;
+
+    // Image Based Lighting
+    uniform uint use_environment;
+    uniform samplerCube irradiance_map;
+    uniform samplerCube prefiltered_map;
+    uniform sampler2D brdf_lut;
+    // Highest mip level of the prefiltered map (roughness 1.0)
+    uniform float prefiltered_levels;
+    // 0 when a post-processing chain tone maps the output instead
+    uniform uint tone_map;
+
+    // Model-View Position and Normals for shading calculations
+    in vec4 mv_point;
+    in vec3 mv_normal;
+    in vec2 st;
+    flat in float receives_shadows;
+
+    // Color of this fragment
+    out vec4 fragColor;
+
+    #include "cs6600/lighting.glsl"
+
+    // Trowbridge-Reitz GGX normal distribution
+    float distribution_ggx(float n_dot_h, float roughness) {
+        float a = roughness * roughness;
+        float a2 = a * a;
+        float denominator = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+        return a2 / (PI * denominator * denominator);
+    }
+
+    // Smith's method with the Schlick-GGX approximation, for direct lighting
+    float geometry_smith(float n_dot_v, float n_dot_l, float roughness) {
+        float k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+        float ggx_v = n_dot_v / (n_dot_v * (1.0 - k) + k);
+        float ggx_l = n_dot_l / (n_dot_l * (1.0 - k) + k);
+        return ggx_v * ggx_l;
+    }
+
+    vec3 fresnel_schlick(float cos_theta, vec3 f0) {
+        return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+    }
+
+    // Rough surfaces reflect less at grazing angles, used for ambient lighting
+    vec3 fresnel_schlick_roughness(float cos_theta, vec3 f0, float roughness) {
+        vec3 grazing = max(vec3(1.0 - roughness), f0);
+        return f0 + (grazing - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+    }
+
+    // Applies the normal map without per-vertex tangents by building the tangent frame from
+    // screen-space derivatives of the position and texture coordinates
+    vec3 perturb_normal(vec3 normal, vec3 position) {
+        vec3 dp1 = dFdx(position);
+        vec3 dp2 = dFdy(position);
+        vec2 duv1 = dFdx(st);
+        vec2 duv2 = dFdy(st);
+
+        vec3 dp2_perpendicular = cross(dp2, normal);
+        vec3 dp1_perpendicular = cross(normal, dp1);
+        vec3 tangent = dp2_perpendicular * duv1.x + dp1_perpendicular * duv2.x;
+        vec3 bitangent = dp2_perpendicular * duv1.y + dp1_perpendicular * duv2.y;
+        float scale = inversesqrt(max(dot(tangent, tangent), dot(bitangent, bitangent)));
+        mat3 tbn = mat3(tangent * scale, bitangent * scale, normal);
+
+        vec3 tangent_normal = texture(normal_map, st).xyz * 2.0 - 1.0;
+        return normalize(tbn * tangent_normal);
+    }
+
+    void main() {
+        // Material Properties
+        vec4 albedo = albedo_factor;
+        float metallic = material_factors.x;
+        float roughness = material_factors.y;
+        float ambient_occlusion = material_factors.z;
+        if (material_maps.x != 0u) {
+            albedo *= texture(albedo_map, st);
+        }
+        if (material_maps.y != 0u) {
+            vec4 metallic_roughness = texture(metallic_roughness_map, st);
+            roughness *= metallic_roughness.g;
+            metallic *= metallic_roughness.b;
+        }
+        if (material_maps.z != 0u) {
+            ambient_occlusion *= texture(ambient_occlusion_map, st).r;
+        }
+        // Perfectly smooth surfaces turn lights into infinitely bright points
+        roughness = clamp(roughness, 0.04, 1.0);
+
+        vec3 normal = normalize(mv_normal);
+        if (material_maps.w != 0u) {
+            normal = perturb_normal(normal, mv_point.xyz);
+        }
+        vec3 view_direction = normalize(camera_position - vec3(mv_point));
+        float n_dot_v = max(dot(normal, view_direction), 0.0001);
+
+        // Reflectance at normal incidence; dielectrics are ~4%, metals tint it by their albedo
+        vec3 f0 = mix(vec3(0.04), albedo.rgb, metallic);
+
+        // Loop accumulates outgoing radiance from light sources in this vec
+        vec3 radiance = vec3(0.0);
+
+        // Only visit the lights binned into this fragment's cluster, or every light if culling
+        // is turned off
+        bool culled = cluster_grid.w != 0u;
+        uvec2 cluster = culled ? clusters[cluster_index()] : uvec2(0u, num_lights);
+        for(uint n = 0; n < cluster.y; n++) {
+            uint i = culled ? light_indices[cluster.x + n] : n;
+            // LIGHT
+            vec3 light_color = lights[i].color.rgb * lights[i].color.w;
+            // Direction from the point on the object towards the light source
+            vec3 light_direction = direction_to_light(lights[i], mv_point);
+            // Cone falloff and shadowing
+            float visibility = spot_factor(lights[i], light_direction)
+                * range_factor(lights[i], mv_point)
+                * shadow_factor(lights[i], mv_point, normal, light_direction);
+
+            float n_dot_l = max(dot(normal, light_direction), 0.0);
+            if (n_dot_l <= 0.0 || visibility <= 0.0) {
+                continue;
+            }
+            vec3 halfway = normalize(view_direction + light_direction);
+            float n_dot_h = max(dot(normal, halfway), 0.0);
+
+            // Cook-Torrance BRDF
+            float distribution = distribution_ggx(n_dot_h, roughness);
+            float geometry = geometry_smith(n_dot_v, n_dot_l, roughness);
+            vec3 fresnel = fresnel_schlick(max(dot(halfway, view_direction), 0.0), f0);
+            vec3 specular = distribution * geometry * fresnel / (4.0 * n_dot_v * n_dot_l + 0.0001);
+
+            // Whatever isn't reflected is refracted and diffused, except by metals which absorb it
+            vec3 diffuse = (vec3(1.0) - fresnel) * (1.0 - metallic) * albedo.rgb / PI;
+
+            radiance += (diffuse + specular) * light_color * n_dot_l * visibility;
+        }
+
+        // Ambient Light
+        vec3 ambient;
+        if (use_environment != 0u) {
+            vec3 fresnel = fresnel_schlick_roughness(n_dot_v, f0, roughness);
+            vec3 diffuse = (vec3(1.0) - fresnel) * (1.0 - metallic)
+                * texture(irradiance_map, normal).rgb * albedo.rgb;
+
+            vec3 reflection = reflect(-view_direction, normal);
+            vec3 prefiltered = textureLod(prefiltered_map, reflection, roughness * prefiltered_levels).rgb;
+            vec2 brdf = texture(brdf_lut, vec2(n_dot_v, roughness)).rg;
+            vec3 specular = prefiltered * (fresnel * brdf.x + brdf.y);
+
+            ambient = (diffuse + specular) * ambient_occlusion;
+        } else {
+            ambient = ambient_light_color.rgb * ambient_light_color.w * albedo.rgb * ambient_occlusion;
+        }
+
+        // Reinhard tone mapping, then gamma correction since the framebuffer isn't sRGB
+        vec3 color = radiance + ambient;
+        if (tone_map != 0u) {
+            color = color / (color + vec3(1.0));
+            color = pow(color, vec3(1.0 / 2.2));
+        }
+        fragColor = vec4(color, albedo.a);
+    }
+"#;
+
+// Convolves the environment over the hemisphere around every direction: the diffuse irradiance
+pub const IRRADIANCE_FRAGMENT_SHADER_SOURCE: &str = r#"
+    #version 460 core
+
+    const float PI = 3.14159265359;
+
+    uniform samplerCube environment_map;
+
+    in vec3 direction;
+    out vec4 fragColor;
+
+    void main() {
+        vec3 normal = normalize(direction);
+        vec3 up = abs(normal.y) < 0.999 ? vec3(0.0, 1.0, 0.0) : vec3(0.0, 0.0, 1.0);
+        vec3 right = normalize(cross(up, normal));
+        up = normalize(cross(normal, right));
+
+        vec3 irradiance = vec3(0.0);
+        float samples = 0.0;
+        float delta = 0.025;
+        for (float phi = 0.0; phi < 2.0 * PI; phi += delta) {
+            for (float theta = 0.0; theta < 0.5 * PI; theta += delta) {
+                // Spherical -> Cartesian in tangent space -> World-Space
+                vec3 tangent = vec3(sin(theta) * cos(phi), sin(theta) * sin(phi), cos(theta));
+                vec3 sample_direction = tangent.x * right + tangent.y * up + tangent.z * normal;
+                irradiance += texture(environment_map, sample_direction).rgb * cos(theta) * sin(theta);
+                samples += 1.0;
+            }
+        }
+        fragColor = vec4(PI * irradiance / samples, 1.0);
+    }
+"#;
+
+// Pre-integrates the environment against the GGX distribution for one roughness (mip level) of
+// the prefiltered specular map, using importance sampling
+pub const PREFILTER_FRAGMENT_SHADER_SOURCE: &str = r#"
+    #version 460 core
+
+    const float PI = 3.14159265359;
+    const uint SAMPLES = 1024u;
+
+    uniform samplerCube environment_map;
+    uniform float roughness;
+    // Width of a face of the environment map, used to pick a mip level that avoids bright dots
+    uniform float environment_resolution;
+
+    in vec3 direction;
+    out vec4 fragColor;
+
+    float radical_inverse(uint bits) {
+        bits = (bits << 16u) | (bits >> 16u);
+        bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+        bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+        bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+        bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+        return float(bits) * 2.3283064365386963e-10;
+    }
+
+    vec2 hammersley(uint i, uint n) {
+        return vec2(float(i) / float(n), radical_inverse(i));
+    }
+
+    vec3 importance_sample_ggx(vec2 xi, vec3 normal, float roughness) {
+        float a = roughness * roughness;
+        float phi = 2.0 * PI * xi.x;
+        float cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+        float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+        vec3 halfway = vec3(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+
+        vec3 up = abs(normal.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+        vec3 tangent = normalize(cross(up, normal));
+        vec3 bitangent = cross(normal, tangent);
+        return normalize(tangent * halfway.x + bitangent * halfway.y + normal * halfway.z);
+    }
+
+    float distribution_ggx(float n_dot_h, float roughness) {
+        float a = roughness * roughness;
+        float a2 = a * a;
+        float denominator = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+        return a2 / (PI * denominator * denominator);
+    }
+
+    void main() {
+        // Assume the view direction is the reflection direction
+        vec3 normal = normalize(direction);
+        vec3 view_direction = normal;
+
+        vec3 color = vec3(0.0);
+        float weight = 0.0;
+        for (uint i = 0u; i < SAMPLES; i++) {
+            vec3 halfway = importance_sample_ggx(hammersley(i, SAMPLES), normal, roughness);
+            vec3 light_direction = normalize(2.0 * dot(view_direction, halfway) * halfway - view_direction);
+            float n_dot_l = dot(normal, light_direction);
+            if (n_dot_l > 0.0) {
+                // Sample a blurrier mip of the environment where samples are sparse
+                float n_dot_h = max(dot(normal, halfway), 0.0);
+                float pdf = distribution_ggx(n_dot_h, roughness) / 4.0 + 0.0001;
+                float texel_angle = 4.0 * PI / (6.0 * environment_resolution * environment_resolution);
+                float sample_angle = 1.0 / (float(SAMPLES) * pdf + 0.0001);
+                float level = roughness == 0.0 ? 0.0 : 0.5 * log2(sample_angle / texel_angle);
+
+                color += textureLod(environment_map, light_direction, level).rgb * n_dot_l;
+                weight += n_dot_l;
+            }
+        }
+        fragColor = vec4(color / weight, 1.0);
+    }
+"#;
+
+// Integrates the split-sum BRDF: x = scale, y = bias applied to F0, indexed by (n_dot_v, roughness)
+pub const BRDF_FRAGMENT_SHADER_SOURCE: &str = r#"
+    #version 460 core
+
+    const float PI = 3.14159265359;
+    const uint SAMPLES = 1024u;
+
+    in vec2 st;
+    out vec2 fragColor;
+
+    float radical_inverse(uint bits) {
+        bits = (bits << 16u) | (bits >> 16u);
+        bits = ((bits & 0x55555555u) << 1u) | ((bits & 0xAAAAAAAAu) >> 1u);
+        bits = ((bits & 0x33333333u) << 2u) | ((bits & 0xCCCCCCCCu) >> 2u);
+        bits = ((bits & 0x0F0F0F0Fu) << 4u) | ((bits & 0xF0F0F0F0u) >> 4u);
+        bits = ((bits & 0x00FF00FFu) << 8u) | ((bits & 0xFF00FF00u) >> 8u);
+        return float(bits) * 2.3283064365386963e-10;
+    }
+
+    vec2 hammersley(uint i, uint n) {
+        return vec2(float(i) / float(n), radical_inverse(i));
+    }
+
+    vec3 importance_sample_ggx(vec2 xi, vec3 normal, float roughness) {
+        float a = roughness * roughness;
+        float phi = 2.0 * PI * xi.x;
+        float cos_theta = sqrt((1.0 - xi.y) / (1.0 + (a * a - 1.0) * xi.y));
+        float sin_theta = sqrt(1.0 - cos_theta * cos_theta);
+        vec3 halfway = vec3(cos(phi) * sin_theta, sin(phi) * sin_theta, cos_theta);
+
+        vec3 up = abs(normal.z) < 0.999 ? vec3(0.0, 0.0, 1.0) : vec3(1.0, 0.0, 0.0);
+        vec3 tangent = normalize(cross(up, normal));
+        vec3 bitangent = cross(normal, tangent);
+        return normalize(tangent * halfway.x + bitangent * halfway.y + normal * halfway.z);
+    }
+
+    // Smith's method with the Schlick-GGX approximation, remapped for image based lighting
+    float geometry_smith(float n_dot_v, float n_dot_l, float roughness) {
+        float k = roughness * roughness / 2.0;
+        float ggx_v = n_dot_v / (n_dot_v * (1.0 - k) + k);
+        float ggx_l = n_dot_l / (n_dot_l * (1.0 - k) + k);
+        return ggx_v * ggx_l;
+    }
+
+    void main() {
+        float n_dot_v = max(st.x, 0.0001);
+        float roughness = st.y;
+        vec3 view_direction = vec3(sqrt(1.0 - n_dot_v * n_dot_v), 0.0, n_dot_v);
+        vec3 normal = vec3(0.0, 0.0, 1.0);
+
+        float scale = 0.0;
+        float bias = 0.0;
+        for (uint i = 0u; i < SAMPLES; i++) {
+            vec3 halfway = importance_sample_ggx(hammersley(i, SAMPLES), normal, roughness);
+            vec3 light_direction = normalize(2.0 * dot(view_direction, halfway) * halfway - view_direction);
+            float n_dot_l = max(light_direction.z, 0.0);
+            float n_dot_h = max(halfway.z, 0.0);
+            float v_dot_h = max(dot(view_direction, halfway), 0.0);
+            if (n_dot_l > 0.0) {
+                float geometry = geometry_smith(n_dot_v, n_dot_l, roughness);
+                float visibility = geometry * v_dot_h / (n_dot_h * n_dot_v);
+                float fresnel = pow(1.0 - v_dot_h, 5.0);
+                scale += (1.0 - fresnel) * visibility;
+                bias += fresnel * visibility;
+            }
+        }
+        fragColor = vec2(scale, bias) / float(SAMPLES);
+    }
+"#;
@@ -0,0 +1,263 @@
+// Full screen passes of the post-processing chain. Each one samples the previous pass as
+// `previous`, and receives its settings in the `parameters` uniform.
+
+// Draws `FragmentOnly::TRIANGLE`, passing the texture coordinates of the screen along as `st`
+pub const FULL_SCREEN_VERTEX_SHADER_SOURCE: &str = r#"
+    #version 460 core
+
+    layout (location = 0) in vec3 vertices;
+
+    out vec2 st;
+
+    void main() {
+        st = vertices.xy * 0.5 + 0.5;
+        gl_Position = vec4(vertices, 1.0);
+    }
+"#;
+
+// x = exposure, y = operator (0 = Reinhard, 1 = ACES)
+pub const TONE_MAPPING_FRAGMENT_SHADER_SOURCE: &str = r#"
+    #version 460 core
+
+    uniform sampler2D previous;
+    uniform vec4 parameters;
+
+    in vec2 st;
+    out vec4 fragColor;
+
+    // Krzysztof Narkowicz's fit of the ACES filmic curve
+    vec3 aces(vec3 color) {
+        const float a = 2.51;
+        const float b = 0.03;
+        const float c = 2.43;
+        const float d = 0.59;
+        const float e = 0.14;
+        return clamp((color * (a * color + b)) / (color * (c * color + d) + e), 0.0, 1.0);
+    }
+
+    void main() {
+        vec4 hdr = texture(previous, st);
+        vec3 color = hdr.rgb * parameters.x;
+        if (parameters.y > 0.5) {
+            color = aces(color);
+        } else {
+            color = color / (color + vec3(1.0));
+        }
+        fragColor = vec4(color, hdr.a);
+    }
+"#;
+
+// x = gamma
+pub const GAMMA_FRAGMENT_SHADER_SOURCE: &str = r#"
+    #version 460 core
+
+    uniform sampler2D previous;
+    uniform vec4 parameters;
+
+    in vec2 st;
+    out vec4 fragColor;
+
+    void main() {
+        vec4 color = texture(previous, st);
+        fragColor = vec4(pow(max(color.rgb, vec3(0.0)), vec3(1.0 / parameters.x)), color.a);
+    }
+"#;
+
+// Fast Approximate Anti-Aliasing. Works best on tone mapped, gamma corrected input.
+pub const FXAA_FRAGMENT_SHADER_SOURCE: &str = r#"
+    #version 460 core
+
+    const float EDGE_THRESHOLD_MIN = 0.0312;
+    const float EDGE_THRESHOLD_MAX = 0.125;
+    const float SUBPIXEL_QUALITY = 0.75;
+    const int ITERATIONS = 12;
+    const float QUALITY[12] = float[](1.0, 1.0, 1.0, 1.0, 1.0, 1.5, 2.0, 2.0, 2.0, 2.0, 4.0, 8.0);
+
+    uniform sampler2D previous;
+
+    in vec2 st;
+    out vec4 fragColor;
+
+    float luma(vec3 color) {
+        return sqrt(dot(color, vec3(0.299, 0.587, 0.114)));
+    }
+
+    float luma_at(vec2 uv) {
+        return luma(texture(previous, uv).rgb);
+    }
+
+    void main() {
+        vec2 texel = 1.0 / vec2(textureSize(previous, 0));
+        vec4 center = texture(previous, st);
+        float luma_center = luma(center.rgb);
+
+        // Skip fragments that aren't on an edge
+        float luma_down = luma_at(st + vec2(0.0, -texel.y));
+        float luma_up = luma_at(st + vec2(0.0, texel.y));
+        float luma_left = luma_at(st + vec2(-texel.x, 0.0));
+        float luma_right = luma_at(st + vec2(texel.x, 0.0));
+        float luma_min = min(luma_center, min(min(luma_down, luma_up), min(luma_left, luma_right)));
+        float luma_max = max(luma_center, max(max(luma_down, luma_up), max(luma_left, luma_right)));
+        float luma_range = luma_max - luma_min;
+        if (luma_range < max(EDGE_THRESHOLD_MIN, luma_max * EDGE_THRESHOLD_MAX)) {
+            fragColor = center;
+            return;
+        }
+
+        float luma_down_left = luma_at(st - texel);
+        float luma_up_right = luma_at(st + texel);
+        float luma_up_left = luma_at(st + vec2(-texel.x, texel.y));
+        float luma_down_right = luma_at(st + vec2(texel.x, -texel.y));
+
+        // Is the edge horizontal or vertical?
+        float luma_down_up = luma_down + luma_up;
+        float luma_left_right = luma_left + luma_right;
+        float luma_left_corners = luma_down_left + luma_up_left;
+        float luma_down_corners = luma_down_left + luma_down_right;
+        float luma_right_corners = luma_down_right + luma_up_right;
+        float luma_up_corners = luma_up_right + luma_up_left;
+        float edge_horizontal = abs(-2.0 * luma_left + luma_left_corners)
+            + abs(-2.0 * luma_center + luma_down_up) * 2.0
+            + abs(-2.0 * luma_right + luma_right_corners);
+        float edge_vertical = abs(-2.0 * luma_up + luma_up_corners)
+            + abs(-2.0 * luma_center + luma_left_right) * 2.0
+            + abs(-2.0 * luma_down + luma_down_corners);
+        bool horizontal = edge_horizontal >= edge_vertical;
+
+        // Which side of the edge is the fragment on?
+        float luma_1 = horizontal ? luma_down : luma_left;
+        float luma_2 = horizontal ? luma_up : luma_right;
+        float gradient_1 = luma_1 - luma_center;
+        float gradient_2 = luma_2 - luma_center;
+        bool steepest_1 = abs(gradient_1) >= abs(gradient_2);
+        float gradient_scaled = 0.25 * max(abs(gradient_1), abs(gradient_2));
+
+        float step_length = horizontal ? texel.y : texel.x;
+        float luma_local_average;
+        if (steepest_1) {
+            step_length = -step_length;
+            luma_local_average = 0.5 * (luma_1 + luma_center);
+        } else {
+            luma_local_average = 0.5 * (luma_2 + luma_center);
+        }
+        vec2 current_uv = st;
+        if (horizontal) {
+            current_uv.y += step_length * 0.5;
+        } else {
+            current_uv.x += step_length * 0.5;
+        }
+
+        // Walk along the edge in both directions until its ends are found
+        vec2 offset = horizontal ? vec2(texel.x, 0.0) : vec2(0.0, texel.y);
+        vec2 uv_1 = current_uv - offset;
+        vec2 uv_2 = current_uv + offset;
+        float luma_end_1 = luma_at(uv_1) - luma_local_average;
+        float luma_end_2 = luma_at(uv_2) - luma_local_average;
+        bool reached_1 = abs(luma_end_1) >= gradient_scaled;
+        bool reached_2 = abs(luma_end_2) >= gradient_scaled;
+        for (int i = 2; i < ITERATIONS && !(reached_1 && reached_2); i++) {
+            if (!reached_1) {
+                uv_1 -= offset * QUALITY[i];
+                luma_end_1 = luma_at(uv_1) - luma_local_average;
+                reached_1 = abs(luma_end_1) >= gradient_scaled;
+            }
+            if (!reached_2) {
+                uv_2 += offset * QUALITY[i];
+                luma_end_2 = luma_at(uv_2) - luma_local_average;
+                reached_2 = abs(luma_end_2) >= gradient_scaled;
+            }
+        }
+
+        // Offset towards the nearest end of the edge
+        float distance_1 = horizontal ? (st.x - uv_1.x) : (st.y - uv_1.y);
+        float distance_2 = horizontal ? (uv_2.x - st.x) : (uv_2.y - st.y);
+        bool direction_1 = distance_1 < distance_2;
+        float distance_final = min(distance_1, distance_2);
+        float edge_length = distance_1 + distance_2;
+        float pixel_offset = -distance_final / edge_length + 0.5;
+        bool center_smaller = luma_center < luma_local_average;
+        bool correct_variation = ((direction_1 ? luma_end_1 : luma_end_2) < 0.0) != center_smaller;
+        float final_offset = correct_variation ? pixel_offset : 0.0;
+
+        // Sub-pixel aliasing, e.g. single bright pixels
+        float luma_average = (1.0 / 12.0) * (2.0 * (luma_down_up + luma_left_right)
+            + luma_left_corners + luma_right_corners);
+        float subpixel_1 = clamp(abs(luma_average - luma_center) / luma_range, 0.0, 1.0);
+        float subpixel_2 = (-2.0 * subpixel_1 + 3.0) * subpixel_1 * subpixel_1;
+        float subpixel_offset = subpixel_2 * subpixel_2 * SUBPIXEL_QUALITY;
+        final_offset = max(final_offset, subpixel_offset);
+
+        vec2 final_uv = st;
+        if (horizontal) {
+            final_uv.y += final_offset * step_length;
+        } else {
+            final_uv.x += final_offset * step_length;
+        }
+        fragColor = vec4(texture(previous, final_uv).rgb, center.a);
+    }
+"#;
+
+// Keeps only the parts of the image brighter than the threshold, with a soft knee
+// x = threshold, y = knee
+pub const BLOOM_BRIGHT_PASS_FRAGMENT_SHADER_SOURCE: &str = r#"
+    #version 460 core
+
+    uniform sampler2D previous;
+    uniform vec4 parameters;
+
+    in vec2 st;
+    out vec4 fragColor;
+
+    void main() {
+        vec3 color = texture(previous, st).rgb;
+        float threshold = parameters.x;
+        float knee = max(parameters.y, 0.00001);
+        float brightness = max(color.r, max(color.g, color.b));
+        float soft = clamp(brightness - threshold + knee, 0.0, 2.0 * knee);
+        soft = soft * soft / (4.0 * knee);
+        float contribution = max(soft, brightness - threshold) / max(brightness, 0.00001);
+        fragColor = vec4(color * contribution, 1.0);
+    }
+"#;
+
+// One direction of a separable 9-tap Gaussian blur
+// xy = direction, (1, 0) for horizontal, (0, 1) for vertical
+pub const GAUSSIAN_BLUR_FRAGMENT_SHADER_SOURCE: &str = r#"
+    #version 460 core
+
+    const float WEIGHTS[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+    uniform sampler2D previous;
+    uniform vec4 parameters;
+
+    in vec2 st;
+    out vec4 fragColor;
+
+    void main() {
+        vec2 step = parameters.xy / vec2(textureSize(previous, 0));
+        vec3 color = texture(previous, st).rgb * WEIGHTS[0];
+        for (int i = 1; i < 5; i++) {
+            color += texture(previous, st + step * float(i)).rgb * WEIGHTS[i];
+            color += texture(previous, st - step * float(i)).rgb * WEIGHTS[i];
+        }
+        fragColor = vec4(color, 1.0);
+    }
+"#;
+
+// Adds the blurred bright parts back on top of the image
+// x = intensity
+pub const BLOOM_COMPOSITE_FRAGMENT_SHADER_SOURCE: &str = r#"
+    #version 460 core
+
+    uniform sampler2D previous;
+    uniform sampler2D bloom;
+    uniform vec4 parameters;
+
+    in vec2 st;
+    out vec4 fragColor;
+
+    void main() {
+        vec4 color = texture(previous, st);
+        fragColor = vec4(color.rgb + texture(bloom, st).rgb * parameters.x, color.a);
+    }
+"#;
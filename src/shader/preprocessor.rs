@@ -0,0 +1,457 @@
+// Resolves `#include "file.glsl"` directives before a shader's source is handed to OpenGL. Files
+// are looked up in an in-memory virtual filesystem first (which holds the built-in snippets, e.g.
+// "cs6600/lighting.glsl"), then next to the including file, then in each search path. The driver
+// only ever sees the expanded source, so the line map translates its line numbers back to the file
+// and line they came from. `#define`s can be injected after the `#version` line, so one source can
+// be compiled into several variants.
+use super::{lighting, Result, ShaderError};
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+// Snippets every preprocessor can include
+const BUILT_IN_FILES: [(&str, &str); 2] = [
+    ("cs6600/lights.glsl", lighting::LIGHTS_SOURCE),
+    ("cs6600/lighting.glsl", lighting::LIGHTING_SOURCE),
+];
+
+#[derive(Debug, Clone)]
+pub struct Preprocessor {
+    search_paths: Vec<PathBuf>,
+    virtual_files: HashMap<Rc<str>, Cow<'static, str>>,
+    defines: Defines,
+}
+
+// Macros defined for a variant of a shader, sorted by name so equal sets hash equally
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Defines(BTreeMap<Rc<str>, Rc<str>>);
+
+// Where a line of the expanded source came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLine {
+    pub file: Rc<str>,
+    // 1-based, like the driver's
+    pub line: usize,
+}
+
+// Expanded source, and where each of its lines came from
+#[derive(Debug, Clone)]
+pub struct Preprocessed {
+    pub source: String,
+    // What the file expanded was called
+    name: Rc<str>,
+    lines: Vec<SourceLine>,
+    // Source of every file expanded, to show the lines diagnostics point at
+    files: HashMap<Rc<str>, String>,
+}
+
+// A file being expanded, and how to find the files it includes
+struct File {
+    // What errors and the line map call it
+    name: Rc<str>,
+    // Identifies the file when checking for cycles, its path if it's on disk
+    key: Rc<str>,
+    directory: Option<PathBuf>,
+}
+
+impl Default for Preprocessor {
+    fn default() -> Self {
+        Preprocessor::new()
+    }
+}
+
+impl Preprocessor {
+    // Only the built-in snippets can be included
+    pub fn new() -> Self {
+        let virtual_files = BUILT_IN_FILES
+            .iter()
+            .map(|(name, source)| (Rc::from(*name), Cow::Borrowed(*source)))
+            .collect();
+        Preprocessor {
+            search_paths: Vec::new(),
+            virtual_files,
+            defines: Defines::default(),
+        }
+    }
+
+    // Defined at the top of the source being processed, but not of the files it includes
+    pub fn with_defines(mut self, defines: Defines) -> Self {
+        self.defines = defines;
+        self
+    }
+
+    // Directories searched, in the order they're added, for files that aren't virtual or next to
+    // the file including them
+    pub fn with_search_path<P>(mut self, path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        self.search_paths.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    // Adds a file to the virtual filesystem, replacing any file of the same name
+    pub fn with_file<S, Source>(mut self, name: S, source: Source) -> Self
+    where
+        S: AsRef<str>,
+        Source: Into<Cow<'static, str>>,
+    {
+        self.virtual_files
+            .insert(Rc::from(name.as_ref()), source.into());
+        self
+    }
+
+    // Expands `source`, which errors and the line map will call `name`
+    pub fn process(&self, source: &str, name: &str) -> Result<Preprocessed> {
+        let file = File {
+            name: Rc::from(name),
+            key: Rc::from(name),
+            directory: None,
+        };
+        self.expand(source, file)
+    }
+
+    // Expands a file on disk, whose includes are also looked for in its directory
+    pub fn process_file<P>(&self, path: P) -> Result<Preprocessed>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let source = super::load_shader(path)?;
+        self.expand(&source, File::on_disk(path))
+    }
+
+    fn expand(&self, source: &str, file: File) -> Result<Preprocessed> {
+        let mut preprocessed = Preprocessed {
+            source: String::with_capacity(source.len()),
+            name: file.name.clone(),
+            lines: Vec::new(),
+            files: HashMap::new(),
+        };
+        // Without a `#version` line, the defines go first
+        if !source.lines().any(is_version) {
+            self.inject_defines(&mut preprocessed);
+        }
+        let mut stack = Vec::new();
+        self.expand_into(source, file, &mut stack, &mut preprocessed)?;
+        Ok(preprocessed)
+    }
+
+    fn expand_into(
+        &self,
+        source: &str,
+        file: File,
+        stack: &mut Vec<Rc<str>>,
+        preprocessed: &mut Preprocessed,
+    ) -> Result<()> {
+        if let Some(start) = stack.iter().position(|key| *key == file.key) {
+            let mut cycle: Vec<String> = stack[start..].iter().map(|key| key.to_string()).collect();
+            cycle.push(file.key.to_string());
+            return Err(ShaderError::IncludeCycle(cycle));
+        }
+        stack.push(file.key.clone());
+        preprocessed
+            .files
+            .entry(file.name.clone())
+            .or_insert_with(|| source.to_string());
+
+        for (index, text) in source.lines().enumerate() {
+            let line = index + 1;
+            match include_target(text) {
+                None => {
+                    preprocessed.source.push_str(text);
+                    preprocessed.source.push('\n');
+                    preprocessed.lines.push(SourceLine {
+                        file: file.name.clone(),
+                        line,
+                    });
+                    // Nothing but comments may come before the `#version` line
+                    if stack.len() == 1 && is_version(text) {
+                        self.inject_defines(preprocessed);
+                    }
+                }
+                Some(None) => {
+                    return Err(ShaderError::MalformedInclude {
+                        file: file.name.to_string(),
+                        line,
+                    })
+                }
+                Some(Some(target)) => {
+                    let (included, source) = self.resolve(target, &file).ok_or_else(|| {
+                        ShaderError::IncludeNotFound {
+                            include: target.to_string(),
+                            file: file.name.to_string(),
+                            line,
+                        }
+                    })?;
+                    self.expand_into(&source, included, stack, preprocessed)?;
+                }
+            }
+        }
+
+        stack.pop();
+        Ok(())
+    }
+
+    fn inject_defines(&self, preprocessed: &mut Preprocessed) {
+        let file: Rc<str> = Rc::from("<defines>");
+        for (index, (name, value)) in self.defines.0.iter().enumerate() {
+            preprocessed
+                .source
+                .push_str(&format!("#define {} {}\n", name, value));
+            preprocessed.lines.push(SourceLine {
+                file: file.clone(),
+                line: index + 1,
+            });
+        }
+    }
+
+    // Finds the file `target` names, and its source
+    fn resolve(&self, target: &str, includer: &File) -> Option<(File, Cow<'static, str>)> {
+        if let Some((name, source)) = self.virtual_files.get_key_value(target) {
+            let file = File {
+                name: name.clone(),
+                key: name.clone(),
+                directory: None,
+            };
+            return Some((file, source.clone()));
+        }
+        includer
+            .directory
+            .iter()
+            .chain(self.search_paths.iter())
+            .map(|directory| directory.join(target))
+            .find(|path| path.is_file())
+            .and_then(|path| {
+                let source = std::fs::read_to_string(&path).ok()?;
+                Some((File::on_disk(&path), Cow::Owned(source)))
+            })
+    }
+}
+
+impl Defines {
+    pub fn new() -> Self {
+        Defines::default()
+    }
+
+    // `#define name value`, replacing any earlier value
+    pub fn define<S, V>(mut self, name: S, value: V) -> Self
+    where
+        S: AsRef<str>,
+        V: ToString,
+    {
+        self.0
+            .insert(Rc::from(name.as_ref()), Rc::from(value.to_string()));
+        self
+    }
+
+    // `#define name`, for `#ifdef name`
+    pub fn flag<S>(self, name: S) -> Self
+    where
+        S: AsRef<str>,
+    {
+        self.define(name, "")
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl File {
+    fn on_disk(path: &Path) -> Self {
+        // The same file reached through different relative paths is still the same file
+        let key = path
+            .canonicalize()
+            .unwrap_or_else(|_| path.to_path_buf())
+            .to_string_lossy()
+            .into();
+        File {
+            name: path.to_string_lossy().into(),
+            key,
+            directory: path.parent().map(Path::to_path_buf),
+        }
+    }
+}
+
+impl Preprocessed {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Where line `line` (1-based) of the expanded source came from
+    pub fn locate(&self, line: usize) -> Option<&SourceLine> {
+        line.checked_sub(1).and_then(|index| self.lines.get(index))
+    }
+
+    // The source of a file expanded into this one
+    pub fn file(&self, name: &str) -> Option<&str> {
+        self.files.get(name).map(String::as_str)
+    }
+
+    // Rewrites the line numbers in a driver's info log, e.g. "0:42(7): error: ..." or
+    // "0(42) : error C1008: ...", to the file and line they refer to
+    pub fn map_log(&self, log: &str) -> String {
+        log.lines()
+            .map(|text| match line_reference(text) {
+                Some((range, line)) => match self.locate(line) {
+                    Some(source_line) => format!(
+                        "{}{}:{}{}",
+                        &text[..range.start],
+                        source_line.file,
+                        source_line.line,
+                        &text[range.end..]
+                    ),
+                    None => text.to_string(),
+                },
+                None => text.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+fn is_version(text: &str) -> bool {
+    text.trim_start()
+        .strip_prefix('#')
+        .is_some_and(|directive| directive.trim_start().starts_with("version"))
+}
+
+// `Some(Some(file))` if the line is an include of `file`, `Some(None)` if it's an include that
+// doesn't name a file
+fn include_target(text: &str) -> Option<Option<&str>> {
+    let directive = text.trim_start().strip_prefix('#')?.trim_start();
+    let argument = directive.strip_prefix("include")?.trim();
+    let target = match argument.chars().next() {
+        Some('"') => argument[1..].split_once('"'),
+        Some('<') => argument[1..].split_once('>'),
+        _ => None,
+    };
+    Some(
+        target
+            .map(|(target, _)| target)
+            .filter(|target| !target.is_empty()),
+    )
+}
+
+// Finds the first "<string>:<line>" or "<string>(<line>)" in a line of an info log, and returns
+// where the reference is and the line it refers to
+pub(crate) fn line_reference(text: &str) -> Option<(Range<usize>, usize)> {
+    let bytes = text.as_bytes();
+    let digits = |start: usize| {
+        bytes[start..]
+            .iter()
+            .take_while(|byte| byte.is_ascii_digit())
+            .count()
+    };
+    let mut start = 0;
+    while start < bytes.len() {
+        let string_digits = digits(start);
+        // Source string numbers have to start a word
+        let starts_word = start == 0 || !bytes[start - 1].is_ascii_alphanumeric();
+        if string_digits == 0 || !starts_word {
+            start += string_digits.max(1);
+            continue;
+        }
+        let separator = start + string_digits;
+        let line_start = separator + 1;
+        let line_digits = match bytes.get(separator) {
+            Some(b':') | Some(b'(') if line_start < bytes.len() => digits(line_start),
+            _ => 0,
+        };
+        let line_end = line_start + line_digits;
+        // "<string>(<line>" has to be closed
+        let parenthesized = line_digits > 0 && bytes[separator] == b'(';
+        if line_digits > 0 && (!parenthesized || bytes.get(line_end) == Some(&b')')) {
+            let line = text[line_start..line_end].parse().ok()?;
+            let end = line_end + parenthesized as usize;
+            return Some((start..end, line));
+        }
+        start = separator;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn includes_expand_and_map_back() {
+        let preprocessor = Preprocessor::new()
+            .with_file(
+                "common.glsl",
+                "float twice(float x) {\n    return 2.0 * x;\n}",
+            )
+            .with_file(
+                "outer.glsl",
+                "#include \"common.glsl\"\nconst float ONE = 1.0;",
+            );
+        let source = "#version 460 core\n  #include <outer.glsl>\nvoid main() {}";
+        let preprocessed = preprocessor.process(source, "main.frag").unwrap();
+
+        assert_eq!(
+            preprocessed.source,
+            "#version 460 core\nfloat twice(float x) {\n    return 2.0 * x;\n}\n\
+             const float ONE = 1.0;\nvoid main() {}\n"
+        );
+        let located = |line| {
+            let source_line = preprocessed.locate(line).unwrap();
+            (source_line.file.to_string(), source_line.line)
+        };
+        assert_eq!(located(1), ("main.frag".to_string(), 1));
+        assert_eq!(located(3), ("common.glsl".to_string(), 2));
+        assert_eq!(located(5), ("outer.glsl".to_string(), 2));
+        assert_eq!(located(6), ("main.frag".to_string(), 3));
+        assert!(preprocessed.locate(0).is_none());
+
+        assert_eq!(
+            preprocessed.map_log("0:3(12): error: syntax error\n0(6) : error C0000: oops"),
+            "common.glsl:2(12): error: syntax error\nmain.frag:3 : error C0000: oops"
+        );
+        assert_eq!(
+            preprocessed.map_log("ERROR: 0:5: 'ONE' : redefinition"),
+            "ERROR: outer.glsl:2: 'ONE' : redefinition"
+        );
+    }
+
+    #[test]
+    fn defines_follow_the_version() {
+        let defines = Defines::new().flag("SHADOWS").define("LIGHTS", 4);
+        let preprocessor = Preprocessor::new().with_defines(defines);
+        let preprocessed = preprocessor
+            .process("// Comment\n#version 460 core\nvoid main() {}", "main.frag")
+            .unwrap();
+        assert_eq!(
+            preprocessed.source,
+            "// Comment\n#version 460 core\n#define LIGHTS 4\n#define SHADOWS \nvoid main() {}\n"
+        );
+        assert_eq!(preprocessed.locate(3).unwrap().file.as_ref(), "<defines>");
+        assert_eq!(preprocessed.locate(5).unwrap().line, 3);
+
+        let preprocessed = preprocessor.process("void main() {}", "main.frag").unwrap();
+        assert!(preprocessed.source.starts_with("#define LIGHTS 4\n"));
+    }
+
+    #[test]
+    fn cycles_and_missing_files() {
+        let preprocessor = Preprocessor::new()
+            .with_file("a.glsl", "#include \"b.glsl\"")
+            .with_file("b.glsl", "#include \"a.glsl\"");
+        assert!(matches!(
+            preprocessor.process("#include \"a.glsl\"", "main.frag"),
+            Err(ShaderError::IncludeCycle(cycle)) if cycle == ["a.glsl", "b.glsl", "a.glsl"]
+        ));
+        assert!(matches!(
+            preprocessor.process("\n#include \"c.glsl\"", "main.frag"),
+            Err(ShaderError::IncludeNotFound { line: 2, .. })
+        ));
+        assert!(matches!(
+            preprocessor.process("#include c.glsl", "main.frag"),
+            Err(ShaderError::MalformedInclude { line: 1, .. })
+        ));
+    }
+}
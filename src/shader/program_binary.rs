@@ -0,0 +1,140 @@
+// Caches linked programs on disk with `glGetProgramBinary()`, so they can be loaded instead of
+// compiled and linked the next time. Binaries are keyed by the expanded sources and by the driver,
+// since a binary only loads on the driver that produced it. Anything that goes wrong with the cache
+// just means compiling from source, so its errors are never surfaced.
+
+// OpenGL Types
+use gl::types::*;
+use std::ffi::CStr;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone)]
+pub struct ProgramBinaryCache {
+    directory: PathBuf,
+}
+
+impl ProgramBinaryCache {
+    pub fn new<P>(directory: P) -> Self
+    where
+        P: AsRef<std::path::Path>,
+    {
+        ProgramBinaryCache {
+            directory: directory.as_ref().to_path_buf(),
+        }
+    }
+
+    // Identifies the program linked from `sources` (each stage's expanded source) by the current
+    // context's driver
+    pub(crate) fn key(sources: &[(GLenum, &str)]) -> u64 {
+        let mut hash = fnv1a(FNV_OFFSET, driver().as_bytes());
+        for (stage, source) in sources {
+            hash = fnv1a(hash, &stage.to_le_bytes());
+            hash = fnv1a(hash, source.as_bytes());
+        }
+        hash
+    }
+
+    // Links the program from its cached binary, false if there isn't one or the driver rejects it
+    pub(crate) fn load(&self, program_id: GLuint, key: u64) -> bool {
+        let Ok(bytes) = std::fs::read(self.path(key)) else {
+            return false;
+        };
+        let Some((format, binary)) = bytes.split_first_chunk::<4>() else {
+            return false;
+        };
+        let mut linked = gl::FALSE as GLint;
+        unsafe {
+            gl::ProgramBinary(
+                program_id,
+                GLenum::from_le_bytes(*format),
+                binary.as_ptr() as *const std::ffi::c_void,
+                binary.len() as GLsizei,
+            );
+            gl::GetProgramiv(program_id, gl::LINK_STATUS, &mut linked);
+        }
+        linked == gl::TRUE as GLint
+    }
+
+    // Set before linking, or the driver may not keep the binary around
+    pub(crate) fn prepare(&self, program_id: GLuint) {
+        unsafe {
+            gl::ProgramParameteri(
+                program_id,
+                gl::PROGRAM_BINARY_RETRIEVABLE_HINT,
+                gl::TRUE as GLint,
+            );
+        }
+    }
+
+    // Saves the linked program's binary, if the driver can provide one
+    pub(crate) fn store(&self, program_id: GLuint, key: u64) {
+        let (mut formats, mut length) = (0, 0);
+        unsafe {
+            gl::GetIntegerv(gl::NUM_PROGRAM_BINARY_FORMATS, &mut formats);
+            gl::GetProgramiv(program_id, gl::PROGRAM_BINARY_LENGTH, &mut length);
+        }
+        if formats <= 0 || length <= 0 {
+            return;
+        }
+
+        let mut binary = vec![0u8; length as usize];
+        let (mut written, mut format) = (0, 0);
+        unsafe {
+            gl::GetProgramBinary(
+                program_id,
+                length,
+                &mut written,
+                &mut format,
+                binary.as_mut_ptr() as *mut std::ffi::c_void,
+            );
+        }
+        binary.truncate(written.max(0) as usize);
+
+        let mut bytes = format.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&binary);
+        // A missing binary is recompiled next time anyway
+        let _ = std::fs::create_dir_all(&self.directory)
+            .and_then(|_| std::fs::write(self.path(key), bytes));
+    }
+
+    fn path(&self, key: u64) -> PathBuf {
+        self.directory.join(format!("{:016x}.bin", key))
+    }
+}
+
+// The driver's vendor, renderer, and version, any of which changing invalidates its binaries
+fn driver() -> String {
+    [gl::VENDOR, gl::RENDERER, gl::VERSION]
+        .map(|name| unsafe {
+            let string = gl::GetString(name);
+            match string.is_null() {
+                true => String::new(),
+                false => CStr::from_ptr(string as *const _)
+                    .to_string_lossy()
+                    .into_owned(),
+            }
+        })
+        .join("\n")
+}
+
+// FNV-1a, since the key has to hash the same way in every run and with every toolchain
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_stable() {
+        assert_eq!(fnv1a(FNV_OFFSET, b""), FNV_OFFSET);
+        assert_eq!(fnv1a(FNV_OFFSET, b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a(FNV_OFFSET, b"foobar"), 0x8594_4171_f739_67e8);
+    }
+}
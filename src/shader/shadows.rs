@@ -0,0 +1,39 @@
+// Depth-only pass used to render shadow maps. Attribute locations match the Blinn-Phong vertex
+// shader so the same VAOs can be drawn with either program.
+pub const VERTEX_SHADER_SOURCE: &str = r#"
+    #version 460 core
+
+    // Vertices in model space
+    layout (location = 0) in vec3 vertices;
+    // Per object model-world transforms
+    layout (location = 2) in mat4 object_mw_transforms;
+
+    // World-Space -> Light-Space (clip) transform of the face being rendered
+    uniform mat4 light_view_projection;
+
+    // Needed by the fragment shader to compute linear depth for cube maps
+    out vec4 world_point;
+
+    void main() {
+        world_point = object_mw_transforms * vec4(vertices, 1.0);
+        gl_Position = light_view_projection * world_point;
+    }
+"#;
+
+pub const FRAGMENT_SHADER_SOURCE: &str = r#"
+    #version 460 core
+
+    // xyz = light position, w = far plane. A far plane of 0.0 keeps the hardware depth (2D maps),
+    // otherwise the normalized distance to the light is written instead (cube maps).
+    uniform vec4 light_position;
+
+    in vec4 world_point;
+
+    void main() {
+        if (light_position.w > 0.0) {
+            gl_FragDepth = length(world_point.xyz - light_position.xyz) / light_position.w;
+        } else {
+            gl_FragDepth = gl_FragCoord.z;
+        }
+    }
+"#;
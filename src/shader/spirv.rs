@@ -0,0 +1,210 @@
+// Loads shaders precompiled to SPIR-V with `GL_ARB_gl_spirv` (core in OpenGL 4.6). The `gl` crate's
+// bindings stop at 4.5, so `glSpecializeShader()` and the SPIR-V enums are loaded and defined here.
+// Specialization constants stand in for the `#define`s source shaders are templated with.
+use super::{info_log, Result, Shader, ShaderError, ShaderLog, ShaderStage};
+use crate::error::GLUtilityError;
+use crate::window::object::{GLObject, ObjectKind};
+
+// OpenGL Types
+use gl::types::*;
+use std::ffi::{CStr, CString};
+
+const SHADER_BINARY_FORMAT_SPIR_V: GLenum = 0x9551;
+// Every SPIR-V module starts with this word
+const SPIR_V_MAGIC: u32 = 0x0723_0203;
+
+type SpecializeShader = extern "system" fn(
+    shader: GLuint,
+    entry_point: *const GLchar,
+    constants: GLuint,
+    constant_indices: *const GLuint,
+    constant_values: *const GLuint,
+);
+
+// Values for the module's `layout(constant_id = ...)` constants, which are otherwise left at their
+// defaults
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SpecializationConstants {
+    indices: Vec<GLuint>,
+    values: Vec<GLuint>,
+}
+
+impl SpecializationConstants {
+    pub fn new() -> Self {
+        SpecializationConstants::default()
+    }
+
+    // Replaces the value of any constant already set with the same id
+    pub fn uint(mut self, id: GLuint, value: u32) -> Self {
+        match self.indices.iter().position(|index| *index == id) {
+            Some(position) => self.values[position] = value,
+            None => {
+                self.indices.push(id);
+                self.values.push(value);
+            }
+        }
+        self
+    }
+
+    pub fn int(self, id: GLuint, value: i32) -> Self {
+        self.uint(id, value as u32)
+    }
+
+    pub fn float(self, id: GLuint, value: f32) -> Self {
+        self.uint(id, value.to_bits())
+    }
+
+    pub fn bool(self, id: GLuint, value: bool) -> Self {
+        self.uint(id, value as u32)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+}
+
+impl<Type: ShaderStage> Shader<'static, Type> {
+    // Specializes the `entry_point` (usually "main") of a SPIR-V module. The result is a compiled
+    // shader like any other, so it's linked the same way as shaders compiled from source.
+    pub fn from_spirv(
+        bytes: &[u8],
+        entry_point: &str,
+        constants: &SpecializationConstants,
+    ) -> Result<Shader<'static, Type>> {
+        check_module(bytes)?;
+        let specialize = specialize_shader()?;
+        let entry_point = CString::new(entry_point).map_err(|_| {
+            ShaderError::FailedToParseSource(GLUtilityError::FailedToConvertToCString(
+                entry_point.to_string(),
+            ))
+        })?;
+
+        // Deleted when dropped, if it fails to specialize
+        let shader = GLObject::new(unsafe { gl::CreateShader(Type::STAGE) }, ObjectKind::Shader);
+        let mut success = gl::FALSE as GLint;
+        unsafe {
+            gl::ShaderBinary(
+                1,
+                &shader.id(),
+                SHADER_BINARY_FORMAT_SPIR_V,
+                bytes.as_ptr() as *const std::ffi::c_void,
+                bytes.len() as GLsizei,
+            );
+            specialize(
+                shader.id(),
+                entry_point.as_ptr(),
+                constants.indices.len() as GLuint,
+                constants.indices.as_ptr(),
+                constants.values.as_ptr(),
+            );
+            gl::GetShaderiv(shader.id(), gl::COMPILE_STATUS, &mut success);
+        }
+        if success != gl::TRUE as GLint {
+            let log = info_log(shader.id(), gl::GetShaderiv, gl::GetShaderInfoLog);
+            // There's no source for the log's lines to refer to
+            return Err(ShaderError::FailedToCompileShader(Box::new(
+                ShaderLog::link(log),
+            )));
+        }
+        Ok(Shader {
+            object: std::rc::Rc::new(shader),
+            source: std::borrow::Cow::Borrowed(""),
+            _pd: std::marker::PhantomData::<Type>,
+        })
+    }
+
+    // Loads the SPIR-V module from disk
+    pub fn from_spirv_file<P>(
+        path: P,
+        entry_point: &str,
+        constants: &SpecializationConstants,
+    ) -> Result<Shader<'static, Type>>
+    where
+        P: AsRef<std::path::Path>,
+    {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path).map_err(|io_error| {
+            ShaderError::FailedToLoadSource(GLUtilityError::CouldNotOpenFile(
+                path.to_string_lossy().to_string(),
+                io_error,
+            ))
+        })?;
+        Self::from_spirv(&bytes, entry_point, constants)
+    }
+}
+
+// Whether the current context can load SPIR-V modules
+pub fn spirv_supported() -> bool {
+    let (mut major, mut minor, mut extensions) = (0, 0, 0);
+    unsafe {
+        gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+        gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+        gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut extensions);
+    }
+    if (major, minor) >= (4, 6) {
+        return true;
+    }
+    (0..extensions.max(0) as GLuint).any(|index| unsafe {
+        let name = gl::GetStringi(gl::EXTENSIONS, index);
+        !name.is_null() && CStr::from_ptr(name as *const _).to_bytes() == b"GL_ARB_gl_spirv"
+    })
+}
+
+// `glSpecializeShader()`, or the extension's `glSpecializeShaderARB()`, from the current context
+fn specialize_shader() -> Result<SpecializeShader> {
+    if !spirv_supported() {
+        return Err(ShaderError::SpirVUnsupported);
+    }
+    ["glSpecializeShader", "glSpecializeShaderARB"]
+        .iter()
+        .find_map(|name| {
+            let name = CString::new(*name).ok()?;
+            let function = unsafe { glfw::ffi::glfwGetProcAddress(name.as_ptr()) };
+            // Both have the signature of `SpecializeShader`
+            (!function.is_null()).then(|| unsafe {
+                std::mem::transmute::<*const std::ffi::c_void, SpecializeShader>(function)
+            })
+        })
+        .ok_or(ShaderError::SpirVUnsupported)
+}
+
+// Catches files that aren't SPIR-V before the driver sees them
+fn check_module(bytes: &[u8]) -> Result<()> {
+    if bytes.len() < 20 || !bytes.len().is_multiple_of(4) {
+        return Err(ShaderError::InvalidSpirV(format!(
+            "{} bytes isn't a whole number of words, or is too short for the header",
+            bytes.len()
+        )));
+    }
+    // Modules are in the endianness of whatever wrote them
+    let magic = [u32::from_le_bytes, u32::from_be_bytes]
+        .iter()
+        .any(|from_bytes| from_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) == SPIR_V_MAGIC);
+    match magic {
+        true => Ok(()),
+        false => Err(ShaderError::InvalidSpirV(
+            "the module doesn't start with the SPIR-V magic number".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modules_are_checked() {
+        let mut module = SPIR_V_MAGIC.to_le_bytes().to_vec();
+        module.resize(20, 0);
+        assert!(check_module(&module).is_ok());
+        assert!(check_module(&module[..18]).is_err());
+        assert!(check_module(b"#version 460 core\n\0\0").is_err());
+
+        let constants = SpecializationConstants::new()
+            .uint(0, 4)
+            .float(1, 1.0)
+            .uint(0, 8);
+        assert_eq!(constants.indices, [0, 1]);
+        assert_eq!(constants.values, [8, 1.0f32.to_bits()]);
+    }
+}
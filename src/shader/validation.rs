@@ -0,0 +1,593 @@
+// Checks GLSL on the CPU, without a GL context, so CI can catch broken shaders without a GPU. The
+// source is preprocessed the same way it is before compiling, then checked for the mistakes a
+// driver would reject outright: a missing or unsupported `#version`, unbalanced brackets and
+// comments, a missing `main()`, and stage layouts the linker requires. Shaders that get past those
+// are type checked by naga's GLSL front end and validator, which catch e.g. undeclared identifiers
+// and mismatched types. naga is stricter than drivers in places, and has no tessellation or
+// geometry stages, so it's a check, not a guarantee.
+use super::{stage_name, Diagnostic, Preprocessor, Result, Severity, ShaderError, ShaderLog};
+
+// OpenGL Types
+use gl::types::*;
+use naga::front::glsl::{Frontend, Options};
+use naga::valid::{
+    Capabilities, EntryPointError, ValidationError, ValidationFlags, Validator, VaryingError,
+};
+use naga::{Span, TypeInner};
+use std::collections::HashMap;
+
+// The newest GLSL the crate targets
+const MAX_VERSION: u32 = 460;
+
+// Validates a shader that only includes the built-in snippets
+pub fn validate_glsl(source: &str, stage: GLenum) -> Result<()> {
+    let name = format!("{} shader", stage_name(stage));
+    validate_glsl_with(source, &name, stage, &Preprocessor::new())
+}
+
+// Validates a shader whose includes (and defines) are resolved by `preprocessor`
+pub fn validate_glsl_with(
+    source: &str,
+    name: &str,
+    stage: GLenum,
+    preprocessor: &Preprocessor,
+) -> Result<()> {
+    let preprocessed = preprocessor.process(source, name)?;
+    let mut diagnostics = check(&preprocessed.source, stage);
+    if diagnostics.is_empty() {
+        return Ok(());
+    }
+
+    // Lines refer to the expanded source until they're mapped back to the file they came from
+    for diagnostic in diagnostics.iter_mut() {
+        if let Some(source_line) = diagnostic.line.and_then(|line| preprocessed.locate(line)) {
+            diagnostic.file = Some(source_line.file.to_string());
+            diagnostic.line = Some(source_line.line);
+        }
+    }
+    let log = diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+    Err(ShaderError::FailedToCompileShader(Box::new(
+        ShaderLog::with_sources(log, diagnostics, &preprocessed),
+    )))
+}
+
+// For tests: panics with the diagnostics if the shader doesn't validate
+pub fn assert_valid_glsl(source: &str, stage: GLenum) {
+    if let Err(error) = validate_glsl(source, stage) {
+        panic!("invalid {} shader: {}", stage_name(stage), error);
+    }
+}
+
+// Every problem with the expanded source
+fn check(source: &str, stage: GLenum) -> Vec<Diagnostic> {
+    let (code, mut diagnostics) = strip_comments(source);
+    diagnostics.extend(check_version(&code));
+    diagnostics.extend(check_brackets(&code));
+
+    let tokens: Vec<&str> = tokens(&code).collect();
+    let declares = |sequence: &[&str]| {
+        tokens
+            .windows(sequence.len())
+            .any(|tokens| tokens == sequence)
+    };
+    if !declares(&["void", "main", "("]) {
+        diagnostics.push(error(None, "no `void main()` entry point".to_string()));
+    }
+    // The linker needs these, and they're easy to forget
+    let missing_layout = match stage {
+        gl::COMPUTE_SHADER if !tokens.contains(&"local_size_x") => Some(
+            "compute shaders must declare `layout(local_size_x = ...) in;`",
+        ),
+        gl::TESS_EVALUATION_SHADER
+            if !["triangles", "quads", "isolines"]
+                .iter()
+                .any(|primitive| tokens.contains(primitive)) =>
+        {
+            Some("tessellation evaluation shaders must declare `layout(triangles | quads | isolines) in;`")
+        }
+        _ => None,
+    };
+    if let Some(message) = missing_layout {
+        diagnostics.push(error(None, message.to_string()));
+    }
+    // naga's errors for a shader that's already broken would only repeat these
+    if diagnostics.is_empty() {
+        diagnostics.extend(type_check(source, &code, stage));
+    }
+    diagnostics.sort_by_key(|diagnostic| diagnostic.line);
+    diagnostics
+}
+
+// The source with comments blanked out (keeping every line and byte where it was), and an error
+// for a block comment that's never closed
+fn strip_comments(source: &str) -> (String, Vec<Diagnostic>) {
+    let mut code = String::with_capacity(source.len());
+    let mut diagnostics = Vec::new();
+    let (mut line, mut column) = (1, 1);
+    // Where the comment being skipped started, and whether it's a block comment
+    let mut comment: Option<(usize, usize, bool)> = None;
+    let mut chars = source.chars().peekable();
+    while let Some(c) = chars.next() {
+        let next = chars.peek().copied();
+        match (comment, c, next) {
+            (None, '/', Some('/')) => comment = Some((line, column, false)),
+            (None, '/', Some('*')) => {
+                comment = Some((line, column, true));
+                // So the '*' isn't also read as the end of the comment
+                chars.next();
+                code.push_str("  ");
+                column += 2;
+                continue;
+            }
+            (Some((_, _, false)), '\n', _) => comment = None,
+            (Some((_, _, true)), '*', Some('/')) => {
+                chars.next();
+                code.push_str("  ");
+                column += 2;
+                comment = None;
+                continue;
+            }
+            _ => {}
+        }
+        match (comment, c) {
+            (_, '\n') => {
+                code.push('\n');
+                line += 1;
+                column = 1;
+                continue;
+            }
+            // As many spaces as bytes, so offsets into the code are offsets into the source
+            (Some(_), _) => code.extend(std::iter::repeat_n(' ', c.len_utf8())),
+            (None, _) => code.push(c),
+        }
+        column += 1;
+    }
+    if let Some((line, column, true)) = comment {
+        let mut diagnostic = error(Some(line), "unterminated comment".to_string());
+        diagnostic.column = Some(column);
+        diagnostics.push(diagnostic);
+    }
+    (code, diagnostics)
+}
+
+// `#version` has to come before anything else, and be one the crate targets
+fn check_version(code: &str) -> Option<Diagnostic> {
+    let (index, first) = code
+        .lines()
+        .enumerate()
+        .find(|(_, text)| !text.trim().is_empty())?;
+    let line = Some(index + 1);
+    let version = first
+        .trim()
+        .strip_prefix('#')
+        .and_then(|directive| directive.trim_start().strip_prefix("version"));
+    match version.map(|version| version.split_whitespace().next().map(str::parse::<u32>)) {
+        None => Some(error(line, "`#version` must come first".to_string())),
+        Some(Some(Ok(version))) if version <= MAX_VERSION => None,
+        Some(Some(Ok(version))) => Some(error(
+            line,
+            format!("GLSL {} is newer than {}", version, MAX_VERSION),
+        )),
+        Some(_) => Some(error(line, "`#version` is missing its number".to_string())),
+    }
+}
+
+// Parses and validates the source with naga, which type checks it. naga has no tessellation or
+// geometry stages, so those shaders only get the checks above.
+fn type_check(source: &str, code: &str, stage: GLenum) -> Vec<Diagnostic> {
+    let stage = match stage {
+        gl::VERTEX_SHADER => naga::ShaderStage::Vertex,
+        gl::FRAGMENT_SHADER => naga::ShaderStage::Fragment,
+        gl::COMPUTE_SHADER => naga::ShaderStage::Compute,
+        _ => return Vec::new(),
+    };
+    let translation = Translation::new(source, code);
+    let locate = |span: Span, message: String| match span.is_defined() {
+        true => translation.locate(span, message),
+        false => error(None, message),
+    };
+
+    let module = match Frontend::default().parse(&Options::from(stage), &translation.source) {
+        Ok(module) => module,
+        Err(errors) => {
+            return errors
+                .errors
+                .iter()
+                .map(|error| locate(error.meta, error.kind.to_string()))
+                .collect()
+        }
+    };
+    // OpenGL matches inputs and outputs without a location by name, naga would put them all at
+    // location 0 and report the collision
+    let flags = ValidationFlags::all() - ValidationFlags::BINDINGS;
+    match Validator::new(flags, Capabilities::all()).validate(&module) {
+        Ok(_) => Vec::new(),
+        // OpenGL allows matrix vertex attributes, naga (following WebGPU) doesn't. Entry points are
+        // validated last, and their bodies before their arguments, so nothing else is missed.
+        Err(validation) if is_matrix_attribute(&module, validation.as_inner()) => Vec::new(),
+        Err(validation) => {
+            // The outermost error only says which function is invalid, the innermost says why
+            let mut message = validation.as_inner().to_string();
+            let mut cause = std::error::Error::source(validation.as_inner());
+            while let Some(error) = cause {
+                message = format!("{}: {}", message, error);
+                cause = error.source();
+            }
+            // The last span is the narrowest, e.g. the expression rather than the function
+            let span = validation
+                .spans()
+                .map(|(span, _)| *span)
+                .filter(Span::is_defined)
+                .last();
+            vec![locate(span.unwrap_or_default(), message)]
+        }
+    }
+}
+
+fn is_matrix_attribute(module: &naga::Module, error: &ValidationError) -> bool {
+    let ValidationError::EntryPoint {
+        source: EntryPointError::Argument(_, VaryingError::NotIOShareableType(ty)),
+        ..
+    } = error
+    else {
+        return false;
+    };
+    matches!(module.types[*ty].inner, TypeInner::Matrix { .. })
+}
+
+// The source in the dialect of GLSL naga reads, which is Vulkan's: uniforms need bindings, and
+// textures and samplers are separate objects. Every line stays where it was, so only columns have
+// to be mapped back.
+struct Translation {
+    source: String,
+    // For each line, where each edit ends in the translated line, and how much longer than the
+    // original the line is by then
+    shifts: Vec<Vec<(usize, isize)>>,
+}
+
+impl Translation {
+    fn new(source: &str, code: &str) -> Self {
+        // Combined samplers (e.g. `uniform sampler2D albedo_map;`), which are split in two
+        let samplers: HashMap<&str, &str> = code.lines().filter_map(sampler_uniform).collect();
+        let mut translated = String::with_capacity(source.len());
+        let mut shifts = Vec::new();
+        let mut bindings = 0;
+        let mut binding = || {
+            bindings += 1;
+            format!("layout(binding = {}) ", bindings - 1)
+        };
+        for (index, (text, code)) in source.split('\n').zip(code.split('\n')).enumerate() {
+            if index > 0 {
+                translated.push('\n');
+            }
+            // Each is the range of the original line replaced, and what it's replaced with
+            let mut edits: Vec<(usize, usize, String)> = Vec::new();
+            let words: Vec<(usize, &str)> = match code.trim_start().starts_with('#') {
+                true => Vec::new(),
+                false => words(code).collect(),
+            };
+            let mut declared = None;
+            for (position, &(start, word)) in words.iter().enumerate() {
+                let end = start + word.len();
+                if word == "uniform" {
+                    if !code[..start].contains("binding") {
+                        edits.push((start, start, binding()));
+                    }
+                    if let [(type_start, ty), (name_start, name), ..] = words[position + 1..] {
+                        if samplers.get(name) == Some(&ty) {
+                            let (texture, sampler) = split_sampler(ty).unwrap_or_default();
+                            let name_end = name_start + name.len();
+                            let declaration =
+                                format!("; {}uniform {} {}_sampler", binding(), sampler, name);
+                            edits.push((type_start, type_start + ty.len(), texture));
+                            edits.push((name_end, name_end, declaration));
+                            declared = Some(name_start);
+                        }
+                    }
+                } else if declared != Some(start) && !code[..start].ends_with('.') {
+                    // Uses of a split sampler put it back together
+                    if let Some(ty) = samplers.get(word) {
+                        edits.push((start, start, format!("{}(", ty)));
+                        edits.push((end, end, format!(", {}_sampler)", word)));
+                    }
+                }
+            }
+
+            let (mut last, mut shift, mut line_shifts) = (0, 0, Vec::new());
+            let line_start = translated.len();
+            for (start, end, replacement) in edits {
+                translated.push_str(&text[last..start]);
+                translated.push_str(&replacement);
+                shift += replacement.len() as isize - (end - start) as isize;
+                line_shifts.push((translated.len() - line_start, shift));
+                last = end;
+            }
+            translated.push_str(&text[last..]);
+            shifts.push(line_shifts);
+        }
+        Translation {
+            source: translated,
+            shifts,
+        }
+    }
+
+    // An error at the start of `span`, in the original source's lines and columns
+    fn locate(&self, span: Span, message: String) -> Diagnostic {
+        let location = span.location(&self.source);
+        let line = location.line_number as usize;
+        let column = location.line_position as usize - 1;
+        let shift = self
+            .shifts
+            .get(line - 1)
+            .and_then(|shifts| shifts.iter().rev().find(|(end, _)| *end <= column))
+            .map_or(0, |(_, shift)| *shift);
+        let mut diagnostic = error(Some(line), message);
+        diagnostic.column = Some((column as isize - shift).max(0) as usize + 1);
+        diagnostic
+    }
+}
+
+// The name and type of a uniform combined sampler declared on `code`
+fn sampler_uniform(code: &str) -> Option<(&str, &str)> {
+    let words: Vec<(usize, &str)> = words(code).collect();
+    let position = words.iter().position(|(_, word)| *word == "uniform")?;
+    let [(_, ty), (start, name), ..] = words[position + 1..] else {
+        return None;
+    };
+    // Arrays of samplers aren't split
+    let declaration_ends = code[start + name.len()..].trim_start().starts_with(';');
+    (split_sampler(ty).is_some() && declaration_ends).then_some((name, ty))
+}
+
+// The texture and sampler types a combined sampler type is split into, e.g. `texture2DArray` and
+// `samplerShadow` for `sampler2DArrayShadow`
+fn split_sampler(ty: &str) -> Option<(String, String)> {
+    let (kind, dimensions) = ty.split_once("sampler")?;
+    if !["", "i", "u"].contains(&kind) || dimensions.is_empty() || dimensions == "Shadow" {
+        return None;
+    }
+    Some(match dimensions.strip_suffix("Shadow") {
+        Some(dimensions) => (
+            format!("{}texture{}", kind, dimensions),
+            "samplerShadow".to_string(),
+        ),
+        None => (
+            format!("{}texture{}", kind, dimensions),
+            "sampler".to_string(),
+        ),
+    })
+}
+
+// Identifiers and keywords, and where they start
+fn words(code: &str) -> impl Iterator<Item = (usize, &str)> {
+    let is_word = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    std::iter::once(0)
+        .chain(
+            code.match_indices(move |c: char| !is_word(c))
+                .map(|(start, separator)| start + separator.len()),
+        )
+        .filter(move |&start| {
+            code[start..].starts_with(|c: char| is_word(c) && !c.is_ascii_digit())
+                && !code[..start].ends_with(is_word)
+        })
+        .map(move |start| {
+            let end = code[start..]
+                .find(|c: char| !is_word(c))
+                .map_or(code.len(), |end| start + end);
+            (start, &code[start..end])
+        })
+}
+
+// Brackets closed by the wrong bracket, or never closed
+fn check_brackets(code: &str) -> Vec<Diagnostic> {
+    let mut open: Vec<(char, usize, usize)> = Vec::new();
+    let mut diagnostics = Vec::new();
+    for (index, text) in code.lines().enumerate() {
+        // Directives, e.g. `#define`, aren't GLSL
+        if text.trim_start().starts_with('#') {
+            continue;
+        }
+        for (column, c) in text.chars().enumerate() {
+            let position = (index + 1, column + 1);
+            match c {
+                '(' | '[' | '{' => open.push((c, position.0, position.1)),
+                ')' | ']' | '}' => {
+                    let expected = match c {
+                        ')' => '(',
+                        ']' => '[',
+                        _ => '{',
+                    };
+                    match open.iter().rposition(|(opener, _, _)| *opener == expected) {
+                        // Anything opened since was never closed
+                        Some(matched) => {
+                            diagnostics.extend(open.drain(matched..).skip(1).map(never_closed));
+                            open.truncate(matched);
+                        }
+                        None => {
+                            let mut diagnostic =
+                                error(Some(position.0), format!("unexpected `{}`", c));
+                            diagnostic.column = Some(position.1);
+                            diagnostics.push(diagnostic);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    diagnostics.extend(open.into_iter().map(never_closed));
+    diagnostics
+}
+
+fn never_closed((c, line, column): (char, usize, usize)) -> Diagnostic {
+    let mut diagnostic = error(Some(line), format!("`{}` is never closed", c));
+    diagnostic.column = Some(column);
+    diagnostic
+}
+
+// Identifiers, numbers, and punctuation, one character at a time
+fn tokens(code: &str) -> impl Iterator<Item = &str> {
+    let mut rest = code;
+    std::iter::from_fn(move || {
+        rest = rest.trim_start();
+        let first = rest.chars().next()?;
+        let length = match first.is_ascii_alphanumeric() || first == '_' {
+            true => rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len()),
+            false => first.len_utf8(),
+        };
+        let (token, remaining) = rest.split_at(length);
+        rest = remaining;
+        Some(token)
+    })
+}
+
+fn error(line: Option<usize>, message: String) -> Diagnostic {
+    Diagnostic {
+        severity: Severity::Error,
+        file: None,
+        line,
+        column: None,
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shader::{blinn_phong, cube_map, pbr, post_processing, shadows, Defines};
+
+    #[test]
+    fn built_in_shaders_validate() {
+        let vertex_shaders = [
+            blinn_phong::VERTEX_SHADER_SOURCE,
+            pbr::VERTEX_SHADER_SOURCE,
+            shadows::VERTEX_SHADER_SOURCE,
+            cube_map::FACE_VERTEX_SHADER_SOURCE,
+            cube_map::SKYBOX_VERTEX_SHADER_SOURCE,
+            post_processing::FULL_SCREEN_VERTEX_SHADER_SOURCE,
+            super::super::fragment_only::VERTEX_SHADER_SOURCE,
+        ];
+        let fragment_shaders = [
+            blinn_phong::FRAGMENT_SHADER_SOURCE,
+            pbr::FRAGMENT_SHADER_SOURCE,
+            pbr::IRRADIANCE_FRAGMENT_SHADER_SOURCE,
+            pbr::PREFILTER_FRAGMENT_SHADER_SOURCE,
+            pbr::BRDF_FRAGMENT_SHADER_SOURCE,
+            shadows::FRAGMENT_SHADER_SOURCE,
+            cube_map::EQUIRECTANGULAR_FRAGMENT_SHADER_SOURCE,
+            cube_map::SKYBOX_FRAGMENT_SHADER_SOURCE,
+            post_processing::TONE_MAPPING_FRAGMENT_SHADER_SOURCE,
+            post_processing::GAMMA_FRAGMENT_SHADER_SOURCE,
+            post_processing::FXAA_FRAGMENT_SHADER_SOURCE,
+            post_processing::BLOOM_BRIGHT_PASS_FRAGMENT_SHADER_SOURCE,
+            post_processing::GAUSSIAN_BLUR_FRAGMENT_SHADER_SOURCE,
+            post_processing::BLOOM_COMPOSITE_FRAGMENT_SHADER_SOURCE,
+        ];
+        for source in vertex_shaders {
+            assert_valid_glsl(source, gl::VERTEX_SHADER);
+        }
+        for source in fragment_shaders {
+            assert_valid_glsl(source, gl::FRAGMENT_SHADER);
+        }
+        let phong = Preprocessor::new()
+            .with_defines(Defines::new().flag(blinn_phong::HALF_VECTOR_SPECULAR));
+        let validated = validate_glsl_with(
+            blinn_phong::FRAGMENT_SHADER_SOURCE,
+            "phong",
+            gl::FRAGMENT_SHADER,
+            &phong,
+        );
+        assert!(validated.is_ok());
+    }
+
+    #[test]
+    fn mistakes_are_located() {
+        let preprocessor =
+            Preprocessor::new().with_file("common.glsl", "float f() {\n    return (1.0;\n}");
+        let source = "#version 460 core\n#include \"common.glsl\"\n/* main */\nvoid main() {}";
+        let Err(ShaderError::FailedToCompileShader(log)) =
+            validate_glsl_with(source, "main.frag", gl::FRAGMENT_SHADER, &preprocessor)
+        else {
+            panic!("the unbalanced bracket wasn't caught");
+        };
+        let error = log.errors().next().unwrap();
+        assert_eq!(error.file.as_deref(), Some("common.glsl"));
+        assert_eq!((error.line, error.column), (Some(2), Some(12)));
+
+        let check = |source: &str, stage| {
+            check(source, stage)
+                .into_iter()
+                .map(|diagnostic| diagnostic.message)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            check("void main() {}", gl::FRAGMENT_SHADER),
+            ["`#version` must come first"]
+        );
+        assert_eq!(
+            check("#version 460\nvoid mian() {}", gl::VERTEX_SHADER),
+            ["no `void main()` entry point"]
+        );
+        assert_eq!(
+            check("#version 460\nvoid main() {}\n/*", gl::COMPUTE_SHADER).len(),
+            2
+        );
+    }
+
+    #[test]
+    fn type_errors_are_located() {
+        let preprocessor = Preprocessor::new().with_file(
+            "common.glsl",
+            "uniform sampler2D albedo_map;\nvec3 albedo(vec2 uv) {\n    \
+             return texture(albedo_map, uv).rgb * brightness;\n}",
+        );
+        let first_error = |common: &str, body: &str| {
+            let source = format!(
+                "#version 460 core\n{}\nout vec4 colour;\nvoid main() {{\n{}\n}}",
+                common, body
+            );
+            let Err(ShaderError::FailedToCompileShader(log)) =
+                validate_glsl_with(&source, "main.frag", gl::FRAGMENT_SHADER, &preprocessor)
+            else {
+                panic!("the mistake in `{}` wasn't caught", body);
+            };
+            let error = log.errors().next().unwrap();
+            (
+                error.file.clone(),
+                error.line,
+                error.column,
+                error.message.clone(),
+            )
+        };
+
+        let (file, line, column, message) = first_error("", "    colour = vec4(uv, 0.0, 1.0);");
+        assert_eq!(
+            (file.as_deref(), line, column),
+            (Some("main.frag"), Some(5), Some(19))
+        );
+        assert!(message.contains("uv"), "{}", message);
+
+        let (file, line, _, _) = first_error(
+            "",
+            "    colour = vec4(1.0);\n    vec3 rgb = colour;\n    colour = vec4(rgb, 1.0);",
+        );
+        assert_eq!((file.as_deref(), line), (Some("main.frag"), Some(6)));
+
+        // Past a sampler, which naga is given as a texture and a sampler
+        let (file, line, column, message) = first_error(
+            "#include \"common.glsl\"",
+            "    colour = vec4(albedo(vec2(0.0)), 1.0);",
+        );
+        assert_eq!(
+            (file.as_deref(), line, column),
+            (Some("common.glsl"), Some(3), Some(42))
+        );
+        assert!(message.contains("brightness"), "{}", message);
+    }
+}
@@ -0,0 +1,113 @@
+// Shaders compiled with different `#define`s, and the programs linked from them, cached per context
+// so each variant is only compiled and linked once however many programs use it
+use super::{compile, stage_name, Defines, Preprocessor, Result, Shader, ShaderStage};
+
+// OpenGL Types
+use crate::window::object::GLObject;
+use gl::types::*;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+#[derive(Debug, Default)]
+pub(crate) struct Variants {
+    // Deleted along with the context, since the cache holds on to them until then
+    shaders: HashMap<ShaderKey, Rc<GLObject>>,
+    // Keyed by the sorted IDs of the cached shaders linked into the program
+    programs: HashMap<Vec<GLuint>, Rc<GLObject>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ShaderKey {
+    stage: GLenum,
+    source: u64,
+    defines: Defines,
+}
+
+impl ShaderKey {
+    fn new(stage: GLenum, source: &str, defines: &Defines) -> Self {
+        let mut hasher = DefaultHasher::new();
+        source.hash(&mut hasher);
+        ShaderKey {
+            stage,
+            source: hasher.finish(),
+            defines: defines.clone(),
+        }
+    }
+}
+
+impl Variants {
+    // The variant of `source` with `defines`, compiled the first time it's asked for
+    pub(crate) fn shader<Type: ShaderStage>(
+        &mut self,
+        source: &str,
+        defines: &Defines,
+    ) -> Result<Shader<'static, Type>> {
+        let key = ShaderKey::new(Type::STAGE, source, defines);
+        let object = match self.shaders.get(&key) {
+            Some(object) => object.clone(),
+            None => {
+                let name = format!("{} shader", stage_name(Type::STAGE));
+                let preprocessor = Preprocessor::new().with_defines(defines.clone());
+                let object = Rc::new(compile(&preprocessor.process(source, &name)?, Type::STAGE)?);
+                self.shaders.insert(key, object.clone());
+                object
+            }
+        };
+        Ok(Shader {
+            object,
+            source: source.to_string().into(),
+            _pd: std::marker::PhantomData::<Type>,
+        })
+    }
+
+    // The program already linked from exactly these shaders, if they're all cached
+    pub(crate) fn program(&self, shader_ids: &[GLuint]) -> Option<Rc<GLObject>> {
+        self.program_key(shader_ids)
+            .and_then(|key| self.programs.get(&key).cloned())
+    }
+
+    pub(crate) fn insert_program(&mut self, shader_ids: &[GLuint], program: &Rc<GLObject>) {
+        if let Some(key) = self.program_key(shader_ids) {
+            self.programs.insert(key, program.clone());
+        }
+    }
+
+    // Only programs made entirely of cached shaders are cached, other shaders' IDs can be reused
+    fn program_key(&self, shader_ids: &[GLuint]) -> Option<Vec<GLuint>> {
+        let cached = |id: &GLuint| self.shaders.values().any(|cached| cached.id() == *id);
+        if !shader_ids.iter().all(cached) {
+            return None;
+        }
+        let mut key = shader_ids.to_vec();
+        key.sort_unstable();
+        Some(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variants_are_keyed_by_stage_source_and_defines() {
+        let source = "#version 460 core\nvoid main() {}\n";
+        let defines = Defines::new().flag("A").define("B", "2");
+        // Defines given in another order are the same variant
+        let reordered = Defines::new().define("B", "2").flag("A");
+        let key = ShaderKey::new(gl::VERTEX_SHADER, source, &defines);
+        assert_eq!(key, ShaderKey::new(gl::VERTEX_SHADER, source, &reordered));
+        // Keyed by the source's contents, not where it's stored
+        let copy: String = source.chars().collect();
+        assert_eq!(key, ShaderKey::new(gl::VERTEX_SHADER, &copy, &defines));
+
+        assert_ne!(key, ShaderKey::new(gl::FRAGMENT_SHADER, source, &defines));
+        assert_ne!(
+            key,
+            ShaderKey::new(gl::VERTEX_SHADER, source, &Defines::new())
+        );
+        let other = "#version 460 core\nvoid main() { }\n";
+        assert_ne!(key, ShaderKey::new(gl::VERTEX_SHADER, other, &defines));
+    }
+}
@@ -0,0 +1,335 @@
+// Cube map textures, loaded from six images or projected from an equirectangular image, and the
+// helpers used to render into them (also used by the skybox and image based lighting).
+use super::{dimensions, open, set_parameters, ColorSpace, Result, Texture, TextureError};
+use crate::program::shadows::cube_face_view_projections;
+use crate::shader::{FragmentShader, Shader, ShaderPipeline, VertexShader};
+use crate::uniform::{Uniform, UniformValue, UpdateUniform};
+use crate::window::object::GLObject;
+
+// OpenGL Types
+use gl::types::*;
+
+// Linear Algebra Types
+use ultraviolet::mat::Mat4;
+use ultraviolet::vec::Vec3;
+
+use std::ffi::c_void;
+use std::mem::size_of_val;
+use std::path::Path;
+use std::rc::Rc;
+
+// Width and height (in texels) of the faces of cube maps projected from equirectangular images
+const EQUIRECTANGULAR_CUBE_MAP_RESOLUTION: GLsizei = 512;
+
+impl Texture {
+    // Loads the six faces of a cube map, in the order +X, -X, +Y, -Y, +Z, -Z. Every face must be
+    // square, and the same size.
+    pub fn cube_map_from_images<P>(faces: [P; 6], color_space: ColorSpace) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let mut images = Vec::with_capacity(6);
+        for path in faces.iter() {
+            let image = open(path.as_ref())?.into_rgba8();
+            let size = dimensions(path.as_ref(), image.dimensions())?;
+            images.push((path.as_ref(), size, image));
+        }
+        let (_, (width, height), _) = images[0];
+        let mismatched = images
+            .iter()
+            .find(|(_, size, _)| size.0 != size.1 || *size != (width, height));
+        if let Some((path, _, _)) = mismatched {
+            return Err(TextureError::MismatchedCubeMapFace(
+                path.display().to_string(),
+            ));
+        }
+
+        let internal_format = match color_space {
+            ColorSpace::Srgb => gl::SRGB8_ALPHA8,
+            ColorSpace::Linear => gl::RGBA8,
+        };
+        let texture = Texture::new(gl::TEXTURE_CUBE_MAP, width, height);
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, texture.id());
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            for (face, (_, _, image)) in images.iter().enumerate() {
+                // Cube map faces are stored top row first, so they aren't flipped like 2D textures
+                gl::TexImage2D(
+                    gl::TEXTURE_CUBE_MAP_POSITIVE_X + face as GLenum,
+                    0,
+                    internal_format as GLint,
+                    width,
+                    height,
+                    0,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    image.as_ptr() as *const c_void,
+                );
+            }
+            gl::GenerateMipmap(gl::TEXTURE_CUBE_MAP);
+            set_parameters(
+                gl::TEXTURE_CUBE_MAP,
+                gl::LINEAR_MIPMAP_LINEAR,
+                gl::CLAMP_TO_EDGE,
+            );
+            gl::TexParameteri(
+                gl::TEXTURE_CUBE_MAP,
+                gl::TEXTURE_WRAP_R,
+                gl::CLAMP_TO_EDGE as GLint,
+            );
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, 0);
+        }
+        Ok(texture)
+    }
+
+    // Projects an equirectangular (latitude-longitude) image, e.g. an HDR panorama, onto the faces
+    // of a floating point, mipmapped, cube map
+    pub fn cube_map_from_equirectangular<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let equirectangular = Texture::from_hdr(path)?;
+        let resolution = EQUIRECTANGULAR_CUBE_MAP_RESOLUTION;
+        let levels = (resolution as f32).log2() as GLsizei + 1;
+        let cube_map = Texture::empty_cube(resolution, levels);
+
+        let vertex_shader = Shader::<VertexShader>::cube_map_face()?;
+        let fragment_shader = Shader::<FragmentShader>::equirectangular_to_cube_map()?;
+        let target = RenderTarget::new();
+        let cube = Triangles::unit_cube();
+        let projected = Pass::new(vertex_shader, fragment_shader).and_then(|pass| {
+            pass.label("equirectangular to cube map");
+            pass.uniform("equirectangular_map", &0)?;
+            equirectangular.bind(0);
+            pass.render_cube(&cube, &target, &cube_map, 0)
+        });
+        // Restores the state it changed before the cube map is used
+        drop(target);
+        projected?;
+
+        // Mip it so it can be sampled blurrier, e.g. by rough reflections
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, cube_map.id());
+            gl::GenerateMipmap(gl::TEXTURE_CUBE_MAP);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, 0);
+        }
+        Ok(cube_map)
+    }
+}
+
+// A framebuffer to render into textures with. Saves the state it tramples (viewport, depth test,
+// blending, face culling) when created, and restores it when dropped.
+pub(crate) struct RenderTarget {
+    framebuffer: GLObject,
+    viewport: [GLint; 4],
+    depth_test: bool,
+    blend: bool,
+    cull_face: bool,
+}
+
+impl RenderTarget {
+    pub(crate) fn new() -> Self {
+        let mut viewport = [0; 4];
+        let depth_test;
+        let blend;
+        let cull_face;
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+            depth_test = gl::IsEnabled(gl::DEPTH_TEST) == gl::TRUE;
+            blend = gl::IsEnabled(gl::BLEND) == gl::TRUE;
+            cull_face = gl::IsEnabled(gl::CULL_FACE) == gl::TRUE;
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Disable(gl::BLEND);
+            gl::Disable(gl::CULL_FACE);
+            gl::Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS);
+        }
+        let framebuffer = GLObject::framebuffer();
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer.id());
+        }
+        RenderTarget {
+            framebuffer,
+            viewport,
+            depth_test,
+            blend,
+            cull_face,
+        }
+    }
+
+    // Attaches `level` of a 2D texture (or a face of a cube map) as the color buffer, and sizes
+    // the viewport to match
+    pub(crate) fn attach(&self, target: GLenum, texture: &Texture, level: GLint) -> Result<()> {
+        let width = (texture.width >> level).max(1);
+        let height = (texture.height >> level).max(1);
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer.id());
+            gl::FramebufferTexture2D(
+                gl::FRAMEBUFFER,
+                gl::COLOR_ATTACHMENT0,
+                target,
+                texture.id(),
+                level,
+            );
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                return Err(TextureError::FramebufferIncomplete(status));
+            }
+            gl::Viewport(0, 0, width, height);
+            gl::Clear(gl::COLOR_BUFFER_BIT);
+        }
+        Ok(())
+    }
+}
+
+// The framebuffer itself is deleted after
+impl Drop for RenderTarget {
+    fn drop(&mut self) {
+        let Some(context) = self.framebuffer.context() else {
+            return;
+        };
+        let [x, y, width, height] = self.viewport;
+        context.with(|| unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::Viewport(x, y, width, height);
+            if self.depth_test {
+                gl::Enable(gl::DEPTH_TEST);
+            }
+            if self.blend {
+                gl::Enable(gl::BLEND);
+            }
+            if self.cull_face {
+                gl::Enable(gl::CULL_FACE);
+            }
+        });
+    }
+}
+
+// A small program used to render into a texture, deleted when dropped
+pub(crate) struct Pass {
+    program: GLObject,
+    #[allow(dead_code)]
+    shaders: ShaderPipeline<'static>,
+}
+
+impl Pass {
+    pub(crate) fn new(
+        vertex_shader: Shader<'static, VertexShader>,
+        fragment_shader: Shader<'static, FragmentShader>,
+    ) -> Result<Self> {
+        let program = GLObject::program();
+        let shaders = ShaderPipeline::new(
+            program.id(),
+            vertex_shader,
+            fragment_shader,
+            None,
+            None,
+            None,
+        )?;
+        unsafe {
+            gl::UseProgram(program.id());
+        }
+        Ok(Pass { program, shaders })
+    }
+
+    pub(crate) fn id(&self) -> GLuint {
+        self.program.id()
+    }
+
+    // Names the pass's program in debug messages and graphics debuggers
+    pub(crate) fn label(&self, name: &str) {
+        self.program.label(name);
+    }
+
+    pub(crate) fn uniform<Value>(&self, name: &str, value: &Value) -> Result<Rc<dyn UpdateUniform>>
+    where
+        Value: UniformValue + 'static,
+    {
+        Ok(Uniform::new(name, value)?.attach(self.id())?)
+    }
+
+    // Renders the unit cube into all six faces of mip `level` of a cube map. The vertex shader
+    // must take the face's transform as `face_view_projection`.
+    pub(crate) fn render_cube(
+        &self,
+        cube: &Triangles,
+        target: &RenderTarget,
+        cube_map: &Texture,
+        level: GLint,
+    ) -> Result<()> {
+        let face_view_projection = self.uniform("face_view_projection", &Mat4::identity())?;
+        let faces = cube_face_view_projections(Vec3::zero(), 0.1, 10.0);
+        for (face, view_projection) in faces.iter().enumerate() {
+            face_view_projection.update(view_projection)?;
+            target.attach(
+                gl::TEXTURE_CUBE_MAP_POSITIVE_X + face as GLenum,
+                cube_map,
+                level,
+            )?;
+            cube.draw();
+        }
+        Ok(())
+    }
+}
+
+// Unindexed triangles on attribute location 0, e.g. a unit cube or the full screen triangle
+pub(crate) struct Triangles {
+    vao: GLObject,
+    // Only read through the VAO, but deleted along with it
+    #[allow(dead_code)]
+    buffer: GLObject,
+    count: GLsizei,
+}
+
+impl Triangles {
+    pub(crate) fn unit_cube() -> Self {
+        // Two triangles per face. Face culling is off whenever it's drawn, so winding doesn't matter
+        #[rustfmt::skip]
+        let vertices: [f32; 108] = [
+            -1.0,  1.0, -1.0,  -1.0, -1.0, -1.0,   1.0, -1.0, -1.0,
+             1.0, -1.0, -1.0,   1.0,  1.0, -1.0,  -1.0,  1.0, -1.0,
+            -1.0, -1.0,  1.0,  -1.0, -1.0, -1.0,  -1.0,  1.0, -1.0,
+            -1.0,  1.0, -1.0,  -1.0,  1.0,  1.0,  -1.0, -1.0,  1.0,
+             1.0, -1.0, -1.0,   1.0, -1.0,  1.0,   1.0,  1.0,  1.0,
+             1.0,  1.0,  1.0,   1.0,  1.0, -1.0,   1.0, -1.0, -1.0,
+            -1.0, -1.0,  1.0,  -1.0,  1.0,  1.0,   1.0,  1.0,  1.0,
+             1.0,  1.0,  1.0,   1.0, -1.0,  1.0,  -1.0, -1.0,  1.0,
+            -1.0,  1.0, -1.0,   1.0,  1.0, -1.0,   1.0,  1.0,  1.0,
+             1.0,  1.0,  1.0,  -1.0,  1.0,  1.0,  -1.0,  1.0, -1.0,
+            -1.0, -1.0, -1.0,  -1.0, -1.0,  1.0,   1.0, -1.0, -1.0,
+             1.0, -1.0, -1.0,  -1.0, -1.0,  1.0,   1.0, -1.0,  1.0,
+        ];
+        Triangles::new(&vertices)
+    }
+
+    pub(crate) fn new(vertices: &[f32]) -> Self {
+        let vao = GLObject::vertex_array();
+        let buffer = GLObject::buffer();
+        unsafe {
+            gl::BindVertexArray(vao.id());
+            gl::BindBuffer(gl::ARRAY_BUFFER, buffer.id());
+            gl::BufferData(
+                gl::ARRAY_BUFFER,
+                size_of_val(vertices) as GLsizeiptr,
+                vertices.as_ptr() as *const c_void,
+                gl::STATIC_DRAW,
+            );
+            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, 0, std::ptr::null());
+            gl::EnableVertexAttribArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            gl::BindVertexArray(0);
+        }
+        Triangles {
+            vao,
+            buffer,
+            count: (vertices.len() / 3) as GLsizei,
+        }
+    }
+
+    pub(crate) fn draw(&self) {
+        unsafe {
+            gl::BindVertexArray(self.vao.id());
+            gl::DrawArrays(gl::TRIANGLES, 0, self.count);
+            gl::BindVertexArray(0);
+        }
+    }
+}
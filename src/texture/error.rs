@@ -0,0 +1,97 @@
+use crate::shader::ShaderError;
+use crate::uniform::UniformError;
+use gl::types::GLenum;
+
+// Error type for Textures
+#[derive(Debug)]
+pub enum TextureError {
+    CouldNotLoadImage(String, image::ImageError),
+    ImageTooLarge(String),
+    MismatchedCubeMapFace(String),
+    FramebufferIncomplete(GLenum),
+    Shader(ShaderError),
+    Uniform(UniformError),
+}
+
+impl std::error::Error for TextureError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::CouldNotLoadImage(_, image_error) => Some(image_error),
+            _ => None,
+        }
+    }
+}
+impl std::fmt::Display for TextureError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TextureError::CouldNotLoadImage(path, error) => {
+                write!(
+                    f,
+                    "Failed to load the image at path: \"{}\"\n{}",
+                    path, error
+                )
+            }
+            TextureError::ImageTooLarge(path) => {
+                write!(
+                    f,
+                    "The image at path: \"{}\" is too large to fit in a texture.",
+                    path
+                )
+            }
+            TextureError::MismatchedCubeMapFace(path) => {
+                write!(
+                    f,
+                    "The cube map face at path: \"{}\" is not square, or not the same size as the other faces.",
+                    path
+                )
+            }
+            TextureError::FramebufferIncomplete(status) => {
+                write!(
+                    f,
+                    "The framebuffer used to render into a texture is incomplete (status: {:#x}).",
+                    status
+                )
+            }
+            TextureError::Shader(error) => {
+                write!(
+                    f,
+                    "Could not create a shader used to render into a texture.\n{}",
+                    error
+                )
+            }
+            TextureError::Uniform(error) => {
+                write!(
+                    f,
+                    "Could not set a uniform used to render into a texture.\n{}",
+                    error
+                )
+            }
+        }
+    }
+}
+
+impl From<ShaderError> for TextureError {
+    fn from(error: ShaderError) -> Self {
+        TextureError::Shader(error)
+    }
+}
+
+impl From<UniformError> for TextureError {
+    fn from(error: UniformError) -> Self {
+        TextureError::Uniform(error)
+    }
+}
+
+// Allows for painless casting into our crate's rollup error
+impl From<TextureError> for crate::GLError {
+    fn from(error: TextureError) -> Self {
+        crate::GLError::Texture(error)
+    }
+}
+
+// Allows for painless casting into our crate's rollup error
+impl From<TextureError> for crate::program::ProgramError {
+    fn from(error: TextureError) -> Self {
+        crate::program::ProgramError::Texture(error)
+    }
+}
@@ -0,0 +1,198 @@
+// Loading images from disk into OpenGL textures
+pub mod error;
+pub use error::TextureError;
+type Result<T> = std::result::Result<T, TextureError>;
+
+// Cube maps, and rendering into textures
+mod cube_map;
+pub(crate) use cube_map::{Pass, RenderTarget, Triangles};
+
+// OpenGL Types
+use crate::window::object::GLObject;
+use gl::types::*;
+
+use std::ffi::c_void;
+use std::path::Path;
+
+// How the texel values of an image should be interpreted when sampled. Colors authored by artists
+// (e.g. albedo maps) are stored in sRGB, while data (e.g. normal, roughness maps) is stored as-is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSpace {
+    Srgb,
+    Linear,
+}
+
+// Handle to an OpenGL texture
+#[derive(Debug)]
+pub struct Texture {
+    // Deleted once nothing refers to the texture anymore
+    object: GLObject,
+    // e.g. GL_TEXTURE_2D, GL_TEXTURE_CUBE_MAP
+    pub(crate) target: GLenum,
+    pub(crate) width: GLsizei,
+    pub(crate) height: GLsizei,
+}
+
+impl Texture {
+    // Loads an 8-bit per channel image (png, jpeg) as a mipmapped RGBA texture
+    pub fn from_file<P>(path: P, color_space: ColorSpace) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let image = open(path.as_ref())?.flipv().into_rgba8();
+        let (width, height) = dimensions(path.as_ref(), image.dimensions())?;
+        let internal_format = match color_space {
+            ColorSpace::Srgb => gl::SRGB8_ALPHA8,
+            ColorSpace::Linear => gl::RGBA8,
+        };
+
+        let texture = Texture::new(gl::TEXTURE_2D, width, height);
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, texture.id());
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                internal_format as GLint,
+                width,
+                height,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                image.as_ptr() as *const c_void,
+            );
+            gl::GenerateMipmap(gl::TEXTURE_2D);
+            set_parameters(gl::TEXTURE_2D, gl::LINEAR_MIPMAP_LINEAR, gl::REPEAT);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+        Ok(texture)
+    }
+
+    // Loads a high dynamic range image (.hdr) as a floating point RGB texture, e.g. for use as an
+    // equirectangular environment map. Other formats are converted to floating point.
+    pub fn from_hdr<P>(path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let image = open(path.as_ref())?.flipv().into_rgb32f();
+        let (width, height) = dimensions(path.as_ref(), image.dimensions())?;
+
+        let texture = Texture::new(gl::TEXTURE_2D, width, height);
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, texture.id());
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 4);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGB16F as GLint,
+                width,
+                height,
+                0,
+                gl::RGB,
+                gl::FLOAT,
+                image.as_ptr() as *const c_void,
+            );
+            set_parameters(gl::TEXTURE_2D, gl::LINEAR, gl::CLAMP_TO_EDGE);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+        Ok(texture)
+    }
+
+    // Creates an empty floating point cube map with `levels` mip levels, used as a render target
+    pub(crate) fn empty_cube(size: GLsizei, levels: GLsizei) -> Self {
+        let texture = Texture::new(gl::TEXTURE_CUBE_MAP, size, size);
+        let min_filter = match levels > 1 {
+            true => gl::LINEAR_MIPMAP_LINEAR,
+            false => gl::LINEAR,
+        };
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, texture.id());
+            gl::TexStorage2D(gl::TEXTURE_CUBE_MAP, levels, gl::RGB16F, size, size);
+            set_parameters(gl::TEXTURE_CUBE_MAP, min_filter, gl::CLAMP_TO_EDGE);
+            gl::TexParameteri(
+                gl::TEXTURE_CUBE_MAP,
+                gl::TEXTURE_WRAP_R,
+                gl::CLAMP_TO_EDGE as GLint,
+            );
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, 0);
+        }
+        texture
+    }
+
+    // Creates an empty two channel floating point texture, used as a render target
+    pub(crate) fn empty_rg(width: GLsizei, height: GLsizei) -> Self {
+        let texture = Texture::new(gl::TEXTURE_2D, width, height);
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, texture.id());
+            gl::TexStorage2D(gl::TEXTURE_2D, 1, gl::RG16F, width, height);
+            set_parameters(gl::TEXTURE_2D, gl::LINEAR, gl::CLAMP_TO_EDGE);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+        texture
+    }
+
+    // Creates an empty texture in any sized internal format (colour or depth), used as a
+    // framebuffer attachment
+    pub(crate) fn empty_2d(width: GLsizei, height: GLsizei, internal_format: GLenum) -> Self {
+        let texture = Texture::new(gl::TEXTURE_2D, width, height);
+        unsafe {
+            gl::BindTexture(gl::TEXTURE_2D, texture.id());
+            gl::TexStorage2D(gl::TEXTURE_2D, 1, internal_format, width, height);
+            set_parameters(gl::TEXTURE_2D, gl::LINEAR, gl::CLAMP_TO_EDGE);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+        }
+        texture
+    }
+
+    // Binds the texture to a texture unit so samplers set to `unit` read from it
+    pub fn bind(&self, unit: GLuint) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(self.target, self.id());
+            gl::ActiveTexture(gl::TEXTURE0);
+        }
+    }
+
+    pub(crate) fn id(&self) -> GLuint {
+        self.object.id()
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width as u32
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height as u32
+    }
+
+    fn new(target: GLenum, width: GLsizei, height: GLsizei) -> Self {
+        Texture {
+            object: GLObject::texture(),
+            target,
+            width,
+            height,
+        }
+    }
+}
+
+// Decodes an image file, in whatever format its extension says it is
+fn open(path: &Path) -> Result<image::DynamicImage> {
+    image::open(path)
+        .map_err(|error| TextureError::CouldNotLoadImage(path.display().to_string(), error))
+}
+
+// Converts image dimensions into the signed sizes OpenGL expects
+fn dimensions(path: &Path, (width, height): (u32, u32)) -> Result<(GLsizei, GLsizei)> {
+    let too_large = || TextureError::ImageTooLarge(path.display().to_string());
+    let width = width.try_into().map_err(|_| too_large())?;
+    let height = height.try_into().map_err(|_| too_large())?;
+    Ok((width, height))
+}
+
+// Filtering and wrapping for the currently bound texture
+unsafe fn set_parameters(target: GLenum, min_filter: GLenum, wrap: GLenum) {
+    gl::TexParameteri(target, gl::TEXTURE_MIN_FILTER, min_filter as GLint);
+    gl::TexParameteri(target, gl::TEXTURE_MAG_FILTER, gl::LINEAR as GLint);
+    gl::TexParameteri(target, gl::TEXTURE_WRAP_S, wrap as GLint);
+    gl::TexParameteri(target, gl::TEXTURE_WRAP_T, wrap as GLint);
+}
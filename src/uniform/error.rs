@@ -1,3 +1,4 @@
+use crate::program::reflection::GlslType;
 use crate::GLUtilityError;
 
 // Error type for Uniforms
@@ -7,6 +8,8 @@ pub enum UniformError {
     MatrixConversion((u8, u8)),
     SettingUniformValue(String),
     CouldNotFindUniformIndex(String),
+    // (Uniform Name, Declared Type, Rust Type)
+    TypeMismatch(String, GlslType, &'static str),
     Other(GLUtilityError),
 }
 
@@ -33,6 +36,13 @@ impl std::fmt::Display for UniformError {
             UniformError::CouldNotFindUniformIndex(name) => {
                 write!(f, "Failed to find the location for '{}'.\n", name)
             }
+            UniformError::TypeMismatch(name, glsl_type, rust_type) => {
+                write!(
+                    f,
+                    "Uniform '{}' is declared as a '{}', which can't be set with a '{}'.",
+                    name, glsl_type, rust_type
+                )
+            }
             UniformError::Other(error) => {
                 write!(f, "{}", error)
             }
@@ -0,0 +1,38 @@
+// GLSL's non-square matrices, which ultraviolet doesn't have. Like ultraviolet's matrices they're
+// stored column-major: `MatrixCxR` has C columns of R rows, the same as GLSL's `matCxR`.
+// (Not to be confused with ultraviolet's `Mat3x4` et al. which are 4 Mat3s for SIMD)
+macro_rules! matrix {
+    ($name:ident, $scalar:ty, $columns:literal, $rows:literal) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Default)]
+        #[repr(C)]
+        pub struct $name {
+            pub cols: [[$scalar; $rows]; $columns],
+        }
+
+        impl $name {
+            pub fn new(cols: [[$scalar; $rows]; $columns]) -> Self {
+                $name { cols }
+            }
+        }
+
+        impl From<[[$scalar; $rows]; $columns]> for $name {
+            fn from(cols: [[$scalar; $rows]; $columns]) -> Self {
+                $name { cols }
+            }
+        }
+    };
+}
+
+matrix!(Matrix2x3, f32, 2, 3);
+matrix!(Matrix2x4, f32, 2, 4);
+matrix!(Matrix3x2, f32, 3, 2);
+matrix!(Matrix3x4, f32, 3, 4);
+matrix!(Matrix4x2, f32, 4, 2);
+matrix!(Matrix4x3, f32, 4, 3);
+
+matrix!(DMatrix2x3, f64, 2, 3);
+matrix!(DMatrix2x4, f64, 2, 4);
+matrix!(DMatrix3x2, f64, 3, 2);
+matrix!(DMatrix3x4, f64, 3, 4);
+matrix!(DMatrix4x2, f64, 4, 2);
+matrix!(DMatrix4x3, f64, 4, 3);
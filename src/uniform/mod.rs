@@ -1,21 +1,25 @@
 // Custom Error Type
 pub mod error;
 pub use error::UniformError;
+// GLSL's non-square matrices
+pub mod matrix;
+pub use matrix::*;
+// Trait UniformValue -> OpenGL glUniform*() Mapping
+mod values;
+pub use values::{ImageUnit, TextureUnit};
 type Result<T> = std::result::Result<T, UniformError>;
 
 // OpenGL Types
 use gl::types::*;
-// Used for defining arrays of floats, vectors, and matrices
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
 use std::rc::Rc;
-use std::vec::Vec;
+
+// Checking values against the types the shaders declare
+use crate::program::reflection::{self, GlslType};
 
 // Used for 'MagicUniform' values to easily set and test for
 use bitflags::bitflags;
 
-// Linear Algebra Crate -> Defining the Uniform and From<> traits on its types
-use ultraviolet::{Mat3, Mat4, Vec2, Vec3, Vec4};
-
 // Flags that are used to set 'magic' uniforms such as 'time' or 'mouse position'
 // During the render loop, the program will check which flags are set
 // and update the corresponding uniform values appropriately
@@ -32,12 +36,77 @@ bitflags! {
 // Essentially dynamic dispatch to the corrent OpenGL function
 pub trait UniformValue {
     fn initialize(&self, location: GLint) -> ();
+    // Whether a uniform declared as `glsl_type` can be set with this type of value
+    fn accepts(glsl_type: GlslType) -> bool
+    where
+        Self: Sized;
+}
+
+// Implemented by values that can also be passed as (elements of) GLSL arrays, e.g. `uniform vec3
+// points[4]` can be set with a `[Vec3; 4]`, a `Vec<Vec3>` or a `&[Vec3]`
+pub trait UniformArrayElement: UniformValue + Sized {
+    fn initialize_array(values: &[Self], location: GLint);
+}
+
+// Implemented, with `#[derive(UniformStruct)]`, by structs mirroring a GLSL `struct` so that a
+// uniform of that type can be set field by field
+pub trait UniformStruct {
+    // Sets `name.field` for each field, returning how many of them the program uses
+    fn set_fields(&self, program_id: GLuint, name: &str) -> Result<usize>;
+}
+pub use cs6600_derive::UniformStruct;
+
+// Used by derived UniformStructs, returns false if the program doesn't use the field
+#[doc(hidden)]
+pub fn set_field<Value>(program_id: GLuint, name: &str, field: &str, value: &Value) -> Result<bool>
+where
+    Value: UniformValue,
+{
+    let full_name = format!("{}.{}", name, field);
+    let c_name = CString::new(full_name.as_str()).map_err(|_| {
+        UniformError::Other(crate::GLUtilityError::FailedToConvertToCString(
+            full_name.clone(),
+        ))
+    })?;
+    match UniformHandle::<Value>::new(program_id, &c_name) {
+        Ok(handle) => {
+            handle.set(value);
+            Ok(true)
+        }
+        Err(UniformError::CouldNotFindUniformIndex(_)) => Ok(false),
+        Err(error) => Err(error),
+    }
 }
-// Implemented by attached Uniform structs that call ".set()" on their inner values, passing in the
-// uniform's location
+
+// Implemented by attached Uniform structs, so a GLProgram can keep uniforms of different types in
+// one map. Values are set through `update()` on `dyn UpdateUniform`, which checks their type.
 pub trait UpdateUniform {
-    // Updates the uniform at `location` to the value of self
-    fn update(&self, value: &dyn UniformValue) -> ();
+    fn name(&self) -> &str;
+    // The type the shaders declare the uniform as, None if the driver didn't report one
+    fn glsl_type(&self) -> Option<GlslType>;
+    // Sets the uniform to a value `update()` has already checked against `glsl_type()`
+    #[doc(hidden)]
+    fn update_unchecked(&self, value: &dyn UniformValue);
+}
+
+impl dyn UpdateUniform {
+    // Sets the uniform, failing if its GLSL type can't be set with a Value
+    pub fn update<Value>(&self, value: &Value) -> Result<()>
+    where
+        Value: UniformValue,
+    {
+        if let Some(glsl_type) = self.glsl_type() {
+            if !Value::accepts(glsl_type) {
+                return Err(UniformError::TypeMismatch(
+                    self.name().to_string(),
+                    glsl_type,
+                    std::any::type_name::<Value>(),
+                ));
+            }
+        }
+        self.update_unchecked(value);
+        Ok(())
+    }
 }
 
 pub struct Uniform<'a, Value>
@@ -69,30 +138,10 @@ where
     // Looks up the uniform index using `name` then initializes that location with the data
     // contained in `value` and finally returns the attached version of the struct
     pub(crate) fn attach(self, program_id: GLuint) -> Result<Rc<dyn UpdateUniform>> {
-        unsafe {
-            gl::UseProgram(program_id);
-        }
-        let name = self.name.clone();
-
-        // Perform the lookup ;::; -1 is OpenGL's operation failed error code
-        let location: GLint;
-        unsafe {
-            location = gl::GetUniformLocation(program_id, name.into_raw());
-        }
-        if location == -1 {
-            Err(UniformError::CouldNotFindUniformIndex(
-                self.name.to_string_lossy().to_string(),
-            ))
-        } else {
-            // Buffer the initial data to the uniform
-            self.value.initialize(location);
-            let attached = UniformHandle {
-                location,
-                value: std::marker::PhantomData::<Value>,
-            };
-            let attached: Rc<dyn UpdateUniform> = Rc::from(attached);
-            Ok(attached)
-        }
+        let handle = UniformHandle::<Value>::new(program_id, &self.name)?;
+        handle.set(self.value);
+        let attached: Rc<dyn UpdateUniform> = Rc::from(handle);
+        Ok(attached)
     }
 
     // Convenience function that transforms the name from CString to Rc<str> to use as a key in a
@@ -106,281 +155,125 @@ where
 
 // When a uniform value is attached to a GLProgram, it is transformed into this. It loses its
 // `name` which becomes its key in the GLProgram's hashmap of Uniform values. It gains a definite
-// location within the program.
+// location within the program, and is checked against the type the shaders declare it as.
 // I really wish there was a good way to make this Uniform<Attached>
 pub struct UniformHandle<Value>
 where
     Value: UniformValue,
 {
+    // The GLProgram the uniform belongs to
+    program_id: GLuint,
+    name: String,
     // Which GLProgram Uniform Index this is bound to
     location: GLint,
+    // What the shaders declare it as, which Value was checked against
+    glsl_type: Option<GlslType>,
     // The associated uniform type
     value: std::marker::PhantomData<Value>,
 }
 
-// The GLProgram's map is abstracted over the dynamic type that implements this trait
-impl<Value> UpdateUniform for UniformHandle<Value>
+impl<Value> UniformHandle<Value>
 where
     Value: UniformValue,
 {
-    // Simple wrapper around `.initialize()`
-    fn update(&self, new_value: &dyn UniformValue) -> () {
-        new_value.initialize(self.location)
-    }
-}
-
-///////////////////////////////////////////////////////
-// Trait UniformValue -> OpenGL glUniform*() Mapping //
-///////////////////////////////////////////////////////
-
-//////////////
-// Matrices //
-//////////////
-impl UniformValue for Mat4 {
-    fn initialize(&self, location: GLint) -> () {
-        let ptr = self.cols.as_ptr() as *const GLfloat;
-        unsafe {
-            gl::UniformMatrix4fv(location, 1, gl::FALSE, ptr);
-        }
-    }
-}
-
-impl UniformValue for Vec<Mat4> {
-    fn initialize(&self, location: GLint) -> () {
-        let ptr = self.as_ptr() as *const GLfloat;
-        let length = self.len() as GLint;
-        unsafe {
-            gl::UniformMatrix4fv(location, length, gl::FALSE, ptr);
-        }
-    }
-}
-
-impl UniformValue for [Mat4] {
-    fn initialize(&self, location: GLint) -> () {
-        let ptr = self.as_ptr() as *const GLfloat;
-        let length = self.len() as GLint;
-        unsafe {
-            gl::UniformMatrix4fv(location, length, gl::FALSE, ptr);
-        }
-    }
-}
-
-impl UniformValue for Mat3 {
-    fn initialize(&self, location: GLint) -> () {
-        let ptr = self.cols.as_ptr() as *const GLfloat;
-        unsafe {
-            gl::UniformMatrix3fv(location, 1, gl::FALSE, ptr);
-        }
-    }
-}
-
-impl UniformValue for Vec<Mat3> {
-    fn initialize(&self, location: GLint) -> () {
-        let ptr = self.as_ptr() as *const GLfloat;
-        let length = self.len() as GLint;
-        unsafe {
-            gl::UniformMatrix3fv(location, length, gl::FALSE, ptr);
-        }
-    }
-}
-
-impl UniformValue for [Mat3] {
-    fn initialize(&self, location: GLint) -> () {
-        let ptr = self.as_ptr() as *const GLfloat;
-        let length = self.len() as GLint;
-        unsafe {
-            gl::UniformMatrix3fv(location, length, gl::FALSE, ptr);
-        }
-    }
-}
-
-/////////////
-// VECTORS //
-/////////////
-impl UniformValue for Vec4 {
-    fn initialize(&self, location: GLint) -> () {
-        unsafe {
-            gl::Uniform4f(location, self.x, self.y, self.z, self.w);
-        }
-    }
-}
-
-impl UniformValue for Vec<Vec4> {
-    fn initialize(&self, location: GLint) -> () {
-        let data = self.as_ptr() as *const GLfloat;
-        let count = self.len() as GLint;
-        unsafe {
-            gl::Uniform4fv(location, count, data);
-        }
-    }
-}
-
-impl UniformValue for (f32, f32, f32, f32) {
-    fn initialize(&self, location: GLint) -> () {
-        unsafe {
-            gl::Uniform4f(location, self.0, self.1, self.2, self.3);
-        }
-    }
-}
-
-impl UniformValue for (GLuint, GLuint, GLuint, GLuint) {
-    fn initialize(&self, location: GLint) -> () {
-        unsafe {
-            gl::Uniform4ui(location, self.0, self.1, self.2, self.3);
-        }
-    }
-}
-
-impl UniformValue for (GLint, GLint, GLint, GLint) {
-    fn initialize(&self, location: GLint) -> () {
-        unsafe {
-            gl::Uniform4i(location, self.0, self.1, self.2, self.3);
-        }
-    }
-}
-
-impl UniformValue for Vec3 {
-    fn initialize(&self, location: GLint) -> () {
-        unsafe {
-            gl::Uniform3f(location, self.x, self.y, self.z);
-        }
-    }
-}
-
-impl UniformValue for Vec<Vec3> {
-    fn initialize(&self, location: GLint) -> () {
-        let data = self.as_ptr() as *const GLfloat;
-        let count = self.len() as GLint;
-        unsafe {
-            gl::Uniform3fv(location, count, data);
-        }
-    }
-}
-
-impl UniformValue for (f32, f32, f32) {
-    fn initialize(&self, location: GLint) -> () {
-        unsafe {
-            gl::Uniform3f(location, self.0, self.1, self.2);
-        }
-    }
-}
-
-impl UniformValue for (GLuint, GLuint, GLuint) {
-    fn initialize(&self, location: GLint) -> () {
-        unsafe {
-            gl::Uniform3ui(location, self.0, self.1, self.2);
-        }
-    }
-}
-
-impl UniformValue for (GLint, GLint, GLint) {
-    fn initialize(&self, location: GLint) -> () {
-        unsafe {
-            gl::Uniform3i(location, self.0, self.1, self.2);
-        }
-    }
-}
+    // Looks up the uniform, failing if it doesn't exist or if its GLSL type can't be set with Value
+    pub(crate) fn new(program_id: GLuint, name: &CStr) -> Result<Self> {
+        let name_string = || name.to_string_lossy().to_string();
 
-impl UniformValue for Vec2 {
-    fn initialize(&self, location: GLint) -> () {
-        unsafe {
-            gl::Uniform2f(location, self.x, self.y);
+        // Perform the lookup ;::; -1 is OpenGL's operation failed error code
+        let location = unsafe { gl::GetUniformLocation(program_id, name.as_ptr()) };
+        if location == -1 {
+            return Err(UniformError::CouldNotFindUniformIndex(name_string()));
         }
-    }
-}
 
-impl UniformValue for Vec<Vec2> {
-    fn initialize(&self, location: GLint) -> () {
-        let data = self.as_ptr() as *const GLfloat;
-        let count = self.len() as GLint;
-        unsafe {
-            gl::Uniform2fv(location, count, data);
+        let glsl_type = reflection::uniform_type(program_id, name);
+        if let Some(glsl_type) = glsl_type {
+            if !Value::accepts(glsl_type) {
+                return Err(UniformError::TypeMismatch(
+                    name_string(),
+                    glsl_type,
+                    std::any::type_name::<Value>(),
+                ));
+            }
         }
-    }
-}
 
-impl UniformValue for (f32, f32) {
-    fn initialize(&self, location: GLint) -> () {
-        unsafe {
-            gl::Uniform2f(location, self.0, self.1);
-        }
+        Ok(UniformHandle {
+            program_id,
+            name: name_string(),
+            location,
+            glsl_type,
+            value: std::marker::PhantomData::<Value>,
+        })
     }
-}
 
-impl UniformValue for (GLuint, GLuint) {
-    fn initialize(&self, location: GLint) -> () {
+    // Sets the uniform, which can only be done with the type it was checked against
+    pub fn set(&self, value: &Value) {
         unsafe {
-            gl::Uniform2ui(location, self.0, self.1);
+            gl::UseProgram(self.program_id);
         }
+        value.initialize(self.location)
     }
-}
 
-impl UniformValue for (GLint, GLint) {
-    fn initialize(&self, location: GLint) -> () {
-        unsafe {
-            gl::Uniform2i(location, self.0, self.1);
-        }
-    }
-}
-
-////////////
-// FLOATS //
-////////////
-
-impl UniformValue for f32 {
-    fn initialize(&self, location: GLint) -> () {
-        unsafe {
-            gl::Uniform1f(location, *self);
-        }
+    pub fn location(&self) -> GLint {
+        self.location
     }
-}
 
-impl UniformValue for Vec<f32> {
-    fn initialize(&self, location: GLint) -> () {
-        let data = self.as_ptr() as *const GLfloat;
-        let count = self.len() as GLint;
-        unsafe {
-            gl::Uniform1fv(location, count, data);
-        }
+    pub fn glsl_type(&self) -> Option<GlslType> {
+        self.glsl_type
     }
 }
 
-//////////////
-// INTEGERS //
-//////////////
-
-impl UniformValue for GLuint {
-    fn initialize(&self, location: GLint) -> () {
-        unsafe {
-            gl::Uniform1ui(location, *self);
-        }
+// The GLProgram's map is abstracted over the dynamic type that implements this trait
+impl<Value> UpdateUniform for UniformHandle<Value>
+where
+    Value: UniformValue,
+{
+    fn name(&self) -> &str {
+        &self.name
     }
-}
 
-impl UniformValue for Vec<GLuint> {
-    fn initialize(&self, location: GLint) -> () {
-        let data = self.as_ptr() as *const GLuint;
-        let count = self.len() as GLint;
-        unsafe {
-            gl::Uniform1uiv(location, count, data);
-        }
+    fn glsl_type(&self) -> Option<GlslType> {
+        self.glsl_type
     }
-}
 
-impl UniformValue for GLint {
-    fn initialize(&self, location: GLint) -> () {
+    // Like `.set()`, for values whose type was checked by the caller
+    fn update_unchecked(&self, new_value: &dyn UniformValue) {
         unsafe {
-            gl::Uniform1i(location, *self);
+            gl::UseProgram(self.program_id);
         }
+        new_value.initialize(self.location)
     }
 }
 
-impl UniformValue for Vec<GLint> {
-    fn initialize(&self, location: GLint) -> () {
-        let data = self.as_ptr() as *const GLint;
-        let count = self.len() as GLint;
-        unsafe {
-            gl::Uniform1iv(location, count, data);
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ultraviolet::{Mat4, Vec3};
+
+    #[test]
+    fn values_are_checked_against_declared_types() {
+        assert!(!Mat4::accepts(GlslType(gl::FLOAT)));
+        assert!(Mat4::accepts(GlslType(gl::FLOAT_MAT4)));
+        assert!(f32::accepts(GlslType(gl::BOOL)));
+        assert!(!f32::accepts(GlslType(gl::FLOAT_MAT4)));
+        assert!(!Vec3::accepts(GlslType(gl::FLOAT_VEC4)));
+
+        // The mismatch is caught before any GL call
+        let shininess: Rc<dyn UpdateUniform> = Rc::new(UniformHandle::<f32> {
+            program_id: 0,
+            name: "material_shininess".to_string(),
+            location: 0,
+            glsl_type: Some(GlslType(gl::FLOAT)),
+            value: std::marker::PhantomData,
+        });
+        let Err(UniformError::TypeMismatch(name, glsl_type, _)) =
+            shininess.update(&Mat4::identity())
+        else {
+            panic!("a mat4 was sent to a float");
+        };
+        assert_eq!(
+            (name.as_str(), glsl_type),
+            ("material_shininess", GlslType(gl::FLOAT))
+        );
     }
 }
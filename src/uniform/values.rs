@@ -0,0 +1,301 @@
+///////////////////////////////////////////////////////
+// Trait UniformValue -> OpenGL glUniform*() Mapping //
+///////////////////////////////////////////////////////
+use super::matrix::*;
+use super::{UniformArrayElement, UniformValue};
+use crate::program::reflection::GlslType;
+
+// OpenGL Types
+use gl::types::*;
+
+// Linear Algebra Crate -> Defining the Uniform traits on its types
+use ultraviolet::{
+    DMat2, DMat3, DMat4, DVec2, DVec3, DVec4, IVec2, IVec3, IVec4, Mat2, Mat3, Mat4, UVec2, UVec3,
+    UVec4, Vec2, Vec3, Vec4,
+};
+
+// The texture unit a `sampler*` reads from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct TextureUnit(pub GLint);
+
+// The image unit an `image*` reads from, and writes to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(transparent)]
+pub struct ImageUnit(pub GLint);
+
+////////////
+// ARRAYS //
+////////////
+impl<Element> UniformValue for [Element]
+where
+    Element: UniformArrayElement,
+{
+    fn initialize(&self, location: GLint) {
+        Element::initialize_array(self, location)
+    }
+}
+
+impl<Element> UniformValue for Vec<Element>
+where
+    Element: UniformArrayElement,
+{
+    fn initialize(&self, location: GLint) {
+        Element::initialize_array(self, location)
+    }
+
+    fn accepts(glsl_type: GlslType) -> bool {
+        Element::accepts(glsl_type)
+    }
+}
+
+impl<Element, const N: usize> UniformValue for [Element; N]
+where
+    Element: UniformArrayElement,
+{
+    fn initialize(&self, location: GLint) {
+        Element::initialize_array(self, location)
+    }
+
+    fn accepts(glsl_type: GlslType) -> bool {
+        Element::accepts(glsl_type)
+    }
+}
+
+// Types laid out in memory exactly as OpenGL expects them, so a slice of them can be passed as is
+// to the `glUniform*v()` function
+macro_rules! contiguous {
+    ($type:ty, $component:ty, $uniform:ident, $($glsl:ident)|+) => {
+        contiguous!($type, $component, $uniform, |glsl_type| matches!(glsl_type.0, $(gl::$glsl)|+));
+    };
+    ($type:ty, $component:ty, $uniform:ident, |$glsl_type:ident| $accepts:expr) => {
+        impl UniformValue for $type {
+            fn initialize(&self, location: GLint) {
+                <$type>::initialize_array(std::slice::from_ref(self), location)
+            }
+
+            fn accepts($glsl_type: GlslType) -> bool {
+                $accepts
+            }
+        }
+
+        impl UniformArrayElement for $type {
+            fn initialize_array(values: &[Self], location: GLint) {
+                let data = values.as_ptr() as *const $component;
+                let count = values.len() as GLsizei;
+                unsafe {
+                    gl::$uniform(location, count, data);
+                }
+            }
+        }
+    };
+}
+
+// Same as above, but for the `glUniformMatrix*v()` functions
+macro_rules! matrix {
+    ($type:ty, $component:ty, $uniform:ident, $glsl:ident) => {
+        impl UniformValue for $type {
+            fn initialize(&self, location: GLint) {
+                <$type>::initialize_array(std::slice::from_ref(self), location)
+            }
+
+            fn accepts(glsl_type: GlslType) -> bool {
+                glsl_type.0 == gl::$glsl
+            }
+        }
+
+        impl UniformArrayElement for $type {
+            fn initialize_array(values: &[Self], location: GLint) {
+                let data = values.as_ptr() as *const $component;
+                let count = values.len() as GLsizei;
+                unsafe {
+                    gl::$uniform(location, count, gl::FALSE, data);
+                }
+            }
+        }
+    };
+}
+
+// Types that are converted to one of the types above first, e.g. tuples and bools
+// Unless told otherwise they can set the same GLSL types as the type they're converted to
+macro_rules! converted {
+    ($type:ty => $into:ty, |$value:ident| $convert:expr) => {
+        converted!($type => $into, |$value| $convert, |glsl_type| <$into>::accepts(glsl_type));
+    };
+    ($type:ty => $into:ty, |$value:ident| $convert:expr, $($glsl:ident)|+) => {
+        converted!($type => $into, |$value| $convert, |glsl_type| {
+            matches!(glsl_type.0, $(gl::$glsl)|+)
+        });
+    };
+    ($type:ty => $into:ty, |$value:ident| $convert:expr, |$glsl_type:ident| $accepts:expr) => {
+        impl UniformValue for $type {
+            fn initialize(&self, location: GLint) {
+                let $value = self;
+                let converted: $into = $convert;
+                converted.initialize(location)
+            }
+
+            fn accepts($glsl_type: GlslType) -> bool {
+                $accepts
+            }
+        }
+
+        impl UniformArrayElement for $type {
+            fn initialize_array(values: &[Self], location: GLint) {
+                let converted: Vec<$into> = values.iter().map(|$value| $convert).collect();
+                <$into>::initialize_array(&converted, location)
+            }
+        }
+    };
+}
+
+//////////////
+// MATRICES //
+//////////////
+matrix!(Mat2, GLfloat, UniformMatrix2fv, FLOAT_MAT2);
+matrix!(Mat3, GLfloat, UniformMatrix3fv, FLOAT_MAT3);
+matrix!(Mat4, GLfloat, UniformMatrix4fv, FLOAT_MAT4);
+matrix!(Matrix2x3, GLfloat, UniformMatrix2x3fv, FLOAT_MAT2x3);
+matrix!(Matrix2x4, GLfloat, UniformMatrix2x4fv, FLOAT_MAT2x4);
+matrix!(Matrix3x2, GLfloat, UniformMatrix3x2fv, FLOAT_MAT3x2);
+matrix!(Matrix3x4, GLfloat, UniformMatrix3x4fv, FLOAT_MAT3x4);
+matrix!(Matrix4x2, GLfloat, UniformMatrix4x2fv, FLOAT_MAT4x2);
+matrix!(Matrix4x3, GLfloat, UniformMatrix4x3fv, FLOAT_MAT4x3);
+
+matrix!(DMat2, GLdouble, UniformMatrix2dv, DOUBLE_MAT2);
+matrix!(DMat3, GLdouble, UniformMatrix3dv, DOUBLE_MAT3);
+matrix!(DMat4, GLdouble, UniformMatrix4dv, DOUBLE_MAT4);
+matrix!(DMatrix2x3, GLdouble, UniformMatrix2x3dv, DOUBLE_MAT2x3);
+matrix!(DMatrix2x4, GLdouble, UniformMatrix2x4dv, DOUBLE_MAT2x4);
+matrix!(DMatrix3x2, GLdouble, UniformMatrix3x2dv, DOUBLE_MAT3x2);
+matrix!(DMatrix3x4, GLdouble, UniformMatrix3x4dv, DOUBLE_MAT3x4);
+matrix!(DMatrix4x2, GLdouble, UniformMatrix4x2dv, DOUBLE_MAT4x2);
+matrix!(DMatrix4x3, GLdouble, UniformMatrix4x3dv, DOUBLE_MAT4x3);
+
+/////////////
+// VECTORS //
+/////////////
+// Booleans can be set with floats, ints or uints
+contiguous!(Vec4, GLfloat, Uniform4fv, FLOAT_VEC4 | BOOL_VEC4);
+contiguous!(Vec3, GLfloat, Uniform3fv, FLOAT_VEC3 | BOOL_VEC3);
+contiguous!(Vec2, GLfloat, Uniform2fv, FLOAT_VEC2 | BOOL_VEC2);
+
+contiguous!(DVec4, GLdouble, Uniform4dv, DOUBLE_VEC4);
+contiguous!(DVec3, GLdouble, Uniform3dv, DOUBLE_VEC3);
+contiguous!(DVec2, GLdouble, Uniform2dv, DOUBLE_VEC2);
+
+contiguous!(IVec4, GLint, Uniform4iv, INT_VEC4 | BOOL_VEC4);
+contiguous!(IVec3, GLint, Uniform3iv, INT_VEC3 | BOOL_VEC3);
+contiguous!(IVec2, GLint, Uniform2iv, INT_VEC2 | BOOL_VEC2);
+
+contiguous!(UVec4, GLuint, Uniform4uiv, UNSIGNED_INT_VEC4 | BOOL_VEC4);
+contiguous!(UVec3, GLuint, Uniform3uiv, UNSIGNED_INT_VEC3 | BOOL_VEC3);
+contiguous!(UVec2, GLuint, Uniform2uiv, UNSIGNED_INT_VEC2 | BOOL_VEC2);
+
+converted!((f32, f32, f32, f32) => Vec4, |v| Vec4::new(v.0, v.1, v.2, v.3));
+converted!((f32, f32, f32) => Vec3, |v| Vec3::new(v.0, v.1, v.2));
+converted!((f32, f32) => Vec2, |v| Vec2::new(v.0, v.1));
+
+converted!((f64, f64, f64, f64) => DVec4, |v| DVec4::new(v.0, v.1, v.2, v.3));
+converted!((f64, f64, f64) => DVec3, |v| DVec3::new(v.0, v.1, v.2));
+converted!((f64, f64) => DVec2, |v| DVec2::new(v.0, v.1));
+
+converted!((GLint, GLint, GLint, GLint) => IVec4, |v| IVec4::new(v.0, v.1, v.2, v.3));
+converted!((GLint, GLint, GLint) => IVec3, |v| IVec3::new(v.0, v.1, v.2));
+converted!((GLint, GLint) => IVec2, |v| IVec2::new(v.0, v.1));
+
+converted!((GLuint, GLuint, GLuint, GLuint) => UVec4, |v| UVec4::new(v.0, v.1, v.2, v.3));
+converted!((GLuint, GLuint, GLuint) => UVec3, |v| UVec3::new(v.0, v.1, v.2));
+converted!((GLuint, GLuint) => UVec2, |v| UVec2::new(v.0, v.1));
+
+// bvecs are passed as ivecs
+converted!((bool, bool, bool, bool) => IVec4,
+    |v| IVec4::new(v.0 as GLint, v.1 as GLint, v.2 as GLint, v.3 as GLint),
+    BOOL_VEC4);
+converted!((bool, bool, bool) => IVec3,
+    |v| IVec3::new(v.0 as GLint, v.1 as GLint, v.2 as GLint),
+    BOOL_VEC3);
+converted!((bool, bool) => IVec2, |v| IVec2::new(v.0 as GLint, v.1 as GLint), BOOL_VEC2);
+
+/////////////
+// SCALARS //
+/////////////
+contiguous!(GLfloat, GLfloat, Uniform1fv, FLOAT | BOOL);
+contiguous!(GLdouble, GLdouble, Uniform1dv, DOUBLE);
+contiguous!(GLuint, GLuint, Uniform1uiv, UNSIGNED_INT | BOOL);
+// Samplers and images can also be set to the unit they read from with a plain int
+contiguous!(GLint, GLint, Uniform1iv, |glsl_type| {
+    matches!(glsl_type.0, gl::INT | gl::BOOL) || glsl_type.is_opaque()
+});
+converted!(bool => GLint, |v| *v as GLint, BOOL);
+
+///////////
+// UNITS //
+///////////
+contiguous!(TextureUnit, GLint, Uniform1iv, |glsl_type| glsl_type
+    .is_sampler());
+contiguous!(ImageUnit, GLint, Uniform1iv, |glsl_type| glsl_type
+    .is_image());
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accepts<Value: UniformValue>(glsl_type: GLenum) -> bool {
+        Value::accepts(GlslType(glsl_type))
+    }
+
+    #[test]
+    fn bool_vectors_take_int_uint_and_float_vectors() {
+        assert!(accepts::<Vec4>(gl::BOOL_VEC4));
+        assert!(accepts::<IVec4>(gl::BOOL_VEC4));
+        assert!(accepts::<UVec4>(gl::BOOL_VEC4));
+        assert!(accepts::<(bool, bool, bool, bool)>(gl::BOOL_VEC4));
+        assert!(accepts::<Vec2>(gl::BOOL_VEC2));
+        assert!(accepts::<(GLint, GLint, GLint)>(gl::BOOL_VEC3));
+        // Only with the same number of components, and only when the uniform is a bvec
+        assert!(!accepts::<Vec3>(gl::BOOL_VEC4));
+        assert!(!accepts::<DVec4>(gl::BOOL_VEC4));
+        assert!(!accepts::<IVec4>(gl::FLOAT_VEC4));
+        assert!(!accepts::<(bool, bool, bool, bool)>(gl::INT_VEC4));
+    }
+
+    #[test]
+    fn ints_take_opaque_types() {
+        assert!(accepts::<GLint>(gl::INT));
+        assert!(accepts::<GLint>(gl::BOOL));
+        assert!(accepts::<GLint>(gl::SAMPLER_2D));
+        assert!(accepts::<GLint>(gl::SAMPLER_CUBE_SHADOW));
+        assert!(accepts::<GLint>(gl::IMAGE_2D));
+        assert!(!accepts::<GLint>(gl::UNSIGNED_INT));
+        assert!(!accepts::<GLint>(gl::FLOAT));
+        // Unsigned ints and floats are never opaque
+        assert!(!accepts::<GLuint>(gl::SAMPLER_2D));
+        assert!(!accepts::<GLfloat>(gl::IMAGE_2D));
+    }
+
+    #[test]
+    fn units_take_only_their_opaque_types() {
+        assert!(accepts::<TextureUnit>(gl::SAMPLER_2D));
+        assert!(accepts::<TextureUnit>(gl::SAMPLER_CUBE));
+        assert!(accepts::<TextureUnit>(gl::SAMPLER_2D_SHADOW));
+        assert!(!accepts::<TextureUnit>(gl::IMAGE_2D));
+        assert!(!accepts::<TextureUnit>(gl::INT));
+
+        assert!(accepts::<ImageUnit>(gl::IMAGE_2D));
+        assert!(accepts::<ImageUnit>(gl::IMAGE_CUBE));
+        assert!(!accepts::<ImageUnit>(gl::SAMPLER_2D));
+        assert!(!accepts::<ImageUnit>(gl::INT));
+    }
+
+    #[test]
+    fn arrays_take_what_their_element_does() {
+        assert!(accepts::<[Vec3; 4]>(gl::FLOAT_VEC3));
+        assert!(!accepts::<[Vec3; 4]>(gl::FLOAT_VEC4));
+        assert!(accepts::<Vec<Mat4>>(gl::FLOAT_MAT4));
+        assert!(!accepts::<Vec<Mat4>>(gl::FLOAT));
+        assert!(accepts::<Vec<TextureUnit>>(gl::SAMPLER_2D));
+        assert!(!accepts::<Vec<TextureUnit>>(gl::INT));
+        assert!(accepts::<[GLfloat; 2]>(gl::BOOL));
+    }
+}
@@ -0,0 +1,272 @@
+// Routes the messages of debug contexts (`KHR_debug`, core in OpenGL 4.3) into a sink, so errors
+// the driver would otherwise swallow show up next to the frame they happened in. Objects are labelled
+// with their Rust-side names, and passes wrapped in debug groups, so the messages (and graphics
+// debuggers) can say which mesh or pass they're about.
+
+// OpenGL Types
+use gl::types::*;
+use std::cell::{Cell, RefCell};
+use std::ffi::{c_void, CStr};
+
+// The shortest `GL_MAX_LABEL_LENGTH` allowed, including the null terminator
+const MAX_LABEL_LENGTH: usize = 256;
+
+// What's done with the messages the driver sends
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DebugSink {
+    // Print every message to stderr
+    #[default]
+    Log,
+    // Print every message, and panic at the end of a frame that had an error
+    PanicOnError,
+    // Return a frame's errors from `render()`, and print everything else
+    Collect,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DebugSeverity {
+    Notification,
+    Low,
+    Medium,
+    High,
+}
+
+#[derive(Debug, Clone)]
+pub struct DebugMessage {
+    // What sent the message, e.g. "shader compiler"
+    pub source: &'static str,
+    // What it's about, e.g. "undefined behavior"
+    pub kind: &'static str,
+    pub id: GLuint,
+    pub severity: DebugSeverity,
+    pub message: String,
+}
+
+impl DebugMessage {
+    pub fn is_error(&self) -> bool {
+        self.kind == "error" || self.severity == DebugSeverity::High
+    }
+}
+
+impl std::fmt::Display for DebugMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "[{:?} {} from {}, id {}] {}",
+            self.severity, self.kind, self.source, self.id, self.message
+        )
+    }
+}
+
+// Shared with the driver, which passes it back to `callback()`. Boxed by the window so it doesn't
+// move while the context can still call back.
+#[derive(Debug, Default)]
+pub(crate) struct DebugOutput {
+    sink: Cell<DebugSink>,
+    // Errors since the last time they were checked
+    errors: RefCell<Vec<DebugMessage>>,
+}
+
+impl DebugOutput {
+    pub(crate) fn set_sink(&self, sink: DebugSink) {
+        self.sink.set(sink);
+    }
+
+    // Installs the callback if the current context is a debug context
+    pub(crate) fn enable(&self) {
+        let mut flags = 0;
+        unsafe {
+            gl::GetIntegerv(gl::CONTEXT_FLAGS, &mut flags);
+        }
+        if flags as GLenum & gl::CONTEXT_FLAG_DEBUG_BIT == 0
+            || !gl::DebugMessageCallback::is_loaded()
+        {
+            return;
+        }
+        unsafe {
+            gl::Enable(gl::DEBUG_OUTPUT);
+            // Call back from the function that caused the message, not some later one
+            gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+            // Notifications (e.g. "buffer will use video memory") are far too chatty
+            gl::DebugMessageControl(
+                gl::DONT_CARE,
+                gl::DONT_CARE,
+                gl::DEBUG_SEVERITY_NOTIFICATION,
+                0,
+                std::ptr::null(),
+                gl::FALSE,
+            );
+            gl::DebugMessageCallback(Some(callback), self as *const DebugOutput as *const c_void);
+        }
+    }
+
+    fn receive(&self, message: DebugMessage) {
+        let sink = self.sink.get();
+        if message.is_error() && sink != DebugSink::Log {
+            if sink == DebugSink::PanicOnError {
+                eprintln!("{}", message);
+            }
+            // The callback may be re-entered by a GL call made while the errors are borrowed
+            if let Ok(mut errors) = self.errors.try_borrow_mut() {
+                errors.push(message);
+            }
+        } else {
+            eprintln!("{}", message);
+        }
+    }
+
+    // Takes the errors since the last check. Panics instead, if that's what the sink is for.
+    pub(crate) fn take_errors(&self) -> Vec<DebugMessage> {
+        let errors = std::mem::take(&mut *self.errors.borrow_mut());
+        if self.sink.get() == DebugSink::PanicOnError && !errors.is_empty() {
+            panic!("OpenGL reported {} error(s), see above", errors.len());
+        }
+        errors
+    }
+}
+
+// Unwinding out of the callback would abort, so the messages are only recorded here, and panicked
+// on once control is back in Rust
+extern "system" fn callback(
+    source: GLenum,
+    kind: GLenum,
+    id: GLuint,
+    severity: GLenum,
+    length: GLsizei,
+    message: *const GLchar,
+    output: *mut c_void,
+) {
+    if output.is_null() || message.is_null() {
+        return;
+    }
+    let output = unsafe { &*(output as *const DebugOutput) };
+    // Some drivers don't pass the length, but do null terminate
+    let message = match length < 0 {
+        true => unsafe { CStr::from_ptr(message) }.to_string_lossy(),
+        false => String::from_utf8_lossy(unsafe {
+            std::slice::from_raw_parts(message as *const u8, length as usize)
+        }),
+    };
+    output.receive(DebugMessage {
+        source: source_name(source),
+        kind: kind_name(kind),
+        id,
+        severity: match severity {
+            gl::DEBUG_SEVERITY_HIGH => DebugSeverity::High,
+            gl::DEBUG_SEVERITY_MEDIUM => DebugSeverity::Medium,
+            gl::DEBUG_SEVERITY_LOW => DebugSeverity::Low,
+            _ => DebugSeverity::Notification,
+        },
+        message: message.trim_end().to_string(),
+    });
+}
+
+fn source_name(source: GLenum) -> &'static str {
+    match source {
+        gl::DEBUG_SOURCE_API => "api",
+        gl::DEBUG_SOURCE_WINDOW_SYSTEM => "window system",
+        gl::DEBUG_SOURCE_SHADER_COMPILER => "shader compiler",
+        gl::DEBUG_SOURCE_THIRD_PARTY => "third party",
+        gl::DEBUG_SOURCE_APPLICATION => "application",
+        _ => "other",
+    }
+}
+
+fn kind_name(kind: GLenum) -> &'static str {
+    match kind {
+        gl::DEBUG_TYPE_ERROR => "error",
+        gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "deprecated behavior",
+        gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => "undefined behavior",
+        gl::DEBUG_TYPE_PORTABILITY => "portability",
+        gl::DEBUG_TYPE_PERFORMANCE => "performance",
+        gl::DEBUG_TYPE_MARKER => "marker",
+        gl::DEBUG_TYPE_PUSH_GROUP => "push group",
+        gl::DEBUG_TYPE_POP_GROUP => "pop group",
+        _ => "other",
+    }
+}
+
+// Labels longer than the driver allows are an error, so they're cut short
+pub(crate) fn label(identifier: GLenum, id: GLuint, name: &str) {
+    if !gl::ObjectLabel::is_loaded() {
+        return;
+    }
+    let name = truncate(name);
+    unsafe {
+        gl::ObjectLabel(
+            identifier,
+            id,
+            name.len() as GLsizei,
+            name.as_ptr() as *const GLchar,
+        );
+    }
+}
+
+fn truncate(name: &str) -> &str {
+    let mut end = name.len().min(MAX_LABEL_LENGTH - 1);
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    &name[..end]
+}
+
+// Groups the commands issued while it's alive under `name` in graphics debuggers
+pub(crate) struct DebugGroup {
+    pushed: bool,
+}
+
+impl DebugGroup {
+    pub(crate) fn push(name: &str) -> Self {
+        let pushed = gl::PushDebugGroup::is_loaded();
+        if pushed {
+            let name = truncate(name);
+            unsafe {
+                gl::PushDebugGroup(
+                    gl::DEBUG_SOURCE_APPLICATION,
+                    0,
+                    name.len() as GLsizei,
+                    name.as_ptr() as *const GLchar,
+                );
+            }
+        }
+        DebugGroup { pushed }
+    }
+}
+
+impl Drop for DebugGroup {
+    fn drop(&mut self) {
+        if self.pushed {
+            unsafe {
+                gl::PopDebugGroup();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_are_kept_by_sink() {
+        let message = |kind, severity| DebugMessage {
+            source: "api",
+            kind,
+            id: 0,
+            severity,
+            message: String::new(),
+        };
+        let output = DebugOutput::default();
+        output.receive(message("error", DebugSeverity::Low));
+        assert!(output.take_errors().is_empty());
+
+        output.set_sink(DebugSink::Collect);
+        output.receive(message("error", DebugSeverity::Low));
+        output.receive(message("performance", DebugSeverity::High));
+        output.receive(message("performance", DebugSeverity::Medium));
+        assert_eq!(output.take_errors().len(), 2);
+        assert!(output.take_errors().is_empty());
+
+        assert_eq!(truncate(&"é".repeat(200)).len(), 254);
+    }
+}
@@ -2,6 +2,8 @@
 pub enum WindowError {
     FailedToInitializeGLFW(glfw::InitError),
     FailedToCreateWindow,
+    // Errors reported by the debug context during a frame
+    DebugOutput(Vec<super::DebugMessage>),
 }
 
 impl std::error::Error for WindowError {
@@ -21,6 +23,12 @@ impl std::fmt::Display for WindowError {
             WindowError::FailedToCreateWindow => {
                 write!(f, "Failed to create a window with an OpenGL context.")
             }
+            WindowError::DebugOutput(errors) => {
+                write!(f, "OpenGL reported {} error(s):", errors.len())?;
+                errors
+                    .iter()
+                    .try_for_each(|error| write!(f, "\n\t{}", error))
+            }
         }
     }
 }
@@ -1,12 +1,19 @@
 use std::collections::VecDeque;
 
 // Import our Error Type
+use crate::interface_blocks::BindingPoints;
 pub use crate::program::camera::CameraEvent;
+use crate::shader::{variants::Variants, ProgramBinaryCache};
 use crate::{program::camera::Direction, GLError};
 use glfw::{Action, Key};
 use ultraviolet::vec::Vec3;
 pub mod error;
 pub use error::WindowError;
+pub mod debug;
+pub use debug::{DebugMessage, DebugSeverity, DebugSink};
+pub(crate) mod object;
+use debug::DebugOutput;
+use object::ContextHandle;
 
 // GLFW - 'Context' trait needed for the 'create_window()' function
 use glfw::Context;
@@ -35,10 +42,20 @@ type WindowGLFWEvents = glfw::GlfwReceiver<(f64, glfw::WindowEvent)>;
 // Main struct
 #[derive(Debug)]
 pub struct GLWindow {
+    // Dropped first, so objects outliving the window know its context is gone
+    alive: std::rc::Rc<()>,
     pub(crate) glfw: GLFW,
     pub(crate) window: Window,
     pub(crate) events: WindowGLFWEvents,
     pub(crate) frame_state: FrameState,
+    // Binding points of the context's uniform and storage buffers
+    pub(crate) binding_points: BindingPoints,
+    // Shader variants compiled, and programs linked, in the context
+    pub(crate) variants: Variants,
+    // Where the built-in programs linked in the context are cached, if anywhere
+    pub(crate) program_binaries: Option<ProgramBinaryCache>,
+    // Where the debug context's messages go. Dropped after the window, which may call back into it.
+    debug: Box<DebugOutput>,
 }
 
 impl GLWindow {
@@ -68,6 +85,8 @@ impl GLWindow {
                 glfw.window_hint(glfw::WindowHint::OpenGlForwardCompat(true));
                 // Anti-Aliasing
                 glfw.window_hint(glfw::WindowHint::Samples(Some(4)));
+                // Report errors and warnings through `KHR_debug` in debug builds
+                glfw.window_hint(glfw::WindowHint::OpenGlDebugContext(cfg!(debug_assertions)));
                 Ok(glfw)
             })
             .map_err(|glfw_init_error| WindowError::FailedToInitializeGLFW(glfw_init_error))
@@ -87,11 +106,18 @@ impl GLWindow {
                 window.set_mouse_button_polling(true);
                 window.set_framebuffer_size_polling(true);
                 let frame_state = FrameState::new(&glfw);
+                let alive = std::rc::Rc::new(());
+                ContextHandle::new(&alive, window.window_ptr()).make_current();
                 Ok(GLWindow {
+                    alive,
                     glfw,
                     window,
                     events,
                     frame_state,
+                    binding_points: BindingPoints::new(),
+                    variants: Variants::default(),
+                    program_binaries: None,
+                    debug: Box::default(),
                 })
             })
     }
@@ -107,6 +133,45 @@ impl GLWindow {
         )
     }
 
+    // Caches the built-in programs' binaries in `directory`, so later runs on the same driver can
+    // skip compiling and linking them
+    pub fn with_program_binary_cache<P>(mut self, directory: P) -> GLWindow
+    where
+        P: AsRef<std::path::Path>,
+    {
+        self.program_binaries = Some(ProgramBinaryCache::new(directory));
+        self
+    }
+
+    // Chooses what's done with the debug context's messages, which are logged by default
+    pub fn with_debug_sink(self, sink: DebugSink) -> GLWindow {
+        self.debug.set_sink(sink);
+        self
+    }
+
+    // Errors the debug context reported since the last check, if they're being collected. Panics
+    // if the sink is `DebugSink::PanicOnError` and there were any.
+    pub fn debug_errors(&self) -> Result<()> {
+        let errors = self.debug.take_errors();
+        match errors.is_empty() {
+            true => Ok(()),
+            false => Err(WindowError::DebugOutput(errors)),
+        }
+    }
+
+    // Makes the window's context current on this thread, which objects created from now on belong to
+    pub(crate) fn make_current(&mut self) {
+        self.window.make_current();
+        ContextHandle::new(&self.alive, self.window.window_ptr()).make_current();
+    }
+
+    // Makes the context current, loads the OpenGL functions from it, and listens to its debug output
+    pub(crate) fn load_gl(&mut self) {
+        self.make_current();
+        gl::load_with(|symbol| self.window.get_proc_address(symbol) as *const _);
+        self.debug.enable();
+    }
+
     // Used in the render loop to set the FrameState
     pub fn process_events(&mut self) -> () {
         // Get Updated Time
@@ -0,0 +1,170 @@
+// OpenGL objects are deleted when whatever owns them is dropped, in the context they were created
+// in. Destroying a context deletes everything created in it, so an object dropped after its window
+// is simply forgotten. Objects must be created once a GLWindow has made its context current: one
+// created without a context (which debug builds assert against) is never deleted.
+use glfw::ffi::GLFWwindow;
+
+// OpenGL Types
+use gl::types::*;
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+thread_local! {
+    // The context last made current by a GLWindow, which new objects belong to
+    static CURRENT: RefCell<Option<ContextHandle>> = const { RefCell::new(None) };
+}
+
+// Refers to a window's context without keeping it alive
+#[derive(Debug, Clone)]
+pub(crate) struct ContextHandle {
+    // Upgradable for as long as the window exists
+    alive: Weak<()>,
+    window: *mut GLFWwindow,
+}
+
+impl ContextHandle {
+    pub(crate) fn new(alive: &Rc<()>, window: *mut GLFWwindow) -> Self {
+        ContextHandle {
+            alive: Rc::downgrade(alive),
+            window,
+        }
+    }
+
+    // Objects created from now on belong to this context
+    pub(crate) fn make_current(self) {
+        CURRENT.with(|current| *current.borrow_mut() = Some(self));
+    }
+
+    pub(crate) fn current() -> Option<Self> {
+        CURRENT.with(|current| current.borrow().clone())
+    }
+
+    // Runs `f` with this context current (restoring whichever was before), unless it's been
+    // destroyed
+    pub(crate) fn with<F>(&self, f: F)
+    where
+        F: FnOnce(),
+    {
+        if self.alive.strong_count() == 0 {
+            return;
+        }
+        unsafe {
+            let previous = glfw::ffi::glfwGetCurrentContext();
+            if previous != self.window {
+                glfw::ffi::glfwMakeContextCurrent(self.window);
+            }
+            f();
+            if previous != self.window {
+                glfw::ffi::glfwMakeContextCurrent(previous);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ObjectKind {
+    Buffer,
+    VertexArray,
+    Texture,
+    Framebuffer,
+    Program,
+    Shader,
+}
+
+// Owns an OpenGL object, and deletes it when dropped
+#[derive(Debug)]
+pub(crate) struct GLObject {
+    id: GLuint,
+    kind: ObjectKind,
+    // None if it was created before any window was, in which case it's leaked
+    context: Option<ContextHandle>,
+}
+
+impl GLObject {
+    // Takes ownership of an object created in the current context
+    pub(crate) fn new(id: GLuint, kind: ObjectKind) -> Self {
+        let context = ContextHandle::current();
+        debug_assert!(
+            context.is_some(),
+            "OpenGL object created before any GLWindow made its context current"
+        );
+        GLObject { id, kind, context }
+    }
+
+    pub(crate) fn buffer() -> Self {
+        let mut id = 0;
+        unsafe {
+            gl::GenBuffers(1, &mut id);
+        }
+        GLObject::new(id, ObjectKind::Buffer)
+    }
+
+    pub(crate) fn vertex_array() -> Self {
+        let mut id = 0;
+        unsafe {
+            gl::GenVertexArrays(1, &mut id);
+        }
+        GLObject::new(id, ObjectKind::VertexArray)
+    }
+
+    pub(crate) fn texture() -> Self {
+        let mut id = 0;
+        unsafe {
+            gl::GenTextures(1, &mut id);
+        }
+        GLObject::new(id, ObjectKind::Texture)
+    }
+
+    pub(crate) fn framebuffer() -> Self {
+        let mut id = 0;
+        unsafe {
+            gl::GenFramebuffers(1, &mut id);
+        }
+        GLObject::new(id, ObjectKind::Framebuffer)
+    }
+
+    pub(crate) fn program() -> Self {
+        GLObject::new(unsafe { gl::CreateProgram() }, ObjectKind::Program)
+    }
+
+    pub(crate) fn id(&self) -> GLuint {
+        self.id
+    }
+
+    // Names the object in debug messages and graphics debuggers
+    pub(crate) fn label(&self, name: &str) {
+        let identifier = match self.kind {
+            ObjectKind::Buffer => gl::BUFFER,
+            ObjectKind::VertexArray => gl::VERTEX_ARRAY,
+            ObjectKind::Texture => gl::TEXTURE,
+            ObjectKind::Framebuffer => gl::FRAMEBUFFER,
+            ObjectKind::Program => gl::PROGRAM,
+            ObjectKind::Shader => gl::SHADER,
+        };
+        super::debug::label(identifier, self.id, name);
+    }
+
+    // The context the object belongs to, e.g. to delete related objects in
+    pub(crate) fn context(&self) -> Option<&ContextHandle> {
+        self.context.as_ref()
+    }
+}
+
+impl Drop for GLObject {
+    fn drop(&mut self) {
+        let Some(context) = self.context.as_ref() else {
+            return;
+        };
+        let id = self.id;
+        context.with(|| unsafe {
+            match self.kind {
+                ObjectKind::Buffer => gl::DeleteBuffers(1, &id),
+                ObjectKind::VertexArray => gl::DeleteVertexArrays(1, &id),
+                ObjectKind::Texture => gl::DeleteTextures(1, &id),
+                ObjectKind::Framebuffer => gl::DeleteFramebuffers(1, &id),
+                ObjectKind::Program => gl::DeleteProgram(id),
+                ObjectKind::Shader => gl::DeleteShader(id),
+            }
+        });
+    }
+}